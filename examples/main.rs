@@ -35,7 +35,7 @@ fn main() {
 
     let parsed = match Parser::parse(expression) {
         Ok(parsed) => parsed,
-        Err(e) => panic!("Parsing failed: {}", e.to_string()),
+        Err(e) => panic!("Parsing failed: {}", e),
     };
 
     println!("Parsed: {:?}", parsed);
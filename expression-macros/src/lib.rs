@@ -0,0 +1,303 @@
+use expression::{
+    Expression, Parser,
+    expression::{BitwiseOperator, Computed, Literal, Operation, Operator},
+    schema::Value,
+};
+use proc_macro::{Delimiter, Span, TokenStream, TokenTree};
+
+// Parses an expression at compile time and expands to the code constructing it,
+// so syntax errors become compile errors. The expression is written either
+// directly, `expr!((age > 25 and name == "John"))`, or as a string literal,
+// `expr!(r"/\d+/ IN name")`, for syntax that isn't valid Rust tokens such as
+// regexes with escapes.
+#[proc_macro]
+pub fn expr(input: TokenStream) -> TokenStream {
+    let source = match string_literal(&input) {
+        Some(source) => source,
+        None => {
+            let mut source = String::new();
+            let mut last_end = None;
+            write_source(input, &mut source, &mut last_end);
+
+            source
+        }
+    };
+
+    let code = match Parser::parse(&source) {
+        Ok(expression) => expression_code(&expression),
+        Err(e) => format!(
+            "compile_error!({:?})",
+            format!("invalid expression `{}`: {}", source, e)
+        ),
+    };
+
+    code.parse().unwrap()
+}
+
+// Rebuilds the source text from the tokens' original positions, so spacing
+// inside field names and literals is preserved instead of re-rendered.
+fn write_source(input: TokenStream, source: &mut String, last_end: &mut Option<(usize, usize)>) {
+    for token in input {
+        match token {
+            TokenTree::Group(group) => {
+                let (open, close) = match group.delimiter() {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::Brace => ("{", "}"),
+                    Delimiter::None => ("", ""),
+                };
+
+                push_token(open, group.span_open(), source, last_end);
+                write_source(group.stream(), source, last_end);
+                push_token(close, group.span_close(), source, last_end);
+            }
+            token => {
+                let text = token.span().source_text().unwrap_or(token.to_string());
+                push_token(&text, token.span(), source, last_end);
+            }
+        }
+    }
+}
+
+fn push_token(text: &str, span: Span, source: &mut String, last_end: &mut Option<(usize, usize)>) {
+    let start = (span.start().line(), span.start().column());
+    if last_end.is_some_and(|end| end != start) {
+        source.push(' ');
+    }
+
+    source.push_str(text);
+    *last_end = Some((span.end().line(), span.end().column()));
+}
+
+fn string_literal(input: &TokenStream) -> Option<String> {
+    let mut tokens = input.clone().into_iter();
+    let literal = match (tokens.next(), tokens.next()) {
+        (Some(TokenTree::Literal(literal)), None) => literal.to_string(),
+        _ => return None,
+    };
+
+    if let Some(raw) = literal.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        return Some(String::from(&raw[hashes + 1..raw.len() - hashes - 1]));
+    }
+
+    unescape(literal.strip_prefix('"')?.strip_suffix('"')?)
+}
+
+fn unescape(literal: &str) -> Option<String> {
+    let mut out = String::with_capacity(literal.len());
+    let mut chars = literal.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next()? {
+            'n' => out.push('\n'),
+            'r' => out.push('\r'),
+            't' => out.push('\t'),
+            '0' => out.push('\0'),
+            'x' => {
+                let hex = chars.by_ref().take(2).collect::<String>();
+                out.push(u8::from_str_radix(&hex, 16).ok()? as char);
+            }
+            'u' => {
+                let hex = chars
+                    .by_ref()
+                    .skip(1)
+                    .take_while(|c| *c != '}')
+                    .collect::<String>();
+                out.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+            }
+            // A line continuation skips the newline and leading whitespace.
+            '\n' => {
+                let rest = chars.as_str().trim_start();
+                chars = rest.chars();
+            }
+            c => out.push(c),
+        }
+    }
+
+    Some(out)
+}
+
+fn expression_code(expression: &Expression) -> String {
+    match expression {
+        Expression::And(and) => format!(
+            "::expression::Expression::And(::expression::expression::And::new(::std::vec![{}]))",
+            join(and.get_subexpressions().iter().map(expression_code))
+        ),
+        Expression::Or(or) => format!(
+            "::expression::Expression::Or(::expression::expression::Or::new(::std::vec![{}]))",
+            join(or.get_subexpressions().iter().map(expression_code))
+        ),
+        Expression::Not(not) => format!(
+            "::expression::Expression::Not(::expression::expression::Not::new({}))",
+            expression_code(not.get_subexpression())
+        ),
+        Expression::Operation(operation) => operation_code(operation),
+        Expression::Reference(reference) => format!(
+            "::expression::Expression::Reference(::expression::expression::Reference::new({:?}))",
+            reference.get_rule_name()
+        ),
+    }
+}
+
+fn operation_code(operation: &Operation) -> String {
+    let op = match operation.op {
+        Operator::Eq => "Eq",
+        Operator::Ne => "Ne",
+        Operator::Gt => "Gt",
+        Operator::Gte => "Gte",
+        Operator::Lt => "Lt",
+        Operator::Lte => "Lte",
+        Operator::In => "In",
+    };
+
+    format!(
+        "::expression::Expression::Operation(::expression::expression::Operation::new({}, ::expression::expression::Operator::{}, {}))",
+        literal_code(&operation.lhs),
+        op,
+        literal_code(&operation.rhs)
+    )
+}
+
+fn literal_code(literal: &Literal) -> String {
+    match literal {
+        Literal::LiteralValue(value) => format!(
+            "::expression::expression::Literal::LiteralValue({})",
+            value_code(value)
+        ),
+        Literal::LiteralField(field_name) => format!(
+            "::expression::expression::Literal::LiteralField(::expression::expression::FieldName::new_static({:?}))",
+            field_name
+        ),
+        Literal::LiteralAge(field_name) => format!(
+            "::expression::expression::Literal::LiteralAge(::expression::expression::FieldName::new_static({:?}))",
+            field_name
+        ),
+        Literal::LiteralComputed(computed) => format!(
+            "::expression::expression::Literal::LiteralComputed(::std::boxed::Box::new({}))",
+            computed_code(computed)
+        ),
+    }
+}
+
+fn computed_code(computed: &Computed) -> String {
+    match computed {
+        Computed::Bitwise(first, rest) => format!(
+            "::expression::expression::Computed::Bitwise({}, {})",
+            literal_code(first),
+            list_code(rest.iter().map(|(op, operand)| {
+                let op = match op {
+                    BitwiseOperator::And => "And",
+                    BitwiseOperator::Or => "Or",
+                    BitwiseOperator::Xor => "Xor",
+                    BitwiseOperator::ShiftLeft => "ShiftLeft",
+                    BitwiseOperator::ShiftRight => "ShiftRight",
+                };
+
+                format!(
+                    "(::expression::expression::BitwiseOperator::{}, {})",
+                    op,
+                    literal_code(operand)
+                )
+            }))
+        ),
+        Computed::Concat(operands) => format!(
+            "::expression::expression::Computed::Concat({})",
+            list_code(operands.iter().map(literal_code))
+        ),
+        Computed::Coalesce(operands) => format!(
+            "::expression::expression::Computed::Coalesce({})",
+            list_code(operands.iter().map(literal_code))
+        ),
+        Computed::If(condition, then, otherwise) => format!(
+            "::expression::expression::Computed::If({}, {}, {})",
+            expression_code(condition),
+            literal_code(then),
+            literal_code(otherwise)
+        ),
+        Computed::First(list) => format!(
+            "::expression::expression::Computed::First({})",
+            literal_code(list)
+        ),
+        Computed::Last(list) => format!(
+            "::expression::expression::Computed::Last({})",
+            literal_code(list)
+        ),
+        Computed::Slice(list, start, end) => format!(
+            "::expression::expression::Computed::Slice({}, {}, {})",
+            literal_code(list),
+            start,
+            end
+        ),
+    }
+}
+
+fn value_code(value: &Value) -> String {
+    let (variant, code) = match value {
+        Value::String(val) => ("String", string_code(val)),
+        Value::Regex(val) => ("Regex", string_code(val)),
+        Value::Number(val) => ("Number", number_code(*val)),
+        Value::Boolean(val) => ("Boolean", format!("{}", val)),
+        Value::Raw(val) => ("Raw", raw_code(val)),
+        Value::DateTime(val) => (
+            "DateTime",
+            datetime_code(val.timestamp(), val.timestamp_subsec_nanos()),
+        ),
+        Value::StringList(items) => (
+            "StringList",
+            list_code(items.iter().map(|val| string_code(val))),
+        ),
+        Value::NumberList(items) => (
+            "NumberList",
+            list_code(items.iter().map(|val| number_code(*val))),
+        ),
+        Value::BooleanList(items) => (
+            "BooleanList",
+            list_code(items.iter().map(|val| format!("{}", val))),
+        ),
+        Value::RawList(items) => ("RawList", list_code(items.iter().map(|val| raw_code(val)))),
+        Value::DateTimeList(items) => (
+            "DateTimeList",
+            list_code(
+                items
+                    .iter()
+                    .map(|val| datetime_code(val.timestamp(), val.timestamp_subsec_nanos())),
+            ),
+        ),
+        Value::Null => return String::from("::expression::schema::Value::Null"),
+    };
+
+    format!("::expression::schema::Value::{}({})", variant, code)
+}
+
+fn string_code(val: &str) -> String {
+    format!("::std::string::String::from({:?})", val)
+}
+
+// Going through the bit pattern keeps the parsed value exact.
+fn number_code(val: f64) -> String {
+    format!("f64::from_bits({:#x})", val.to_bits())
+}
+
+fn raw_code(val: &[u8]) -> String {
+    list_code(val.iter().map(|byte| format!("{}u8", byte)))
+}
+
+fn datetime_code(secs: i64, nanos: u32) -> String {
+    format!(
+        "::expression::chrono::DateTime::from_timestamp({}, {}).unwrap()",
+        secs, nanos
+    )
+}
+
+fn list_code(items: impl Iterator<Item = String>) -> String {
+    format!("::std::vec![{}]", join(items))
+}
+
+fn join(items: impl Iterator<Item = String>) -> String {
+    items.collect::<Vec<String>>().join(", ")
+}
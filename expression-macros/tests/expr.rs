@@ -0,0 +1,30 @@
+use expression::Parser;
+use expression_macros::expr;
+
+#[test]
+fn matches_runtime_parser() {
+    assert_eq!(
+        expr!((age > 25 and name == "John" and user:role IN ["admin", "owner"])),
+        Parser::parse(r#"(age > 25 AND name == "John" AND user:role IN ["admin", "owner"])"#)
+            .unwrap()
+    );
+    assert_eq!(
+        expr!(!((score <= -1.5e3 or flag == true or deleted == null))),
+        Parser::parse("!((score <= -1.5e3 OR flag == true OR deleted == null))").unwrap()
+    );
+}
+
+#[test]
+fn accepts_string_literals() {
+    assert_eq!(
+        expr!(r#"(/\d+/ IN name and created IN [2020-01-01T00:00:00Z, 2021-01-01T00:00:00.5Z])"#),
+        Parser::parse(
+            r#"(/\d+/ IN name AND created IN [2020-01-01T00:00:00Z, 2021-01-01T00:00:00.5Z])"#
+        )
+        .unwrap()
+    );
+    assert_eq!(
+        expr!("(key == |0a ff| and note == \"a\\tb\")"),
+        Parser::parse(r#"(key == |0a ff| and note == "a\tb")"#).unwrap()
+    );
+}
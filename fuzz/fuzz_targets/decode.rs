@@ -0,0 +1,15 @@
+#![no_main]
+
+use expression::Expression;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &[u8]| {
+    // Untrusted bytes may be rejected but must not panic or overflow the stack.
+    // Whatever decodes must survive a round trip through encoding.
+    if let Ok(expression) = Expression::from_bytes(input) {
+        assert_eq!(
+            Expression::from_bytes(&expression.to_bytes()).ok(),
+            Some(expression)
+        );
+    }
+});
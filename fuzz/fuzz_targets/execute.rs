@@ -0,0 +1,216 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use chrono::{DateTime, Utc};
+use expression::{
+    Engine, Expression, SchemaBuilder,
+    expression::{
+        And, BitwiseOperator, Computed, FieldName, Literal, Not, Operation, Operator, Or,
+    },
+    schema::{Schema, Value},
+};
+use libfuzzer_sys::fuzz_target;
+
+// Mirrors of the AST that `arbitrary` can generate. Values aren't checked
+// against the grammar, so regexes may not compile and date ranges may have any
+// length, as with ASTs built in code.
+#[derive(Arbitrary, Debug)]
+enum Node {
+    And(Vec<Node>),
+    Or(Vec<Node>),
+    Not(Box<Node>),
+    Operation(Operand, Op, Operand),
+}
+
+#[derive(Arbitrary, Debug)]
+enum Operand {
+    Field(u8),
+    Age(u8),
+    Value(ValueInput),
+    Bitwise(Box<Operand>, Vec<(BitOp, Operand)>),
+    Concat(Vec<Operand>),
+    Coalesce(Vec<Operand>),
+    If(Box<Node>, Box<Operand>, Box<Operand>),
+    First(Box<Operand>),
+    Last(Box<Operand>),
+    Slice(Box<Operand>, usize, usize),
+}
+
+#[derive(Arbitrary, Debug)]
+enum BitOp {
+    And,
+    Or,
+    Xor,
+    ShiftLeft,
+    ShiftRight,
+}
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    In,
+}
+
+#[derive(Arbitrary, Debug)]
+enum ValueInput {
+    String(String),
+    Regex(String),
+    Number(f64),
+    Boolean(bool),
+    Raw(Vec<u8>),
+    DateTime(i64, u32),
+    StringList(Vec<String>),
+    NumberList(Vec<f64>),
+    BooleanList(Vec<bool>),
+    RawList(Vec<Vec<u8>>),
+    DateTimeList(Vec<(i64, u32)>),
+    Null,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Record {
+    string: Option<String>,
+    number: Option<f64>,
+    boolean: Option<bool>,
+    raw: Option<Vec<u8>>,
+    datetime: Option<(i64, u32)>,
+    string_list: Option<Vec<String>>,
+    number_list: Option<Vec<f64>>,
+    boolean_list: Option<Vec<bool>>,
+    raw_list: Option<Vec<Vec<u8>>>,
+    datetime_list: Option<Vec<(i64, u32)>>,
+}
+
+// The last name isn't in the schema.
+const FIELDS: [&str; 11] = [
+    "string",
+    "number",
+    "boolean",
+    "raw",
+    "datetime",
+    "string_list",
+    "number_list",
+    "boolean_list",
+    "raw_list",
+    "datetime_list",
+    "missing",
+];
+
+fn datetime((secs, nanos): (i64, u32)) -> DateTime<Utc> {
+    DateTime::from_timestamp(secs, nanos).unwrap_or_default()
+}
+
+fn schema() -> Schema<Record> {
+    SchemaBuilder::new()
+        .with_string_field("string", |r: &Record| r.string.clone())
+        .with_number_field("number", |r| r.number)
+        .with_boolean_field("boolean", |r| r.boolean)
+        .with_raw_field("raw", |r| r.raw.clone())
+        .with_datetime_field("datetime", |r| r.datetime.map(datetime))
+        .with_string_list_field("string_list", |r| r.string_list.clone())
+        .with_number_list_field("number_list", |r| r.number_list.clone())
+        .with_boolean_list_field("boolean_list", |r| r.boolean_list.clone())
+        .with_raw_list_field("raw_list", |r| r.raw_list.clone())
+        .with_datetime_list_field("datetime_list", |r| {
+            r.datetime_list
+                .as_ref()
+                .map(|items| items.iter().copied().map(datetime).collect())
+        })
+        .build()
+}
+
+fn expression(node: Node) -> Expression {
+    match node {
+        Node::And(nodes) => Expression::And(And::new(nodes.into_iter().map(expression).collect())),
+        Node::Or(nodes) => Expression::Or(Or::new(nodes.into_iter().map(expression).collect())),
+        Node::Not(node) => Expression::Not(Not::new(expression(*node))),
+        Node::Operation(lhs, op, rhs) => {
+            let op = match op {
+                Op::Eq => Operator::Eq,
+                Op::Ne => Operator::Ne,
+                Op::Gt => Operator::Gt,
+                Op::Gte => Operator::Gte,
+                Op::Lt => Operator::Lt,
+                Op::Lte => Operator::Lte,
+                Op::In => Operator::In,
+            };
+
+            Expression::Operation(Operation::new(literal(lhs), op, literal(rhs)))
+        }
+    }
+}
+
+fn literal(operand: Operand) -> Literal {
+    match operand {
+        Operand::Field(i) => {
+            Literal::LiteralField(FieldName::new_static(FIELDS[i as usize % FIELDS.len()]))
+        }
+        Operand::Age(i) => {
+            Literal::LiteralAge(FieldName::new_static(FIELDS[i as usize % FIELDS.len()]))
+        }
+        Operand::Value(value) => Literal::LiteralValue(match value {
+            ValueInput::String(val) => Value::String(val),
+            ValueInput::Regex(val) => Value::Regex(val),
+            ValueInput::Number(val) => Value::Number(val),
+            ValueInput::Boolean(val) => Value::Boolean(val),
+            ValueInput::Raw(val) => Value::Raw(val),
+            ValueInput::DateTime(secs, nanos) => Value::DateTime(datetime((secs, nanos))),
+            ValueInput::StringList(items) => Value::StringList(items),
+            ValueInput::NumberList(items) => Value::NumberList(items),
+            ValueInput::BooleanList(items) => Value::BooleanList(items),
+            ValueInput::RawList(items) => Value::RawList(items),
+            ValueInput::DateTimeList(items) => {
+                Value::DateTimeList(items.into_iter().map(datetime).collect())
+            }
+            ValueInput::Null => Value::Null,
+        }),
+        Operand::Bitwise(first, rest) => {
+            let rest = rest
+                .into_iter()
+                .map(|(op, operand)| {
+                    let op = match op {
+                        BitOp::And => BitwiseOperator::And,
+                        BitOp::Or => BitwiseOperator::Or,
+                        BitOp::Xor => BitwiseOperator::Xor,
+                        BitOp::ShiftLeft => BitwiseOperator::ShiftLeft,
+                        BitOp::ShiftRight => BitwiseOperator::ShiftRight,
+                    };
+
+                    (op, literal(operand))
+                })
+                .collect();
+
+            Literal::LiteralComputed(Box::new(Computed::Bitwise(literal(*first), rest)))
+        }
+        Operand::Concat(operands) => Literal::LiteralComputed(Box::new(Computed::Concat(
+            operands.into_iter().map(literal).collect(),
+        ))),
+        Operand::Coalesce(operands) => Literal::LiteralComputed(Box::new(Computed::Coalesce(
+            operands.into_iter().map(literal).collect(),
+        ))),
+        Operand::If(condition, then, otherwise) => Literal::LiteralComputed(Box::new(
+            Computed::If(expression(*condition), literal(*then), literal(*otherwise)),
+        )),
+        Operand::First(list) => Literal::LiteralComputed(Box::new(Computed::First(literal(*list)))),
+        Operand::Last(list) => Literal::LiteralComputed(Box::new(Computed::Last(literal(*list)))),
+        Operand::Slice(list, start, end) => {
+            Literal::LiteralComputed(Box::new(Computed::Slice(literal(*list), start, end)))
+        }
+    }
+}
+
+fuzz_target!(|input: (Node, Record)| {
+    let (node, record) = input;
+    let expression = expression(node);
+    let engine = Engine::new(schema());
+
+    // Errors are fine, only panics and crashes are findings. Execution doesn't
+    // require validation, so both run on every input.
+    let _ = engine.validate(&expression);
+    let _ = engine.execute(&expression, &record);
+});
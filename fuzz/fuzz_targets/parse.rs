@@ -0,0 +1,17 @@
+#![no_main]
+
+use expression::Parser;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    // Whatever parses must survive a round trip through serialization.
+    if let Ok(expression) = Parser::parse(input) {
+        let serialized = expression.serialize();
+        assert_eq!(
+            Parser::parse(&serialized).ok(),
+            Some(expression),
+            "{}",
+            serialized
+        );
+    }
+});
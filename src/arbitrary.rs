@@ -0,0 +1,298 @@
+use chrono::{DateTime, Utc};
+use proptest::prelude::*;
+
+use crate::{
+    descriptor::{FieldDescriptor, SchemaDescriptor},
+    expression::{And, Expression, Literal, Not, Operation, Operator, Or},
+    schema::{Type, Value},
+};
+
+// Generated fields are named after their type, e.g. `number_1` or
+// `string_list_0`, so a field always has the same type and every generated
+// operation passes validation against `descriptor()`.
+const FIELDS_PER_TYPE: usize = 3;
+
+const FIELD_TYPES: [(Type, &str); 10] = [
+    (Type::String, "string"),
+    (Type::Number, "number"),
+    (Type::Boolean, "boolean"),
+    (Type::Raw, "raw"),
+    (Type::DateTime, "datetime"),
+    (Type::StringList, "string_list"),
+    (Type::NumberList, "number_list"),
+    (Type::BooleanList, "boolean_list"),
+    (Type::RawList, "raw_list"),
+    (Type::DateTimeList, "datetime_list"),
+];
+
+pub fn descriptor() -> SchemaDescriptor {
+    SchemaDescriptor {
+        fields: FIELD_TYPES
+            .iter()
+            .flat_map(|(field_type, prefix)| {
+                (0..FIELDS_PER_TYPE).map(move |i| FieldDescriptor {
+                    name: format!("{}_{}", prefix, i),
+                    field_type: *field_type,
+                    description: None,
+                })
+            })
+            .collect(),
+    }
+}
+
+fn field(field_type: Type) -> BoxedStrategy<Literal> {
+    let (_, prefix) = FIELD_TYPES
+        .iter()
+        .find(|(t, _)| *t == field_type)
+        .expect("no generated fields of this type");
+
+    let field = (0..FIELDS_PER_TYPE)
+        .prop_map(move |i| Literal::LiteralField(format!("{}_{}", prefix, i).into()));
+
+    // Ages of datetime fields stand in for number fields.
+    match field_type {
+        Type::Number => prop_oneof![
+            3 => field,
+            1 => (0..FIELDS_PER_TYPE)
+                .prop_map(|i| Literal::LiteralAge(format!("datetime_{}", i).into())),
+        ]
+        .boxed(),
+        _ => field.boxed(),
+    }
+}
+
+fn number() -> impl Strategy<Value = f64> {
+    prop_oneof![
+        any::<i32>().prop_map(f64::from),
+        any::<f64>().prop_filter("finite", |num| num.is_finite()),
+    ]
+}
+
+fn raw() -> impl Strategy<Value = Vec<u8>> {
+    prop::collection::vec(any::<u8>(), 1..8)
+}
+
+// Patterns always compile, as the engine expects valid regexes.
+fn regex() -> impl Strategy<Value = String> {
+    "\\^?[a-z0-9 ]{0,6}\\$?"
+}
+
+fn datetime() -> impl Strategy<Value = DateTime<Utc>> {
+    (0i64..4_102_444_800, 0u32..1_000_000_000)
+        .prop_map(|(secs, nanos)| DateTime::from_timestamp(secs, nanos).unwrap())
+}
+
+fn value(value_type: Type) -> BoxedStrategy<Value> {
+    match value_type {
+        Type::String => any::<String>().prop_map(Value::String).boxed(),
+        Type::Regex => regex().prop_map(Value::Regex).boxed(),
+        Type::Number => number().prop_map(Value::Number).boxed(),
+        Type::Boolean => any::<bool>().prop_map(Value::Boolean).boxed(),
+        Type::Raw => raw().prop_map(Value::Raw).boxed(),
+        Type::DateTime => datetime().prop_map(Value::DateTime).boxed(),
+        Type::StringList => prop::collection::vec(any::<String>(), 1..4)
+            .prop_map(Value::StringList)
+            .boxed(),
+        Type::NumberList => prop::collection::vec(number(), 1..4)
+            .prop_map(Value::NumberList)
+            .boxed(),
+        Type::BooleanList => prop::collection::vec(any::<bool>(), 1..4)
+            .prop_map(Value::BooleanList)
+            .boxed(),
+        Type::RawList => prop::collection::vec(raw(), 1..4)
+            .prop_map(Value::RawList)
+            .boxed(),
+        Type::DateTimeList => prop::collection::vec(datetime(), 1..4)
+            .prop_map(Value::DateTimeList)
+            .boxed(),
+        Type::Null => Just(Value::Null).boxed(),
+    }
+}
+
+fn literal_value(value_type: Type) -> BoxedStrategy<Literal> {
+    value(value_type).prop_map(Literal::LiteralValue).boxed()
+}
+
+fn list_type(scalar: Type) -> Type {
+    match scalar {
+        Type::String => Type::StringList,
+        Type::Number => Type::NumberList,
+        Type::Boolean => Type::BooleanList,
+        Type::Raw => Type::RawList,
+        _ => Type::DateTimeList,
+    }
+}
+
+fn operation(
+    lhs: BoxedStrategy<Literal>,
+    op: Operator,
+    rhs: BoxedStrategy<Literal>,
+) -> BoxedStrategy<Operation> {
+    (lhs, rhs)
+        .prop_map(move |(lhs, rhs)| Operation::new(lhs, op.clone(), rhs))
+        .boxed()
+}
+
+// Comparisons of a field with a value of the same type, in either order.
+fn comparison(field_type: Type, op: Operator) -> BoxedStrategy<Operation> {
+    prop_oneof![
+        operation(field(field_type), op.clone(), literal_value(field_type)),
+        operation(literal_value(field_type), op, field(field_type)),
+    ]
+    .boxed()
+}
+
+fn scalar_operation(scalar: Type) -> BoxedStrategy<Operation> {
+    let list = list_type(scalar);
+    let ops = match scalar {
+        Type::Number | Type::DateTime => vec![
+            Operator::Eq,
+            Operator::Ne,
+            Operator::Gt,
+            Operator::Gte,
+            Operator::Lt,
+            Operator::Lte,
+        ],
+        _ => vec![Operator::Eq, Operator::Ne],
+    };
+
+    let mut strategies = ops
+        .into_iter()
+        .map(|op| comparison(scalar, op))
+        .collect::<Vec<BoxedStrategy<Operation>>>();
+    strategies.push(comparison(list, Operator::Eq));
+    strategies.push(comparison(list, Operator::Ne));
+    strategies.push(operation(
+        field(scalar),
+        Operator::Eq,
+        literal_value(Type::Null),
+    ));
+    strategies.push(operation(
+        field(scalar),
+        Operator::Ne,
+        literal_value(Type::Null),
+    ));
+
+    match scalar {
+        // A datetime is "in" a list of exactly two datetimes, its range.
+        Type::DateTime => strategies.push(operation(
+            field(scalar),
+            Operator::In,
+            (datetime(), datetime())
+                .prop_map(|(a, b)| {
+                    Literal::LiteralValue(Value::DateTimeList(vec![a.min(b), a.max(b)]))
+                })
+                .boxed(),
+        )),
+        _ => {
+            strategies.push(operation(field(scalar), Operator::In, literal_value(list)));
+            strategies.push(operation(literal_value(scalar), Operator::In, field(list)));
+        }
+    }
+
+    match scalar {
+        Type::String => {
+            strategies.push(operation(
+                literal_value(Type::String),
+                Operator::In,
+                field(Type::String),
+            ));
+            strategies.push(operation(
+                literal_value(Type::Regex),
+                Operator::In,
+                field(Type::String),
+            ));
+            strategies.push(operation(
+                literal_value(Type::Regex),
+                Operator::In,
+                field(Type::StringList),
+            ));
+        }
+        Type::Raw => strategies.push(operation(
+            literal_value(Type::Raw),
+            Operator::In,
+            field(Type::Raw),
+        )),
+        _ => {}
+    }
+
+    prop::strategy::Union::new(strategies).boxed()
+}
+
+impl Arbitrary for Value {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Value>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop::strategy::Union::new(
+            [
+                Type::String,
+                Type::Regex,
+                Type::Number,
+                Type::Boolean,
+                Type::Raw,
+                Type::DateTime,
+                Type::StringList,
+                Type::NumberList,
+                Type::BooleanList,
+                Type::RawList,
+                Type::DateTimeList,
+                Type::Null,
+            ]
+            .map(value),
+        )
+        .boxed()
+    }
+}
+
+impl Arbitrary for Literal {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Literal>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            any::<Value>().prop_map(Literal::LiteralValue),
+            prop::strategy::Union::new(FIELD_TYPES.map(|(field_type, _)| field(field_type))),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for Operation {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Operation>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop::strategy::Union::new(
+            [
+                Type::String,
+                Type::Number,
+                Type::Boolean,
+                Type::Raw,
+                Type::DateTime,
+            ]
+            .map(scalar_operation),
+        )
+        .boxed()
+    }
+}
+
+impl Arbitrary for Expression {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Expression>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        any::<Operation>()
+            .prop_map(Expression::Operation)
+            .prop_recursive(4, 32, 4, |inner| {
+                prop_oneof![
+                    prop::collection::vec(inner.clone(), 2..4)
+                        .prop_map(|e| Expression::And(And::new(e))),
+                    prop::collection::vec(inner.clone(), 2..4)
+                        .prop_map(|e| Expression::Or(Or::new(e))),
+                    inner.prop_map(|e| Expression::Not(Not::new(e))),
+                ]
+            })
+            .boxed()
+    }
+}
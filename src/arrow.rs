@@ -0,0 +1,484 @@
+use std::{collections::HashSet, sync::Arc};
+
+use arrow_arith::boolean::{and, is_not_null, is_null, not, or};
+use arrow_array::{
+    Array, ArrayRef, BinaryArray, BooleanArray, RecordBatch, Scalar, StringArray,
+    cast::AsArray,
+    types::{Float64Type, TimestampNanosecondType},
+};
+use arrow_buffer::{BooleanBuffer, NullBuffer};
+use arrow_cast::cast;
+use arrow_ord::cmp;
+use arrow_schema::{ArrowError, DataType, TimeUnit};
+use arrow_string::like;
+use thiserror::Error;
+
+use crate::{
+    engine::{self, ExecutionError, ValidationError, compile_regex},
+    expression::{Expression, Literal, Operation, Operator},
+    schema::{SchemaBuilder, Type, Value},
+    serialize::Serialize,
+    transpile::FieldMapping,
+};
+
+#[derive(Error, Debug)]
+pub enum EvaluateError {
+    #[error("Invalid expression: {0}")]
+    InvalidExpressionError(ValidationError),
+    #[error("The batch has no column '{0}'")]
+    MissingColumnError(String),
+    #[error("Cannot evaluate '{0}' on columns")]
+    UnsupportedOperationError(String),
+    #[error("{0}")]
+    ExecutionError(ExecutionError),
+    #[error("Arrow error: {0}")]
+    ArrowError(ArrowError),
+}
+
+impl EvaluateError {
+    fn unsupported(operation: &Operation) -> Self {
+        EvaluateError::UnsupportedOperationError(Serialize::fmt(operation))
+    }
+}
+
+// Evaluates expressions a column at a time over Arrow record batches, giving
+// the mask of matching rows. Fields are mapped to columns like for the
+// transpilers, and columns are cast to the type of their field, e.g. integer
+// columns to Float64 for number fields. Results are the same as the engine's
+// on the rows: nulls equal each other and nothing else, and other comparisons
+// with a null are false. List columns aren't supported.
+pub struct ArrowEvaluator {
+    mapping: FieldMapping,
+}
+
+enum Operand {
+    Column(ArrayRef),
+    Literal(Value),
+}
+
+impl Operand {
+    fn nulls(&self) -> Option<NullBuffer> {
+        match self {
+            Operand::Column(column) => column.logical_nulls(),
+            Operand::Literal(_) => None,
+        }
+    }
+}
+
+impl ArrowEvaluator {
+    pub fn new(mapping: FieldMapping) -> Self {
+        Self { mapping }
+    }
+
+    pub fn evaluate(
+        &self,
+        expression: &Expression,
+        batch: &RecordBatch,
+    ) -> Result<BooleanArray, EvaluateError> {
+        engine::validate(expression, &|field_name| {
+            self.mapping
+                .get_field(field_name)
+                .map(|field| field.field_type)
+        })
+        .map_err(EvaluateError::InvalidExpressionError)?;
+
+        self.evaluate_at(expression, batch)
+    }
+
+    fn evaluate_at(
+        &self,
+        expression: &Expression,
+        batch: &RecordBatch,
+    ) -> Result<BooleanArray, EvaluateError> {
+        let len = batch.num_rows();
+
+        match expression {
+            Expression::And(and_) => {
+                let mut mask = constant(true, len);
+                for subexpression in and_.get_subexpressions() {
+                    // Later subexpressions can't change a mask without rows.
+                    if mask.true_count() == 0 {
+                        break;
+                    }
+                    let next = self.evaluate_at(subexpression, batch)?;
+                    mask = and(&mask, &next).map_err(EvaluateError::ArrowError)?;
+                }
+
+                Ok(mask)
+            }
+            Expression::Or(or_) => {
+                let mut mask = constant(false, len);
+                for subexpression in or_.get_subexpressions() {
+                    if mask.true_count() == len {
+                        break;
+                    }
+                    let next = self.evaluate_at(subexpression, batch)?;
+                    mask = or(&mask, &next).map_err(EvaluateError::ArrowError)?;
+                }
+
+                Ok(mask)
+            }
+            Expression::Not(not_) => not(&self.evaluate_at(not_.get_subexpression(), batch)?)
+                .map_err(EvaluateError::ArrowError),
+            Expression::Operation(operation) => self.evaluate_operation(operation, batch),
+            // Rejected by validation.
+            Expression::Reference(reference) => Err(EvaluateError::ExecutionError(
+                ExecutionError::UnresolvedReferenceError(reference.get_rule_name().to_string()),
+            )),
+        }
+    }
+
+    fn evaluate_operation(
+        &self,
+        operation: &Operation,
+        batch: &RecordBatch,
+    ) -> Result<BooleanArray, EvaluateError> {
+        let len = batch.num_rows();
+
+        // Without fields the result is the same for every row.
+        if let (Literal::LiteralValue(_), Literal::LiteralValue(_)) =
+            (&operation.lhs, &operation.rhs)
+        {
+            let no_fields = SchemaBuilder::<()>::new().build();
+            let result = engine::execute_operation(operation, &(), &no_fields)
+                .map_err(EvaluateError::ExecutionError)?;
+
+            return Ok(constant(result, len));
+        }
+
+        let lhs_type = self.literal_type(&operation.lhs);
+        let rhs_type = self.literal_type(&operation.rhs);
+        let lhs = self.operand(&operation.lhs, lhs_type, batch, operation)?;
+        let rhs = self.operand(&operation.rhs, rhs_type, batch, operation)?;
+
+        if let (Operand::Column(column), Operand::Literal(Value::Null))
+        | (Operand::Literal(Value::Null), Operand::Column(column)) = (&lhs, &rhs)
+        {
+            return match operation.op {
+                Operator::Eq => is_null(column).map_err(EvaluateError::ArrowError),
+                Operator::Ne => is_not_null(column).map_err(EvaluateError::ArrowError),
+                _ => Ok(constant(false, len)),
+            };
+        }
+
+        let values = match (&operation.op, lhs_type, rhs_type) {
+            (Operator::In, _, _) => {
+                self.contains(operation, &lhs, &rhs, lhs_type, rhs_type, len)?
+            }
+            (op, Type::Number, Type::Number) => {
+                let lhs = numbers(&lhs);
+                let rhs = numbers(&rhs);
+
+                compare(&lhs, &rhs, op, len)
+            }
+            (op, Type::DateTime, Type::DateTime) => {
+                let lhs = timestamps(&lhs, operation)?;
+                let rhs = timestamps(&rhs, operation)?;
+
+                compare(&lhs, &rhs, op, len)
+            }
+            (op, Type::String | Type::Boolean | Type::Raw, _) => {
+                let kernel = match op {
+                    Operator::Eq => cmp::eq,
+                    Operator::Ne => cmp::neq,
+                    _ => return Err(EvaluateError::unsupported(operation)),
+                };
+                let result = match (&lhs, &rhs) {
+                    (Operand::Column(lhs), Operand::Column(rhs)) => kernel(lhs, rhs),
+                    (Operand::Column(column), Operand::Literal(value))
+                    | (Operand::Literal(value), Operand::Column(column)) => {
+                        let value =
+                            scalar(value).ok_or_else(|| EvaluateError::unsupported(operation))?;
+
+                        kernel(column, &value)
+                    }
+                    _ => return Err(EvaluateError::unsupported(operation)),
+                };
+
+                result.map_err(EvaluateError::ArrowError)?.values().clone()
+            }
+            _ => return Err(EvaluateError::unsupported(operation)),
+        };
+
+        Ok(with_nulls(values, lhs.nulls(), rhs.nulls(), &operation.op))
+    }
+
+    // `IN` against a list literal, a datetime range or a string.
+    fn contains(
+        &self,
+        operation: &Operation,
+        lhs: &Operand,
+        rhs: &Operand,
+        lhs_type: Type,
+        rhs_type: Type,
+        len: usize,
+    ) -> Result<BooleanBuffer, EvaluateError> {
+        let unsupported = || EvaluateError::unsupported(operation);
+
+        Ok(match (lhs, rhs) {
+            (Operand::Column(column), Operand::Literal(list)) => match list {
+                Value::StringList(items) => {
+                    let items = items.iter().map(String::as_str).collect::<HashSet<&str>>();
+                    let column = column.as_string::<i32>();
+
+                    BooleanBuffer::collect_bool(len, |i| items.contains(column.value(i)))
+                }
+                Value::NumberList(items) => {
+                    // Like `==`, NaN is in no list and -0 is the same as 0.
+                    let key = |num: f64| if num == 0.0 { 0 } else { num.to_bits() };
+                    let items = items
+                        .iter()
+                        .filter(|num| !num.is_nan())
+                        .map(|num| key(*num))
+                        .collect::<HashSet<u64>>();
+                    let column = column.as_primitive::<Float64Type>();
+
+                    BooleanBuffer::collect_bool(len, |i| {
+                        let num = column.value(i);
+                        !num.is_nan() && items.contains(&key(num))
+                    })
+                }
+                Value::BooleanList(items) => {
+                    let column = column.as_boolean();
+
+                    BooleanBuffer::collect_bool(len, |i| items.contains(&column.value(i)))
+                }
+                Value::RawList(items) => {
+                    let items = items.iter().map(Vec::as_slice).collect::<HashSet<&[u8]>>();
+                    let column = column.as_binary::<i32>();
+
+                    BooleanBuffer::collect_bool(len, |i| items.contains(column.value(i)))
+                }
+                Value::DateTimeList(range) => {
+                    let [from, until] = range.as_slice() else {
+                        return Err(EvaluateError::ExecutionError(
+                            ExecutionError::InvalidDateRangeError,
+                        ));
+                    };
+                    let from = from.timestamp_nanos_opt().ok_or_else(unsupported)?;
+                    let until = until.timestamp_nanos_opt().ok_or_else(unsupported)?;
+                    let column = column.as_primitive::<TimestampNanosecondType>();
+
+                    BooleanBuffer::collect_bool(len, |i| {
+                        let timestamp = column.value(i);
+                        timestamp >= from && timestamp < until
+                    })
+                }
+                Value::String(haystack) if lhs_type == Type::String => {
+                    let column = column.as_string::<i32>();
+
+                    BooleanBuffer::collect_bool(len, |i| haystack.contains(column.value(i)))
+                }
+                _ => return Err(unsupported()),
+            },
+            (Operand::Literal(Value::Regex(pattern)), Operand::Column(column))
+                if rhs_type == Type::String =>
+            {
+                let regex = compile_regex(pattern).map_err(|e| {
+                    EvaluateError::ExecutionError(ExecutionError::InvalidRegexError(e))
+                })?;
+                let column = column.as_string::<i32>();
+
+                BooleanBuffer::collect_bool(len, |i| regex.is_match(column.value(i)))
+            }
+            (Operand::Literal(Value::String(needle)), Operand::Column(column))
+                if rhs_type == Type::String =>
+            {
+                let needle = Scalar::new(StringArray::from(vec![needle.as_str()]));
+
+                like::contains(column, &needle)
+                    .map_err(EvaluateError::ArrowError)?
+                    .values()
+                    .clone()
+            }
+            (Operand::Column(lhs), Operand::Column(rhs))
+                if lhs_type == Type::String && rhs_type == Type::String =>
+            {
+                like::contains(rhs, lhs)
+                    .map_err(EvaluateError::ArrowError)?
+                    .values()
+                    .clone()
+            }
+            _ => return Err(unsupported()),
+        })
+    }
+
+    // Fields are known to be mapped after validation.
+    fn literal_type(&self, literal: &Literal) -> Type {
+        match literal {
+            Literal::LiteralValue(value) => value.get_type(),
+            Literal::LiteralField(field_name) => self
+                .mapping
+                .get_field(field_name)
+                .map_or(Type::Null, |field| field.field_type),
+            Literal::LiteralAge(_) => Type::Number,
+            Literal::LiteralComputed(_) => engine::literal_type_at(
+                literal,
+                &|field_name| {
+                    self.mapping
+                        .get_field(field_name)
+                        .map(|field| field.field_type)
+                },
+                0,
+            )
+            .unwrap_or(Type::Null),
+        }
+    }
+
+    fn operand(
+        &self,
+        literal: &Literal,
+        field_type: Type,
+        batch: &RecordBatch,
+        operation: &Operation,
+    ) -> Result<Operand, EvaluateError> {
+        let field_name = match literal {
+            Literal::LiteralValue(value) => return Ok(Operand::Literal(value.clone())),
+            Literal::LiteralField(field_name) => field_name,
+            Literal::LiteralAge(_) | Literal::LiteralComputed(_) => {
+                return Err(EvaluateError::unsupported(operation));
+            }
+        };
+        let column_name = self
+            .mapping
+            .get_field(field_name)
+            .map(|field| field.name.as_str())
+            .unwrap_or(field_name);
+        let column = batch
+            .column_by_name(column_name)
+            .ok_or_else(|| EvaluateError::MissingColumnError(column_name.to_string()))?;
+
+        let data_type = match field_type {
+            Type::String => DataType::Utf8,
+            Type::Number => DataType::Float64,
+            Type::Boolean => DataType::Boolean,
+            Type::Raw => DataType::Binary,
+            // Only the unit changes, timestamps are UTC whatever the zone.
+            Type::DateTime => match column.data_type() {
+                DataType::Timestamp(_, zone) => {
+                    DataType::Timestamp(TimeUnit::Nanosecond, zone.clone())
+                }
+                _ => DataType::Timestamp(TimeUnit::Nanosecond, None),
+            },
+            _ => return Err(EvaluateError::unsupported(operation)),
+        };
+        if column.data_type() == &data_type {
+            return Ok(Operand::Column(column.clone()));
+        }
+
+        cast(column, &data_type)
+            .map(Operand::Column)
+            .map_err(EvaluateError::ArrowError)
+    }
+}
+
+// Values of a column or a literal, indexed by row.
+enum Side<'a, N> {
+    Column(&'a [N]),
+    Literal(N),
+}
+
+impl<N: Copy> Side<'_, N> {
+    fn get(&self, i: usize) -> N {
+        match self {
+            Side::Column(values) => values[i],
+            Side::Literal(value) => *value,
+        }
+    }
+}
+
+fn numbers(operand: &Operand) -> Side<'_, f64> {
+    match operand {
+        Operand::Column(column) => Side::Column(column.as_primitive::<Float64Type>().values()),
+        Operand::Literal(Value::Number(num)) => Side::Literal(*num),
+        Operand::Literal(_) => unreachable!(),
+    }
+}
+
+fn timestamps<'a>(
+    operand: &'a Operand,
+    operation: &Operation,
+) -> Result<Side<'a, i64>, EvaluateError> {
+    Ok(match operand {
+        Operand::Column(column) => {
+            Side::Column(column.as_primitive::<TimestampNanosecondType>().values())
+        }
+        Operand::Literal(Value::DateTime(datetime)) => Side::Literal(
+            datetime
+                .timestamp_nanos_opt()
+                .ok_or_else(|| EvaluateError::unsupported(operation))?,
+        ),
+        Operand::Literal(_) => unreachable!(),
+    })
+}
+
+// Arrow's float kernels order by IEEE total order, where NaN equals NaN and -0
+// is less than 0, so numbers are compared here like the engine does.
+fn compare<N: Copy + PartialOrd>(
+    lhs: &Side<N>,
+    rhs: &Side<N>,
+    op: &Operator,
+    len: usize,
+) -> BooleanBuffer {
+    let op: fn(&N, &N) -> bool = match op {
+        Operator::Eq => N::eq,
+        Operator::Ne => N::ne,
+        Operator::Gt => N::gt,
+        Operator::Gte => N::ge,
+        Operator::Lt => N::lt,
+        Operator::Lte => N::le,
+        Operator::In => unreachable!(),
+    };
+
+    BooleanBuffer::collect_bool(len, |i| op(&lhs.get(i), &rhs.get(i)))
+}
+
+fn scalar(value: &Value) -> Option<Scalar<ArrayRef>> {
+    let array: ArrayRef = match value {
+        Value::String(val) => Arc::new(StringArray::from(vec![val.as_str()])),
+        Value::Boolean(val) => Arc::new(BooleanArray::from(vec![*val])),
+        Value::Raw(val) => Arc::new(BinaryArray::from_vec(vec![val.as_slice()])),
+        _ => return None,
+    };
+
+    Some(Scalar::new(array))
+}
+
+// `values` holds the comparison of the non-null rows. A null equals only
+// another null, so `==` holds and `!=` fails when both sides are null, `!=`
+// holds when only one is, and everything else involving a null fails.
+fn with_nulls(
+    values: BooleanBuffer,
+    lhs: Option<NullBuffer>,
+    rhs: Option<NullBuffer>,
+    op: &Operator,
+) -> BooleanArray {
+    if lhs.is_none() && rhs.is_none() {
+        return BooleanArray::new(values, None);
+    }
+
+    let len = values.len();
+    let valid = |nulls: Option<NullBuffer>| {
+        nulls.map_or_else(|| BooleanBuffer::new_set(len), NullBuffer::into_inner)
+    };
+    let lhs = valid(lhs);
+    let rhs = valid(rhs);
+
+    let mut values = &values & &(&lhs & &rhs);
+    match op {
+        Operator::Eq => values = &values | &(&!&lhs & &!&rhs),
+        Operator::Ne => values = &values | &(&lhs ^ &rhs),
+        _ => {}
+    }
+
+    BooleanArray::new(values, None)
+}
+
+fn constant(value: bool, len: usize) -> BooleanArray {
+    let values = match value {
+        true => BooleanBuffer::new_set(len),
+        false => BooleanBuffer::new_unset(len),
+    };
+
+    BooleanArray::new(values, None)
+}
@@ -0,0 +1,46 @@
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::{engine::ExecutionError, expression::Expression};
+
+// What an audit sink is told about one `Engine::execute` call.
+pub struct AuditRecord<'a> {
+    // `Expression::fingerprint`, the same for equivalent formatting.
+    pub fingerprint: u64,
+    // The target, as identified by the key extractor.
+    pub key: String,
+    pub result: &'a Result<bool, ExecutionError>,
+    pub started_at: SystemTime,
+    pub duration: Duration,
+}
+
+pub(crate) struct Audit<T> {
+    pub(crate) key_extractor: Box<dyn Fn(&T) -> String>,
+    pub(crate) sink: Box<dyn Fn(&AuditRecord)>,
+}
+
+impl<T> Audit<T> {
+    // Runs `execute` and reports it to the sink, as started at `now` or the
+    // system time. Fingerprints are only computed when auditing.
+    pub(crate) fn record(
+        &self,
+        expression: &Expression,
+        target: &T,
+        now: Option<SystemTime>,
+        execute: impl FnOnce() -> Result<bool, ExecutionError>,
+    ) -> Result<bool, ExecutionError> {
+        let started_at = now.unwrap_or_else(SystemTime::now);
+        let start = Instant::now();
+        let result = execute();
+        let duration = start.elapsed();
+
+        (self.sink)(&AuditRecord {
+            fingerprint: expression.fingerprint(),
+            key: (self.key_extractor)(target),
+            result: &result,
+            started_at,
+            duration,
+        });
+
+        result
+    }
+}
@@ -0,0 +1,224 @@
+mod record;
+mod repl;
+
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    process::ExitCode,
+};
+
+use expression::{
+    Engine, Parser, descriptor::SchemaDescriptor, grammar, lsp::LanguageServer, schema::Type,
+    typescript::to_typescript,
+};
+use serde_json::Value as Json;
+
+const USAGE: &str = "usage: expression-cli <expression> <descriptor.json>
+       expression-cli csv <expression> <descriptor.json>
+       expression-cli repl <descriptor.json> [record.json]
+       expression-cli lsp <descriptor.json>
+       expression-cli infer <record.json>
+       expression-cli typescript <descriptor.json>
+       expression-cli grammar [--json]
+
+Reads JSON records from stdin, one per line, and prints the ones matching the
+expression. The descriptor lists the records' fields and their types, nested
+fields are named with `:`, e.g. `address:city`. With `csv` the records are
+the rows of a CSV file with a header instead, and fields are its columns.
+
+The REPL reads expressions interactively and shows how they parse, validate and
+evaluate against the sample record. The language server speaks LSP over stdio.
+`infer` prints a descriptor for records like the sample record, and
+`typescript` the descriptor's fields and operator rules as a TypeScript module.
+`grammar` prints the expression grammar as EBNF, or with its operators and
+keywords as JSON.";
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let result = match args.as_slice() {
+        [command, descriptor_path] if command == "repl" => {
+            read_descriptor(descriptor_path).and_then(|descriptor| repl::run(descriptor, None))
+        }
+        [command, descriptor_path, record_path] if command == "repl" => {
+            read_descriptor(descriptor_path).and_then(|descriptor| {
+                let record = read_json(record_path)?;
+                repl::run(descriptor, Some(record))
+            })
+        }
+        [command, descriptor_path] if command == "lsp" => read_descriptor(descriptor_path)
+            .and_then(|descriptor| {
+                LanguageServer::new(descriptor)
+                    .run(io::stdin().lock(), io::stdout().lock())
+                    .map(|()| ExitCode::SUCCESS)
+                    .map_err(|e| e.to_string())
+            }),
+        [command] if command == "grammar" => {
+            print!("{}", grammar::ebnf());
+            Ok(ExitCode::SUCCESS)
+        }
+        [command, flag] if command == "grammar" && flag == "--json" => {
+            serde_json::to_string_pretty(&grammar::grammar())
+                .map(|json| {
+                    println!("{}", json);
+                    ExitCode::SUCCESS
+                })
+                .map_err(|e| e.to_string())
+        }
+        [command, descriptor_path] if command == "typescript" => read_descriptor(descriptor_path)
+            .map(|descriptor| {
+                print!("{}", to_typescript(&descriptor));
+                ExitCode::SUCCESS
+            }),
+        [command, record_path] if command == "infer" => read_json(record_path).and_then(|record| {
+            let descriptor = SchemaDescriptor::infer_from_json(&record);
+            serde_json::to_string_pretty(&descriptor)
+                .map(|json| {
+                    println!("{}", json);
+                    ExitCode::SUCCESS
+                })
+                .map_err(|e| e.to_string())
+        }),
+        [command, expression, descriptor_path] if command == "csv" => {
+            read_descriptor(descriptor_path)
+                .and_then(|descriptor| filter_csv(expression, descriptor))
+        }
+        [expression, descriptor_path] => {
+            read_descriptor(descriptor_path).and_then(|descriptor| filter(expression, descriptor))
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    result.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        ExitCode::from(2)
+    })
+}
+
+fn read_json(path: &str) -> Result<Json, String> {
+    fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+        .map_err(|e| format!("Invalid JSON in {}: {}", path, e))
+}
+
+fn read_descriptor(path: &str) -> Result<SchemaDescriptor, String> {
+    serde_json::from_value(read_json(path)?)
+        .map_err(|e| format!("Invalid descriptor {}: {}", path, e))
+}
+
+fn filter(expression: &str, descriptor: SchemaDescriptor) -> Result<ExitCode, String> {
+    let expression = Parser::parse(expression).map_err(|e| format!("Invalid expression: {}", e))?;
+
+    let engine = Engine::new(record::schema(descriptor));
+    engine
+        .validate(&expression)
+        .map_err(|e| format!("Invalid expression: {}", e))?;
+
+    let mut failed = false;
+    let mut stdout = io::stdout().lock();
+    for (i, line) in io::stdin().lock().lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("Failed to read stdin: {}", e);
+                return Ok(ExitCode::FAILURE);
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let record = match serde_json::from_str::<Json>(&line) {
+            Ok(record) => record,
+            Err(e) => {
+                eprintln!("Line {}: invalid JSON: {}", i + 1, e);
+                failed = true;
+                continue;
+            }
+        };
+
+        match engine.execute(&expression, &record) {
+            Ok(true) => {
+                if writeln!(stdout, "{}", line).is_err() {
+                    return Ok(ExitCode::FAILURE);
+                }
+            }
+            Ok(false) => {}
+            Err(e) => {
+                eprintln!("Line {}: {}", i + 1, e);
+                failed = true;
+            }
+        }
+    }
+
+    Ok(if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn filter_csv(expression: &str, descriptor: SchemaDescriptor) -> Result<ExitCode, String> {
+    let expression = Parser::parse(expression).map_err(|e| format!("Invalid expression: {}", e))?;
+
+    let mut reader = csv::Reader::from_reader(io::stdin().lock());
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("Failed to read the CSV header: {}", e))?
+        .clone();
+    // Leaked like the fields of JSON records, see `record::schema`.
+    let fields = descriptor
+        .fields
+        .into_iter()
+        .map(|field| (&*field.name.leak(), field.field_type))
+        .collect::<Vec<(&'static str, Type)>>();
+    let schema = expression::csv::schema(&headers, &fields).map_err(|e| e.to_string())?;
+
+    let engine = Engine::new(schema);
+    engine
+        .validate(&expression)
+        .map_err(|e| format!("Invalid expression: {}", e))?;
+
+    let mut writer = csv::Writer::from_writer(io::stdout().lock());
+    if writer.write_record(&headers).is_err() {
+        return Ok(ExitCode::FAILURE);
+    }
+
+    let mut failed = false;
+    for (i, row) in reader.records().enumerate() {
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => {
+                eprintln!("Row {}: invalid CSV: {}", i + 1, e);
+                failed = true;
+                continue;
+            }
+        };
+
+        match engine.execute(&expression, &row) {
+            Ok(true) => {
+                if writer.write_record(&row).is_err() {
+                    return Ok(ExitCode::FAILURE);
+                }
+            }
+            Ok(false) => {}
+            Err(e) => {
+                eprintln!("Row {}: {}", i + 1, e);
+                failed = true;
+            }
+        }
+    }
+
+    if writer.flush().is_err() {
+        return Ok(ExitCode::FAILURE);
+    }
+
+    Ok(if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
@@ -0,0 +1,77 @@
+use chrono::{DateTime, Utc};
+use expression::{
+    SchemaBuilder,
+    descriptor::SchemaDescriptor,
+    schema::{Schema, Type},
+};
+use serde_json::Value as Json;
+
+// Schemas are keyed by `&'static str`, the names are leaked as they live for
+// the whole run anyway.
+pub fn schema(descriptor: SchemaDescriptor) -> Schema<Json> {
+    descriptor
+        .fields
+        .into_iter()
+        .fold(SchemaBuilder::new(), |builder, field| {
+            let name: &'static str = field.name.leak();
+
+            let builder = match field.field_type {
+                Type::String => builder.with_string_field(name, move |r| string(lookup(r, name)?)),
+                Type::Number => builder.with_number_field(name, move |r| lookup(r, name)?.as_f64()),
+                Type::Boolean => {
+                    builder.with_boolean_field(name, move |r| lookup(r, name)?.as_bool())
+                }
+                Type::Raw => builder.with_raw_field(name, move |r| raw(lookup(r, name)?)),
+                Type::DateTime => {
+                    builder.with_datetime_field(name, move |r| datetime(lookup(r, name)?))
+                }
+                Type::StringList => {
+                    builder.with_string_list_field(name, move |r| list(lookup(r, name)?, string))
+                }
+                Type::NumberList => builder
+                    .with_number_list_field(name, move |r| list(lookup(r, name)?, Json::as_f64)),
+                Type::BooleanList => builder
+                    .with_boolean_list_field(name, move |r| list(lookup(r, name)?, Json::as_bool)),
+                Type::RawList => {
+                    builder.with_raw_list_field(name, move |r| list(lookup(r, name)?, raw))
+                }
+                Type::DateTimeList => builder
+                    .with_datetime_list_field(name, move |r| list(lookup(r, name)?, datetime)),
+                // Regex and null fields can't be extracted from a record.
+                Type::Regex | Type::Null => builder,
+            };
+
+            match &field.description {
+                Some(description) => builder.with_description(name, description),
+                None => builder,
+            }
+        })
+        .build()
+}
+
+pub fn lookup<'a>(record: &'a Json, field_name: &str) -> Option<&'a Json> {
+    field_name
+        .split(':')
+        .try_fold(record, |json, key| json.as_object()?.get(key))
+}
+
+fn string(json: &Json) -> Option<String> {
+    json.as_str().map(String::from)
+}
+
+// Raw values are arrays of bytes.
+fn raw(json: &Json) -> Option<Vec<u8>> {
+    list(json, |byte| u8::try_from(byte.as_u64()?).ok())
+}
+
+// Datetimes are RFC 3339 strings.
+fn datetime(json: &Json) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(json.as_str()?)
+        .ok()
+        .map(|datetime| datetime.to_utc())
+}
+
+// A list with an item of the wrong type is treated as missing.
+fn list<T>(json: &Json, item: impl Fn(&Json) -> Option<T>) -> Option<Vec<T>> {
+    json.as_array()?.iter().map(item).collect()
+}
@@ -0,0 +1,172 @@
+use std::{
+    io::{self, BufRead, Write},
+    process::ExitCode,
+};
+
+use expression::{
+    Engine, Expression, Parser,
+    descriptor::SchemaDescriptor,
+    expression::{Literal, Operation},
+};
+use serde_json::Value as Json;
+
+use crate::{read_json, record};
+
+const HELP: &str = "Type an expression to parse, validate and evaluate it.
+  :record <file.json>  load a sample record
+  :fields              list the schema's fields
+  :help                show this message
+  :quit                exit";
+
+pub fn run(descriptor: SchemaDescriptor, mut record: Option<Json>) -> Result<ExitCode, String> {
+    let engine = Engine::new(record::schema(descriptor.clone()));
+
+    println!("{}", HELP);
+    let mut stdin = io::stdin().lock();
+    loop {
+        print!("> ");
+        io::stdout().flush().map_err(|e| e.to_string())?;
+
+        let mut line = String::new();
+        if stdin.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            println!();
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        let line = line.trim();
+        match line.split_once(' ').unwrap_or((line, "")) {
+            ("", _) => {}
+            (":quit" | ":q", _) => return Ok(ExitCode::SUCCESS),
+            (":help", _) => println!("{}", HELP),
+            (":fields", _) => {
+                for field in &descriptor.fields {
+                    match &field.description {
+                        Some(description) => println!(
+                            "  {}: {} - {}",
+                            field.name,
+                            field.field_type.variant_name(),
+                            description
+                        ),
+                        None => println!("  {}: {}", field.name, field.field_type.variant_name()),
+                    }
+                }
+            }
+            (":record", path) => match read_json(path.trim()) {
+                Ok(json) => record = Some(json),
+                Err(e) => println!("{}", e),
+            },
+            _ => evaluate(&engine, line, record.as_ref()),
+        }
+    }
+}
+
+fn evaluate(engine: &Engine<Json>, line: &str, record: Option<&Json>) {
+    let expression = match Parser::parse(line) {
+        Ok(expression) => expression,
+        Err(e) => {
+            println!("Parse error: {}", e);
+            return;
+        }
+    };
+
+    if let Err(e) = engine.validate(&expression) {
+        print_tree(&expression, 1);
+        println!("Validation error: {}", e);
+        return;
+    }
+
+    match record {
+        Some(record) => {
+            trace(engine, &expression, record, 1);
+            match engine.execute(&expression, record) {
+                Ok(result) => println!("Result: {}", result),
+                Err(e) => println!("Execution error: {}", e),
+            }
+        }
+        None => {
+            print_tree(&expression, 1);
+            println!("Valid, load a record with :record to evaluate it");
+        }
+    }
+}
+
+fn print_tree(expression: &Expression, depth: usize) {
+    let indent = "  ".repeat(depth);
+    match expression {
+        Expression::And(and) => {
+            println!("{}AND", indent);
+            for subexpression in and.get_subexpressions() {
+                print_tree(subexpression, depth + 1);
+            }
+        }
+        Expression::Or(or) => {
+            println!("{}OR", indent);
+            for subexpression in or.get_subexpressions() {
+                print_tree(subexpression, depth + 1);
+            }
+        }
+        Expression::Not(not) => {
+            println!("{}NOT", indent);
+            print_tree(not.get_subexpression(), depth + 1);
+        }
+        Expression::Operation(_) | Expression::Reference(_) => {
+            println!("{}{}", indent, expression.serialize())
+        }
+    }
+}
+
+// Prints the tree with the outcome of every node. Each node is evaluated on
+// its own, so subexpressions the engine would short-circuit are shown too.
+fn trace(engine: &Engine<Json>, expression: &Expression, record: &Json, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let outcome = match engine.execute(expression, record) {
+        Ok(result) => result.to_string(),
+        Err(e) => format!("error: {}", e),
+    };
+
+    match expression {
+        Expression::And(and) => {
+            println!("{}AND => {}", indent, outcome);
+            for subexpression in and.get_subexpressions() {
+                trace(engine, subexpression, record, depth + 1);
+            }
+        }
+        Expression::Or(or) => {
+            println!("{}OR => {}", indent, outcome);
+            for subexpression in or.get_subexpressions() {
+                trace(engine, subexpression, record, depth + 1);
+            }
+        }
+        Expression::Not(not) => {
+            println!("{}NOT => {}", indent, outcome);
+            trace(engine, not.get_subexpression(), record, depth + 1);
+        }
+        Expression::Operation(operation) => println!(
+            "{}{} => {}{}",
+            indent,
+            expression.serialize(),
+            outcome,
+            field_values(operation, record)
+        ),
+        Expression::Reference(_) => println!("{}{} => {}", indent, expression.serialize(), outcome),
+    }
+}
+
+fn field_values(operation: &Operation, record: &Json) -> String {
+    let values = [&operation.lhs, &operation.rhs]
+        .into_iter()
+        .flat_map(Literal::get_field_operands)
+        .map(|(field_name, _)| {
+            format!(
+                "{} = {}",
+                field_name,
+                record::lookup(record, field_name).unwrap_or(&Json::Null)
+            )
+        })
+        .collect::<Vec<String>>();
+
+    match values.is_empty() {
+        true => String::new(),
+        false => format!("  ({})", values.join(", ")),
+    }
+}
@@ -0,0 +1,653 @@
+use alloc::{boxed::Box, string::String, vec::Vec};
+
+#[cfg(feature = "chrono")]
+use chrono::DateTime;
+use thiserror::Error;
+
+use crate::{
+    expression::{
+        And, BitwiseOperator, Computed, Expression, Literal, MAX_DEPTH, Not, Operation, Operator,
+        Or, Reference,
+    },
+    schema::Value,
+};
+
+#[derive(Error, Debug)]
+pub enum DecodeError {
+    #[error("Unexpected end of input at byte {0}")]
+    UnexpectedEndError(usize),
+    #[error("Invalid {0} tag {1} at byte {2}")]
+    InvalidTagError(&'static str, u8, usize),
+    #[error("Invalid length at byte {0}")]
+    InvalidLengthError(usize),
+    #[error("Invalid UTF-8 string at byte {0}")]
+    InvalidUtf8Error(usize),
+    #[cfg(feature = "chrono")]
+    #[error("Invalid datetime at byte {0}")]
+    InvalidDateTimeError(usize),
+    #[error("Unexpected trailing bytes at byte {0}")]
+    TrailingBytesError(usize),
+    #[error("Input is not an encoded expression")]
+    InvalidHeaderError,
+    #[error("Unsupported format version {0}, the newest supported version is {1}")]
+    UnsupportedVersionError(u8, u8),
+    #[error("Expressions can be nested at most {0} levels deep")]
+    TooDeepError(usize),
+}
+
+// Every encoding starts with `MAGIC` followed by the format version. The version
+// is bumped whenever the encoding changes; decoders keep accepting all older
+// versions and reject newer ones instead of misreading them. Tags are checked
+// against the version of the input, tags it predates are invalid. Version 2
+// added computed operands, version 3 rule references and version 4 age
+// literals.
+const MAGIC: &[u8; 2] = b"EX";
+pub const FORMAT_VERSION: u8 = 4;
+
+pub trait Encode {
+    fn encode(&self, buf: &mut Vec<u8>);
+}
+
+pub trait Decode: Sized {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError>;
+}
+
+pub struct Reader<'a> {
+    input: &'a [u8],
+    pos: usize,
+    version: u8,
+    depth: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        Self {
+            input,
+            pos: 0,
+            version: FORMAT_VERSION,
+            depth: 0,
+        }
+    }
+
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    // The format version of the input, for decoders that need to read older layouts.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.input.len()
+    }
+
+    fn read_u8(&mut self) -> Result<u8, DecodeError> {
+        let byte = *self
+            .input
+            .get(self.pos)
+            .ok_or(DecodeError::UnexpectedEndError(self.pos))?;
+        self.pos += 1;
+
+        Ok(byte)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.input.len())
+            .ok_or(DecodeError::UnexpectedEndError(self.input.len()))?;
+        let bytes = &self.input[self.pos..end];
+        self.pos = end;
+
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        Ok(self.read_bytes(N)?.try_into().unwrap())
+    }
+
+    fn read_len(&mut self) -> Result<usize, DecodeError> {
+        let start = self.pos;
+        let len = self.read_index()?;
+
+        // Every encoded element takes at least one byte, so a length larger than
+        // the remaining input can never be valid and must not drive an allocation.
+        if len > self.input.len() - self.pos {
+            return Err(DecodeError::InvalidLengthError(start));
+        }
+
+        Ok(len)
+    }
+
+    // A length or index, as a little-endian base 128 varint.
+    fn read_index(&mut self) -> Result<usize, DecodeError> {
+        let start = self.pos;
+        let mut value: u64 = 0;
+        let mut shift = 0;
+
+        loop {
+            let byte = self.read_u8()?;
+            if shift >= 64 {
+                return Err(DecodeError::InvalidLengthError(start));
+            }
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+        }
+
+        usize::try_from(value).map_err(|_| DecodeError::InvalidLengthError(start))
+    }
+
+    fn read_string(&mut self) -> Result<String, DecodeError> {
+        let start = self.pos;
+        let len = self.read_len()?;
+        let bytes = self.read_bytes(len)?;
+
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::InvalidUtf8Error(start))
+    }
+
+    // Decoding is recursive, so expressions and computed operands nested deeper
+    // than `MAX_DEPTH` are rejected instead of overflowing the stack.
+    fn read_nested<T>(
+        &mut self,
+        read: impl FnOnce(&mut Self) -> Result<T, DecodeError>,
+    ) -> Result<T, DecodeError> {
+        if self.depth > MAX_DEPTH {
+            return Err(DecodeError::TooDeepError(MAX_DEPTH));
+        }
+
+        self.depth += 1;
+        let result = read(self);
+        self.depth -= 1;
+
+        result
+    }
+
+    fn read_list<T>(
+        &mut self,
+        read_item: impl Fn(&mut Self) -> Result<T, DecodeError>,
+    ) -> Result<Vec<T>, DecodeError> {
+        let len = self.read_len()?;
+        let mut items = Vec::with_capacity(len);
+        for _ in 0..len {
+            items.push(read_item(self)?);
+        }
+
+        Ok(items)
+    }
+}
+
+fn write_len(buf: &mut Vec<u8>, len: usize) {
+    let mut value = len as u64;
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf.push(byte);
+            break;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+fn write_string(buf: &mut Vec<u8>, val: &str) {
+    write_len(buf, val.len());
+    buf.extend_from_slice(val.as_bytes());
+}
+
+fn write_list<T>(buf: &mut Vec<u8>, items: &[T], write_item: impl Fn(&mut Vec<u8>, &T)) {
+    write_len(buf, items.len());
+    for item in items {
+        write_item(buf, item);
+    }
+}
+
+fn write_number(buf: &mut Vec<u8>, val: &f64) {
+    buf.extend_from_slice(&val.to_le_bytes());
+}
+
+fn write_boolean(buf: &mut Vec<u8>, val: &bool) {
+    buf.push(*val as u8);
+}
+
+#[cfg(feature = "raw")]
+fn write_raw(buf: &mut Vec<u8>, val: &[u8]) {
+    write_len(buf, val.len());
+    buf.extend_from_slice(val);
+}
+
+#[cfg(feature = "chrono")]
+fn write_datetime(buf: &mut Vec<u8>, val: &DateTime<chrono::Utc>) {
+    buf.extend_from_slice(&val.timestamp().to_le_bytes());
+    buf.extend_from_slice(&val.timestamp_subsec_nanos().to_le_bytes());
+}
+
+fn read_number(reader: &mut Reader) -> Result<f64, DecodeError> {
+    Ok(f64::from_le_bytes(reader.read_array()?))
+}
+
+fn read_boolean(reader: &mut Reader) -> Result<bool, DecodeError> {
+    let pos = reader.position();
+    match reader.read_u8()? {
+        0 => Ok(false),
+        1 => Ok(true),
+        tag => Err(DecodeError::InvalidTagError("boolean", tag, pos)),
+    }
+}
+
+#[cfg(feature = "raw")]
+fn read_raw(reader: &mut Reader) -> Result<Vec<u8>, DecodeError> {
+    let len = reader.read_len()?;
+
+    Ok(reader.read_bytes(len)?.to_vec())
+}
+
+#[cfg(feature = "chrono")]
+fn read_datetime(reader: &mut Reader) -> Result<DateTime<chrono::Utc>, DecodeError> {
+    let pos = reader.position();
+    let secs = i64::from_le_bytes(reader.read_array()?);
+    let nanos = u32::from_le_bytes(reader.read_array()?);
+
+    DateTime::from_timestamp(secs, nanos).ok_or(DecodeError::InvalidDateTimeError(pos))
+}
+
+impl Encode for Expression {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Expression::And(and) => {
+                buf.push(0);
+                and.encode(buf);
+            }
+            Expression::Or(or) => {
+                buf.push(1);
+                or.encode(buf);
+            }
+            Expression::Not(not) => {
+                buf.push(2);
+                not.encode(buf);
+            }
+            Expression::Operation(operation) => {
+                buf.push(3);
+                operation.encode(buf);
+            }
+            Expression::Reference(reference) => {
+                buf.push(4);
+                reference.encode(buf);
+            }
+        }
+    }
+}
+
+impl Decode for Expression {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        reader.read_nested(|reader| {
+            let pos = reader.position();
+            Ok(match reader.read_u8()? {
+                0 => Expression::And(And::decode(reader)?),
+                1 => Expression::Or(Or::decode(reader)?),
+                2 => Expression::Not(Not::decode(reader)?),
+                3 => Expression::Operation(Operation::decode(reader)?),
+                4 if reader.version() >= 3 => Expression::Reference(Reference::decode(reader)?),
+                tag => return Err(DecodeError::InvalidTagError("expression", tag, pos)),
+            })
+        })
+    }
+}
+
+impl Encode for And {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        write_list(buf, self.get_subexpressions(), |buf, e| e.encode(buf));
+    }
+}
+
+impl Decode for And {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        Ok(And::new(reader.read_list(Expression::decode)?))
+    }
+}
+
+impl Encode for Or {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        write_list(buf, self.get_subexpressions(), |buf, e| e.encode(buf));
+    }
+}
+
+impl Decode for Or {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        Ok(Or::new(reader.read_list(Expression::decode)?))
+    }
+}
+
+impl Encode for Not {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.get_subexpression().encode(buf);
+    }
+}
+
+impl Decode for Not {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        Ok(Not::new(Expression::decode(reader)?))
+    }
+}
+
+impl Encode for Reference {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        write_string(buf, self.get_rule_name());
+    }
+}
+
+impl Decode for Reference {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        Ok(Reference::new(&reader.read_string()?))
+    }
+}
+
+impl Encode for Operation {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.lhs.encode(buf);
+        self.op.encode(buf);
+        self.rhs.encode(buf);
+    }
+}
+
+impl Decode for Operation {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let lhs = Literal::decode(reader)?;
+        let op = Operator::decode(reader)?;
+        let rhs = Literal::decode(reader)?;
+
+        Ok(Operation::new(lhs, op, rhs))
+    }
+}
+
+impl Encode for Literal {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Literal::LiteralValue(value) => {
+                buf.push(0);
+                value.encode(buf);
+            }
+            Literal::LiteralField(field_name) => {
+                buf.push(1);
+                write_string(buf, field_name);
+            }
+            #[cfg(feature = "chrono")]
+            Literal::LiteralAge(field_name) => {
+                buf.push(2);
+                write_string(buf, field_name);
+            }
+            Literal::LiteralComputed(computed) => {
+                buf.push(3);
+                computed.encode(buf);
+            }
+        }
+    }
+}
+
+impl Decode for Literal {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let pos = reader.position();
+        Ok(match reader.read_u8()? {
+            0 => Literal::LiteralValue(Value::decode(reader)?),
+            1 => Literal::LiteralField(reader.read_string()?.into()),
+            #[cfg(feature = "chrono")]
+            2 if reader.version() >= 4 => Literal::LiteralAge(reader.read_string()?.into()),
+            3 if reader.version() >= 2 => {
+                Literal::LiteralComputed(Box::new(reader.read_nested(Computed::decode)?))
+            }
+            tag => return Err(DecodeError::InvalidTagError("literal", tag, pos)),
+        })
+    }
+}
+
+impl Encode for Computed {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Computed::Bitwise(first, rest) => {
+                buf.push(0);
+                first.encode(buf);
+                write_list(buf, rest, |buf, (op, operand)| {
+                    op.encode(buf);
+                    operand.encode(buf);
+                });
+            }
+            Computed::Concat(operands) => {
+                buf.push(1);
+                write_list(buf, operands, |buf, operand| operand.encode(buf));
+            }
+            Computed::Coalesce(operands) => {
+                buf.push(2);
+                write_list(buf, operands, |buf, operand| operand.encode(buf));
+            }
+            Computed::If(condition, then, otherwise) => {
+                buf.push(3);
+                condition.encode(buf);
+                then.encode(buf);
+                otherwise.encode(buf);
+            }
+            Computed::First(operand) => {
+                buf.push(4);
+                operand.encode(buf);
+            }
+            Computed::Last(operand) => {
+                buf.push(5);
+                operand.encode(buf);
+            }
+            Computed::Slice(operand, start, end) => {
+                buf.push(6);
+                operand.encode(buf);
+                write_len(buf, *start);
+                write_len(buf, *end);
+            }
+        }
+    }
+}
+
+impl Decode for Computed {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let pos = reader.position();
+        Ok(match reader.read_u8()? {
+            0 => {
+                let first = Literal::decode(reader)?;
+                let rest = reader.read_list(|reader| {
+                    Ok((BitwiseOperator::decode(reader)?, Literal::decode(reader)?))
+                })?;
+
+                Computed::Bitwise(first, rest)
+            }
+            1 => Computed::Concat(reader.read_list(Literal::decode)?),
+            2 => Computed::Coalesce(reader.read_list(Literal::decode)?),
+            3 => Computed::If(
+                Expression::decode(reader)?,
+                Literal::decode(reader)?,
+                Literal::decode(reader)?,
+            ),
+            4 => Computed::First(Literal::decode(reader)?),
+            5 => Computed::Last(Literal::decode(reader)?),
+            6 => Computed::Slice(
+                Literal::decode(reader)?,
+                reader.read_index()?,
+                reader.read_index()?,
+            ),
+            tag => return Err(DecodeError::InvalidTagError("computed", tag, pos)),
+        })
+    }
+}
+
+impl Encode for BitwiseOperator {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(match self {
+            BitwiseOperator::And => 0,
+            BitwiseOperator::Or => 1,
+            BitwiseOperator::Xor => 2,
+            BitwiseOperator::ShiftLeft => 3,
+            BitwiseOperator::ShiftRight => 4,
+        });
+    }
+}
+
+impl Decode for BitwiseOperator {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let pos = reader.position();
+        Ok(match reader.read_u8()? {
+            0 => BitwiseOperator::And,
+            1 => BitwiseOperator::Or,
+            2 => BitwiseOperator::Xor,
+            3 => BitwiseOperator::ShiftLeft,
+            4 => BitwiseOperator::ShiftRight,
+            tag => return Err(DecodeError::InvalidTagError("bitwise operator", tag, pos)),
+        })
+    }
+}
+
+impl Encode for Operator {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(match self {
+            Operator::Eq => 0,
+            Operator::Ne => 1,
+            Operator::Gt => 2,
+            Operator::Gte => 3,
+            Operator::Lt => 4,
+            Operator::Lte => 5,
+            Operator::In => 6,
+        });
+    }
+}
+
+impl Decode for Operator {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let pos = reader.position();
+        Ok(match reader.read_u8()? {
+            0 => Operator::Eq,
+            1 => Operator::Ne,
+            2 => Operator::Gt,
+            3 => Operator::Gte,
+            4 => Operator::Lt,
+            5 => Operator::Lte,
+            6 => Operator::In,
+            tag => return Err(DecodeError::InvalidTagError("operator", tag, pos)),
+        })
+    }
+}
+
+impl Encode for Value {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Value::String(val) => {
+                buf.push(0);
+                write_string(buf, val);
+            }
+            #[cfg(feature = "regex")]
+            Value::Regex(val) => {
+                buf.push(1);
+                write_string(buf, val);
+            }
+            Value::Number(val) => {
+                buf.push(2);
+                write_number(buf, val);
+            }
+            Value::Boolean(val) => {
+                buf.push(3);
+                write_boolean(buf, val);
+            }
+            #[cfg(feature = "raw")]
+            Value::Raw(val) => {
+                buf.push(4);
+                write_raw(buf, val);
+            }
+            #[cfg(feature = "chrono")]
+            Value::DateTime(val) => {
+                buf.push(5);
+                write_datetime(buf, val);
+            }
+            Value::StringList(items) => {
+                buf.push(6);
+                write_list(buf, items, |buf, val| write_string(buf, val));
+            }
+            Value::NumberList(items) => {
+                buf.push(7);
+                write_list(buf, items, write_number);
+            }
+            Value::BooleanList(items) => {
+                buf.push(8);
+                write_list(buf, items, write_boolean);
+            }
+            #[cfg(feature = "raw")]
+            Value::RawList(items) => {
+                buf.push(9);
+                write_list(buf, items, |buf, val| write_raw(buf, val));
+            }
+            #[cfg(feature = "chrono")]
+            Value::DateTimeList(items) => {
+                buf.push(10);
+                write_list(buf, items, write_datetime);
+            }
+            Value::Null => buf.push(11),
+        }
+    }
+}
+
+impl Decode for Value {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let pos = reader.position();
+        // Values of disabled types are rejected as invalid tags.
+        Ok(match reader.read_u8()? {
+            0 => Value::String(reader.read_string()?),
+            #[cfg(feature = "regex")]
+            1 => Value::Regex(reader.read_string()?),
+            2 => Value::Number(read_number(reader)?),
+            3 => Value::Boolean(read_boolean(reader)?),
+            #[cfg(feature = "raw")]
+            4 => Value::Raw(read_raw(reader)?),
+            #[cfg(feature = "chrono")]
+            5 => Value::DateTime(read_datetime(reader)?),
+            6 => Value::StringList(reader.read_list(Reader::read_string)?),
+            7 => Value::NumberList(reader.read_list(read_number)?),
+            8 => Value::BooleanList(reader.read_list(read_boolean)?),
+            #[cfg(feature = "raw")]
+            9 => Value::RawList(reader.read_list(read_raw)?),
+            #[cfg(feature = "chrono")]
+            10 => Value::DateTimeList(reader.read_list(read_datetime)?),
+            11 => Value::Null,
+            tag => return Err(DecodeError::InvalidTagError("value", tag, pos)),
+        })
+    }
+}
+
+pub fn to_bytes<E: Encode>(value: &E) -> Vec<u8> {
+    let mut buf = Vec::from(MAGIC);
+    buf.push(FORMAT_VERSION);
+    value.encode(&mut buf);
+
+    buf
+}
+
+pub fn from_bytes<D: Decode>(input: &[u8]) -> Result<D, DecodeError> {
+    let mut reader = Reader::new(input);
+    if reader.read_bytes(MAGIC.len()).ok() != Some(MAGIC) {
+        return Err(DecodeError::InvalidHeaderError);
+    }
+
+    reader.version = match reader.read_u8()? {
+        0 => return Err(DecodeError::InvalidHeaderError),
+        version if version > FORMAT_VERSION => {
+            return Err(DecodeError::UnsupportedVersionError(
+                version,
+                FORMAT_VERSION,
+            ));
+        }
+        version => version,
+    };
+
+    let value = D::decode(&mut reader)?;
+
+    if !reader.is_empty() {
+        return Err(DecodeError::TrailingBytesError(reader.position()));
+    }
+
+    Ok(value)
+}
@@ -0,0 +1,117 @@
+use crate::{
+    engine::ValidationError,
+    expression::{Computed, Expression, Literal, MAX_DEPTH},
+    schema::Value,
+};
+
+// Limits on the size of an expression, for services that accept expressions
+// from users and want to refuse expensive ones before executing them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Budget {
+    // Groups, negations, operations and references, including those in the
+    // conditions of `if()` operands.
+    pub max_nodes: usize,
+    // Regex literals, each one is compiled on execution.
+    pub max_regexes: usize,
+    // Items of any list literal, also inside computed operands.
+    pub max_list_len: usize,
+    // Levels of nested groups and negations, capped at `MAX_DEPTH`.
+    pub max_depth: usize,
+}
+
+#[derive(Default)]
+struct Usage {
+    nodes: usize,
+    #[cfg(feature = "regex")]
+    regexes: usize,
+}
+
+pub(crate) fn check(expression: &Expression, budget: &Budget) -> Result<(), ValidationError> {
+    check_at(expression, budget, &mut Usage::default(), 0)
+}
+
+// Stops at the first limit exceeded, so pathological expressions are only
+// walked as far as needed.
+fn check_at(
+    expression: &Expression,
+    budget: &Budget,
+    usage: &mut Usage,
+    depth: usize,
+) -> Result<(), ValidationError> {
+    let max_depth = budget.max_depth.min(MAX_DEPTH);
+    if depth > max_depth {
+        return Err(ValidationError::TooDeepError(max_depth));
+    }
+
+    usage.nodes += 1;
+    if usage.nodes > budget.max_nodes {
+        return Err(ValidationError::TooManyNodesError(budget.max_nodes));
+    }
+
+    match expression {
+        Expression::And(and) => and
+            .get_subexpressions()
+            .iter()
+            .try_for_each(|i| check_at(i, budget, usage, depth + 1)),
+        Expression::Or(or) => or
+            .get_subexpressions()
+            .iter()
+            .try_for_each(|i| check_at(i, budget, usage, depth + 1)),
+        Expression::Not(not) => check_at(not.get_subexpression(), budget, usage, depth + 1),
+        Expression::Operation(operation) => {
+            check_literal(&operation.lhs, budget, usage, depth)?;
+            check_literal(&operation.rhs, budget, usage, depth)
+        }
+        Expression::Reference(_) => Ok(()),
+    }
+}
+
+// Values and conditions inside computed operands count like those of the
+// operation itself.
+fn check_literal(
+    literal: &Literal,
+    budget: &Budget,
+    usage: &mut Usage,
+    depth: usize,
+) -> Result<(), ValidationError> {
+    match literal {
+        Literal::LiteralValue(value) => {
+            #[cfg(feature = "regex")]
+            if let Value::Regex(_) = value {
+                usage.regexes += 1;
+                if usage.regexes > budget.max_regexes {
+                    return Err(ValidationError::TooManyRegexesError(budget.max_regexes));
+                }
+            }
+            if list_len(value) > budget.max_list_len {
+                return Err(ValidationError::ListTooLongError(budget.max_list_len));
+            }
+
+            Ok(())
+        }
+        Literal::LiteralComputed(computed) => {
+            if let Computed::If(condition, _, _) = &**computed {
+                check_at(condition, budget, usage, depth + 1)?;
+            }
+            computed
+                .get_operands()
+                .into_iter()
+                .try_for_each(|operand| check_literal(operand, budget, usage, depth))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Zero for values that aren't lists.
+fn list_len(value: &Value) -> usize {
+    match value {
+        Value::StringList(items) => items.len(),
+        Value::NumberList(items) => items.len(),
+        Value::BooleanList(items) => items.len(),
+        #[cfg(feature = "raw")]
+        Value::RawList(items) => items.len(),
+        #[cfg(feature = "chrono")]
+        Value::DateTimeList(items) => items.len(),
+        _ => 0,
+    }
+}
@@ -0,0 +1,82 @@
+#[cfg(feature = "regex")]
+use alloc::string::String;
+
+use crate::{
+    expression::{Expression, FieldName, Literal, Operation, Operator},
+    schema::Value,
+};
+
+// Entry point for building expressions in code, e.g.
+// `Expr::field("age").gt(25).and(Expr::field("name").eq("John"))`.
+pub struct Expr;
+
+impl Expr {
+    pub fn field(field_name: &str) -> Operand {
+        Operand(Literal::LiteralField(FieldName::from(field_name)))
+    }
+
+    pub fn value(value: impl Into<Value>) -> Operand {
+        Operand(Literal::LiteralValue(value.into()))
+    }
+}
+
+pub struct Operand(Literal);
+
+impl<V: Into<Value>> From<V> for Operand {
+    fn from(value: V) -> Self {
+        Expr::value(value)
+    }
+}
+
+impl From<Operand> for Literal {
+    fn from(operand: Operand) -> Self {
+        operand.0
+    }
+}
+
+impl Operand {
+    fn operation(self, op: Operator, rhs: impl Into<Operand>) -> Expression {
+        Expression::Operation(Operation::new(self.0, op, rhs.into().0))
+    }
+
+    pub fn eq(self, rhs: impl Into<Operand>) -> Expression {
+        self.operation(Operator::Eq, rhs)
+    }
+
+    pub fn ne(self, rhs: impl Into<Operand>) -> Expression {
+        self.operation(Operator::Ne, rhs)
+    }
+
+    pub fn gt(self, rhs: impl Into<Operand>) -> Expression {
+        self.operation(Operator::Gt, rhs)
+    }
+
+    pub fn gte(self, rhs: impl Into<Operand>) -> Expression {
+        self.operation(Operator::Gte, rhs)
+    }
+
+    pub fn lt(self, rhs: impl Into<Operand>) -> Expression {
+        self.operation(Operator::Lt, rhs)
+    }
+
+    pub fn lte(self, rhs: impl Into<Operand>) -> Expression {
+        self.operation(Operator::Lte, rhs)
+    }
+
+    pub fn is_in(self, rhs: impl Into<Operand>) -> Expression {
+        self.operation(Operator::In, rhs)
+    }
+
+    pub fn is_null(self) -> Expression {
+        self.operation(Operator::Eq, Value::Null)
+    }
+
+    pub fn contains(self, needle: impl Into<Operand>) -> Expression {
+        needle.into().operation(Operator::In, self)
+    }
+
+    #[cfg(feature = "regex")]
+    pub fn matches(self, pattern: &str) -> Expression {
+        Expr::value(Value::Regex(String::from(pattern))).operation(Operator::In, self)
+    }
+}
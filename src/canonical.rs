@@ -0,0 +1,120 @@
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{
+    expression::{And, Computed, Expression, Literal, Not, Operation, Operator, Or},
+    schema::Value,
+    serialize::Serialize,
+};
+
+pub trait Canonicalize {
+    fn canonicalize(&self) -> Self;
+}
+
+impl Canonicalize for Expression {
+    fn canonicalize(&self) -> Self {
+        match self {
+            Expression::And(and) => Expression::And(and.canonicalize()),
+            Expression::Or(or) => Expression::Or(or.canonicalize()),
+            Expression::Not(not) => Expression::Not(not.canonicalize()),
+            Expression::Operation(operation) => Expression::Operation(operation.canonicalize()),
+            Expression::Reference(reference) => Expression::Reference(reference.clone()),
+        }
+    }
+}
+
+// And/Or are commutative, so their children are ordered by their own canonical
+// serialization to make the ordering independent of how the rule was written.
+fn canonicalize_subexpressions(subexpressions: &[Expression]) -> Vec<Expression> {
+    let mut subexpressions = subexpressions
+        .iter()
+        .map(Canonicalize::canonicalize)
+        .collect::<Vec<Expression>>();
+    subexpressions.sort_by_cached_key(Serialize::fmt);
+
+    subexpressions
+}
+
+impl Canonicalize for And {
+    fn canonicalize(&self) -> Self {
+        And::new(canonicalize_subexpressions(self.get_subexpressions()))
+    }
+}
+
+impl Canonicalize for Or {
+    fn canonicalize(&self) -> Self {
+        Or::new(canonicalize_subexpressions(self.get_subexpressions()))
+    }
+}
+
+impl Canonicalize for Not {
+    fn canonicalize(&self) -> Self {
+        Not::new(self.get_subexpression().canonicalize())
+    }
+}
+
+impl Canonicalize for Operation {
+    fn canonicalize(&self) -> Self {
+        let lhs = self.lhs.canonicalize();
+        let rhs = self.rhs.canonicalize();
+
+        // Comparisons are written with the field on the left, e.g. `25 < age`
+        // becomes `age > 25`.
+        if let (Literal::LiteralValue(_), Literal::LiteralField(_)) = (&lhs, &rhs) {
+            let mirrored = match self.op {
+                Operator::Eq => Some(Operator::Eq),
+                Operator::Ne => Some(Operator::Ne),
+                Operator::Gt => Some(Operator::Lt),
+                Operator::Gte => Some(Operator::Lte),
+                Operator::Lt => Some(Operator::Gt),
+                Operator::Lte => Some(Operator::Gte),
+                Operator::In => None,
+            };
+
+            if let Some(op) = mirrored {
+                return Operation::new(rhs, op, lhs);
+            }
+        }
+
+        Operation::new(lhs, self.op.clone(), rhs)
+    }
+}
+
+impl Canonicalize for Literal {
+    fn canonicalize(&self) -> Self {
+        match self {
+            Literal::LiteralValue(value) => Literal::LiteralValue(value.canonicalize()),
+            Literal::LiteralField(field_name) => Literal::LiteralField(field_name.clone()),
+            #[cfg(feature = "chrono")]
+            Literal::LiteralAge(field_name) => Literal::LiteralAge(field_name.clone()),
+            Literal::LiteralComputed(computed) => {
+                let computed = match &**computed {
+                    Computed::If(condition, then, otherwise) => {
+                        Computed::If(condition.canonicalize(), then.clone(), otherwise.clone())
+                    }
+                    computed => computed.clone(),
+                };
+
+                Literal::LiteralComputed(Box::new(
+                    computed.map_operands(|operand| operand.canonicalize()),
+                ))
+            }
+        }
+    }
+}
+
+fn canonicalize_number(val: f64) -> f64 {
+    // -0.0 compares equal to 0.0 but would serialize as "-0".
+    if val == 0.0 { 0.0 } else { val }
+}
+
+impl Canonicalize for Value {
+    fn canonicalize(&self) -> Self {
+        match self {
+            Value::Number(val) => Value::Number(canonicalize_number(*val)),
+            Value::NumberList(items) => {
+                Value::NumberList(items.iter().copied().map(canonicalize_number).collect())
+            }
+            value => value.clone(),
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use alloc::rc::Rc;
+use core::cell::Cell;
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+// Tells the engine the current time, e.g. for `age_of`. See `Engine::with_clock`.
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+// The system time, which engines use without a clock of their own.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+// A time that only changes when set, for deterministic tests. Shared through an
+// `Rc`, the time can be moved after handing the clock to an engine.
+#[derive(Clone, Debug)]
+pub struct FixedClock {
+    now: Cell<DateTime<Utc>>,
+}
+
+impl FixedClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Cell::new(now),
+        }
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        self.now.set(now);
+    }
+
+    pub fn advance(&self, delta: TimeDelta) {
+        self.now.set(self.now.get() + delta);
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.now.get()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Rc<C> {
+    fn now(&self) -> DateTime<Utc> {
+        (**self).now()
+    }
+}
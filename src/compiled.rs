@@ -0,0 +1,323 @@
+use alloc::{boxed::Box, string::String, vec, vec::Vec};
+
+use hashbrown::HashSet;
+
+use crate::{
+    engine::{ExecutionError, Fields, execute_operation, extract_literal},
+    expression::{Expression, FieldName, Literal, Operation, Operator},
+    misc::hash_key,
+    schema::Value,
+};
+
+// Lists with at least this many items are turned into hash sets. Scanning a
+// short list is as fast as hashing the value.
+const SET_THRESHOLD: usize = 16;
+
+// An expression prepared for repeated execution, see `Engine::compile`. `IN`
+// checks against large list literals look the value up in a hash set instead
+// of scanning the list. Executed over a batch of targets, comparisons of number
+// fields with constants run over the field's values extracted into a slice.
+pub struct CompiledExpression {
+    expression: Expression,
+    node: Node,
+}
+
+impl CompiledExpression {
+    pub fn get_expression(&self) -> &Expression {
+        &self.expression
+    }
+
+    pub(crate) fn execute<T>(
+        &self,
+        target: &T,
+        fields: &Fields<T>,
+    ) -> Result<bool, ExecutionError> {
+        self.node.execute(target, fields)
+    }
+
+    pub(crate) fn execute_batch<T>(
+        &self,
+        targets: &[T],
+        fields: &Fields<T>,
+    ) -> Result<Vec<bool>, ExecutionError> {
+        self.node.execute_batch(
+            targets,
+            fields,
+            &vec![true; targets.len()],
+            &mut Columns::default(),
+        )
+    }
+}
+
+enum Node {
+    And(Vec<Node>),
+    Or(Vec<Node>),
+    Not(Box<Node>),
+    Operation(Operation),
+    // The operation is kept for values that can't be in the set, so they fail
+    // like they would against the list.
+    In(Operation, HashSet<Value>),
+    // A number field compared with a constant, see `Threshold`.
+    Compare(Operation, Threshold),
+    Reference(String),
+}
+
+// `field op value`, with the operator flipped if the constant came first.
+struct Threshold {
+    field_name: FieldName,
+    op: Operator,
+    value: f64,
+}
+
+impl Threshold {
+    // Nulls are extracted as NaN, which compares the same way: unequal to
+    // every number and neither greater nor less.
+    fn compare(&self, values: &[f64]) -> Vec<bool> {
+        let value = self.value;
+        match self.op {
+            Operator::Eq => values.iter().map(|v| *v == value).collect(),
+            Operator::Ne => values.iter().map(|v| *v != value).collect(),
+            Operator::Gt => values.iter().map(|v| *v > value).collect(),
+            Operator::Gte => values.iter().map(|v| *v >= value).collect(),
+            Operator::Lt => values.iter().map(|v| *v < value).collect(),
+            Operator::Lte => values.iter().map(|v| *v <= value).collect(),
+            Operator::In => unreachable!(),
+        }
+    }
+}
+
+// Number fields extracted for a batch, shared by the comparisons on the same
+// field. `None` when a target has a value that isn't a number, in which case
+// the comparisons fall back to executing on each target.
+#[derive(Default)]
+struct Columns<'a> {
+    columns: Vec<(&'a str, Option<Vec<f64>>)>,
+}
+
+impl<'a> Columns<'a> {
+    fn get<T>(&mut self, field_name: &'a str, targets: &[T], fields: &Fields<T>) -> Option<&[f64]> {
+        let index = match self
+            .columns
+            .iter()
+            .position(|(name, _)| *name == field_name)
+        {
+            Some(index) => index,
+            None => {
+                let values = fields.get_field(field_name).and_then(|field| {
+                    targets
+                        .iter()
+                        .map(|target| match (field.field_extractor)(target) {
+                            Value::Number(num) => Some(num),
+                            Value::Null => Some(f64::NAN),
+                            _ => None,
+                        })
+                        .collect()
+                });
+                self.columns.push((field_name, values));
+
+                self.columns.len() - 1
+            }
+        };
+
+        self.columns[index].1.as_deref()
+    }
+}
+
+impl Node {
+    fn execute<T>(&self, target: &T, fields: &Fields<T>) -> Result<bool, ExecutionError> {
+        match self {
+            Node::And(nodes) => {
+                for node in nodes {
+                    if !node.execute(target, fields)? {
+                        return Ok(false);
+                    }
+                }
+
+                Ok(true)
+            }
+            Node::Or(nodes) => {
+                for node in nodes {
+                    if node.execute(target, fields)? {
+                        return Ok(true);
+                    }
+                }
+
+                Ok(false)
+            }
+            Node::Not(node) => node.execute(target, fields).map(|result| !result),
+            Node::Operation(operation) | Node::Compare(operation, _) => {
+                execute_operation(operation, target, fields)
+            }
+            Node::Reference(rule_name) => {
+                Err(ExecutionError::UnresolvedReferenceError(rule_name.clone()))
+            }
+            Node::In(operation, set) => {
+                let value = extract_literal(&operation.lhs, target, fields)?;
+                match (&value, &operation.rhs) {
+                    (Value::Null, _) => Ok(false),
+                    (Value::String(_), Literal::LiteralValue(Value::StringList(_)))
+                    | (Value::Number(_), Literal::LiteralValue(Value::NumberList(_))) => {
+                        Ok(hash_key(value).is_some_and(|key| set.contains(&key)))
+                    }
+                    #[cfg(feature = "raw")]
+                    (Value::Raw(_), Literal::LiteralValue(Value::RawList(_))) => {
+                        Ok(set.contains(&value))
+                    }
+                    _ => execute_operation(operation, target, fields),
+                }
+            }
+        }
+    }
+
+    // Executes on the targets where `active` is set, as if each was executed on
+    // its own, so groups short-circuit and errors come from the same clauses.
+    // The results for the other targets are unspecified.
+    fn execute_batch<'a, T>(
+        &'a self,
+        targets: &[T],
+        fields: &Fields<T>,
+        active: &[bool],
+        columns: &mut Columns<'a>,
+    ) -> Result<Vec<bool>, ExecutionError> {
+        match self {
+            Node::And(nodes) => {
+                let mut results = active.to_vec();
+                for node in nodes {
+                    if !results.contains(&true) {
+                        break;
+                    }
+                    let next = node.execute_batch(targets, fields, &results, columns)?;
+                    for (result, next) in results.iter_mut().zip(next) {
+                        *result &= next;
+                    }
+                }
+
+                Ok(results)
+            }
+            Node::Or(nodes) => {
+                let mut results = vec![false; targets.len()];
+                let mut remaining = active.to_vec();
+                for node in nodes {
+                    if !remaining.contains(&true) {
+                        break;
+                    }
+                    let next = node.execute_batch(targets, fields, &remaining, columns)?;
+                    for ((result, remaining), next) in
+                        results.iter_mut().zip(remaining.iter_mut()).zip(next)
+                    {
+                        *result |= *remaining && next;
+                        *remaining &= !next;
+                    }
+                }
+
+                Ok(results)
+            }
+            Node::Not(node) => Ok(node
+                .execute_batch(targets, fields, active, columns)?
+                .into_iter()
+                .map(|result| !result)
+                .collect()),
+            Node::Compare(_, threshold) => {
+                match columns.get(&threshold.field_name, targets, fields) {
+                    Some(values) => Ok(threshold.compare(values)),
+                    None => self.execute_each(targets, fields, active),
+                }
+            }
+            _ => self.execute_each(targets, fields, active),
+        }
+    }
+
+    fn execute_each<T>(
+        &self,
+        targets: &[T],
+        fields: &Fields<T>,
+        active: &[bool],
+    ) -> Result<Vec<bool>, ExecutionError> {
+        targets
+            .iter()
+            .zip(active)
+            .map(|(target, active)| match active {
+                true => self.execute(target, fields),
+                false => Ok(false),
+            })
+            .collect()
+    }
+}
+
+// Expects a validated expression, so nesting is bounded.
+pub(crate) fn compile(expression: &Expression) -> CompiledExpression {
+    CompiledExpression {
+        expression: expression.clone(),
+        node: compile_node(expression),
+    }
+}
+
+fn compile_node(expression: &Expression) -> Node {
+    match expression {
+        Expression::And(and) => {
+            Node::And(and.get_subexpressions().iter().map(compile_node).collect())
+        }
+        Expression::Or(or) => Node::Or(or.get_subexpressions().iter().map(compile_node).collect()),
+        Expression::Not(not) => Node::Not(Box::new(compile_node(not.get_subexpression()))),
+        Expression::Operation(operation) => match (&operation.op, &operation.rhs) {
+            (Operator::In, Literal::LiteralValue(list)) => match set(list) {
+                Some(set) => Node::In(operation.clone(), set),
+                None => Node::Operation(operation.clone()),
+            },
+            _ => match threshold(operation) {
+                Some(threshold) => Node::Compare(operation.clone(), threshold),
+                None => Node::Operation(operation.clone()),
+            },
+        },
+        Expression::Reference(reference) => {
+            Node::Reference(String::from(reference.get_rule_name()))
+        }
+    }
+}
+
+fn set(list: &Value) -> Option<HashSet<Value>> {
+    let items: Vec<Value> = match list {
+        Value::StringList(items) if items.len() >= SET_THRESHOLD => {
+            items.iter().cloned().map(Value::String).collect()
+        }
+        Value::NumberList(items) if items.len() >= SET_THRESHOLD => {
+            items.iter().copied().map(Value::Number).collect()
+        }
+        #[cfg(feature = "raw")]
+        Value::RawList(items) if items.len() >= SET_THRESHOLD => {
+            items.iter().cloned().map(Value::Raw).collect()
+        }
+        _ => return None,
+    };
+
+    Some(items.into_iter().filter_map(hash_key).collect())
+}
+
+fn threshold(operation: &Operation) -> Option<Threshold> {
+    let (field_name, op, value) = match (&operation.lhs, &operation.rhs) {
+        (Literal::LiteralField(field_name), Literal::LiteralValue(Value::Number(value))) => {
+            (field_name, operation.op.clone(), *value)
+        }
+        (Literal::LiteralValue(Value::Number(value)), Literal::LiteralField(field_name)) => {
+            let op = match operation.op {
+                Operator::Gt => Operator::Lt,
+                Operator::Gte => Operator::Lte,
+                Operator::Lt => Operator::Gt,
+                Operator::Lte => Operator::Gte,
+                ref op => op.clone(),
+            };
+
+            (field_name, op, *value)
+        }
+        _ => return None,
+    };
+
+    match op {
+        Operator::In => None,
+        op => Some(Threshold {
+            field_name: field_name.clone(),
+            op,
+            value,
+        }),
+    }
+}
@@ -0,0 +1,309 @@
+use crate::{
+    descriptor::SchemaDescriptor,
+    engine,
+    expression::{Expression, FieldName, Literal, Operation, Operator},
+    highlight::{Span, TokenClass, highlight},
+    schema::Type,
+};
+
+pub(crate) const OPERATORS: [Operator; 7] = [
+    Operator::Eq,
+    Operator::Ne,
+    Operator::Gt,
+    Operator::Gte,
+    Operator::Lt,
+    Operator::Lte,
+    Operator::In,
+];
+
+pub(crate) const TYPES: &[Type] = &[
+    Type::String,
+    #[cfg(feature = "regex")]
+    Type::Regex,
+    Type::Number,
+    Type::Boolean,
+    #[cfg(feature = "raw")]
+    Type::Raw,
+    #[cfg(feature = "chrono")]
+    Type::DateTime,
+    Type::StringList,
+    Type::NumberList,
+    Type::BooleanList,
+    #[cfg(feature = "raw")]
+    Type::RawList,
+    #[cfg(feature = "chrono")]
+    Type::DateTimeList,
+    Type::Null,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SuggestionKind {
+    Field,
+    Operator,
+    Keyword,
+    // A literal skeleton such as `""` or a datetime to fill in
+    Template,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Suggestion {
+    pub label: String,
+    pub kind: SuggestionKind,
+    // Replaces `span`, which covers the partial word before the cursor.
+    pub insert_text: String,
+    pub span: Span,
+    pub detail: Option<String>,
+}
+
+// Where the cursor is in the grammar, judged from the tokens before it.
+#[derive(Clone)]
+enum Context {
+    // An operand, with the left-hand side type and operator when it is a
+    // right-hand side. The type is None when it isn't known.
+    Operand(Option<(Option<Type>, Operator)>),
+    Operator(Option<Type>),
+    Connective,
+}
+
+// Suggestions for the text at `cursor` (a byte offset) in a partial expression:
+// fields, operators valid for the left-hand side's type, and literal templates
+// for the types the operator accepts.
+pub fn complete(input: &str, cursor: usize, descriptor: &SchemaDescriptor) -> Vec<Suggestion> {
+    let cursor = cursor.min(input.len());
+    let tokens = highlight(&input[..cursor]);
+
+    // A word or operator touching the cursor is what is being typed.
+    let (tokens, span) = match tokens.split_last() {
+        Some(((span, class), rest))
+            if span.end == cursor
+                && matches!(
+                    class,
+                    TokenClass::Field
+                        | TokenClass::Keyword
+                        | TokenClass::Operator
+                        | TokenClass::Invalid
+                ) =>
+        {
+            (rest, span.clone())
+        }
+        _ => (&tokens[..], cursor..cursor),
+    };
+    let prefix = &input[span.clone()];
+
+    let field_type = |field_name: &str| {
+        descriptor
+            .get_field(field_name)
+            .map(|field| field.field_type)
+    };
+
+    let mut suggestions = Vec::new();
+    let mut push = |label: &str, kind, insert_text: &str, detail: Option<&str>| {
+        if label
+            .to_ascii_lowercase()
+            .starts_with(&prefix.to_ascii_lowercase())
+        {
+            suggestions.push(Suggestion {
+                label: String::from(label),
+                kind,
+                insert_text: String::from(insert_text),
+                span: span.clone(),
+                detail: detail.map(String::from),
+            });
+        }
+    };
+
+    match context(input, tokens, &field_type) {
+        Context::Operand(rhs_of) => {
+            let accepts = |rhs: Type| match &rhs_of {
+                Some((Some(lhs), op)) => is_valid(*lhs, op, rhs),
+                _ => true,
+            };
+
+            for field in &descriptor.fields {
+                if accepts(field.field_type) {
+                    let detail = field.field_type.variant_name();
+                    push(
+                        &field.name,
+                        SuggestionKind::Field,
+                        &field.name,
+                        Some(detail),
+                    );
+                }
+            }
+
+            if rhs_of.is_some() {
+                for &rhs in TYPES {
+                    if accepts(rhs) {
+                        for template in templates(rhs) {
+                            let detail = rhs.variant_name();
+                            push(template, SuggestionKind::Template, template, Some(detail));
+                        }
+                    }
+                }
+            } else {
+                push("(", SuggestionKind::Keyword, "(", None);
+                push("!(", SuggestionKind::Keyword, "!(", None);
+            }
+        }
+        Context::Operator(lhs) => {
+            for op in OPERATORS {
+                let valid = match lhs {
+                    Some(lhs) => TYPES
+                        .iter()
+                        .any(|rhs| !rhs.is_null() && is_valid(lhs, &op, *rhs)),
+                    None => true,
+                };
+
+                if valid {
+                    push(
+                        op.fmt_static(),
+                        SuggestionKind::Operator,
+                        op.fmt_static(),
+                        None,
+                    );
+                }
+            }
+        }
+        Context::Connective => {
+            push("and", SuggestionKind::Keyword, "and", None);
+            push("or", SuggestionKind::Keyword, "or", None);
+            push(")", SuggestionKind::Keyword, ")", None);
+        }
+    }
+
+    suggestions
+}
+
+fn context(
+    input: &str,
+    tokens: &[(Span, TokenClass)],
+    field_type: &dyn Fn(&str) -> Option<Type>,
+) -> Context {
+    let mut context = Context::Operand(None);
+    // The context before the last operand, which operators combining operands
+    // continue.
+    let mut operand_start = Context::Operand(None);
+    let mut tokens = tokens.iter();
+    while let Some((span, class)) = tokens.next() {
+        let text = &input[span.clone()];
+        // A bracket after an operand slices it, keeping its context.
+        if text == "[" && !matches!(context, Context::Operand(_)) {
+            tokens
+                .by_ref()
+                .find(|(span, _)| &input[span.clone()] == "]");
+            continue;
+        }
+        let operand = match class {
+            TokenClass::Field => Some(field_type(text)),
+            TokenClass::String => Some(Some(Type::String)),
+            #[cfg(feature = "regex")]
+            TokenClass::Regex => Some(Some(Type::Regex)),
+            TokenClass::Number => Some(Some(Type::Number)),
+            #[cfg(feature = "raw")]
+            TokenClass::Raw => Some(Some(Type::Raw)),
+            #[cfg(feature = "chrono")]
+            TokenClass::DateTime => Some(Some(Type::DateTime)),
+            TokenClass::Keyword if text.eq_ignore_ascii_case("null") => Some(Some(Type::Null)),
+            TokenClass::Keyword
+                if text.eq_ignore_ascii_case("true") || text.eq_ignore_ascii_case("false") =>
+            {
+                Some(Some(Type::Boolean))
+            }
+            TokenClass::Punctuation if text == "[" => {
+                // A list is typed by its first item.
+                let items = tokens
+                    .by_ref()
+                    .take_while(|(span, _)| &input[span.clone()] != "]")
+                    .collect::<Vec<&(Span, TokenClass)>>();
+                let item = items
+                    .into_iter()
+                    .find(|(_, class)| *class != TokenClass::Punctuation);
+
+                Some(item.and_then(|(span, class)| match class {
+                    TokenClass::String => Some(Type::StringList),
+                    TokenClass::Number => Some(Type::NumberList),
+                    #[cfg(feature = "raw")]
+                    TokenClass::Raw => Some(Type::RawList),
+                    #[cfg(feature = "chrono")]
+                    TokenClass::DateTime => Some(Type::DateTimeList),
+                    TokenClass::Keyword if !input[span.clone()].eq_ignore_ascii_case("null") => {
+                        Some(Type::BooleanList)
+                    }
+                    _ => None,
+                }))
+            }
+            _ => None,
+        };
+
+        context = match (operand, class) {
+            (Some(operand_type), _) => {
+                operand_start = context.clone();
+                match context {
+                    Context::Operand(None) => Context::Operator(operand_type),
+                    _ => Context::Connective,
+                }
+            }
+            (None, TokenClass::Operator) => {
+                let op = OPERATORS
+                    .into_iter()
+                    .find(|op| op.fmt_static().eq_ignore_ascii_case(text));
+                let lhs = match context {
+                    Context::Operator(lhs) => lhs,
+                    _ => None,
+                };
+
+                match op {
+                    Some(op) => Context::Operand(Some((lhs, op))),
+                    None => operand_start.clone(),
+                }
+            }
+            (None, TokenClass::Punctuation) if text == ")" => Context::Connective,
+            (None, TokenClass::Reference) => Context::Connective,
+            // `(`, `!`, `and` and `or` all start a new operand.
+            _ => Context::Operand(None),
+        };
+    }
+
+    context
+}
+
+// Validation decides which operand types fit, through placeholder fields of
+// the given types, so the rules stay in one place.
+pub(crate) fn is_valid(lhs: Type, op: &Operator, rhs: Type) -> bool {
+    let operation = Operation::new(
+        Literal::LiteralField(FieldName::new_static("lhs")),
+        op.clone(),
+        Literal::LiteralField(FieldName::new_static("rhs")),
+    );
+
+    engine::validate(
+        &Expression::Operation(operation),
+        &|field_name| match field_name {
+            "lhs" => Some(lhs),
+            _ => Some(rhs),
+        },
+    )
+    .is_ok()
+}
+
+fn templates(value_type: Type) -> &'static [&'static str] {
+    match value_type {
+        Type::String => &["\"\""],
+        #[cfg(feature = "regex")]
+        Type::Regex => &["//"],
+        Type::Number => &["0"],
+        Type::Boolean => &["true", "false"],
+        #[cfg(feature = "raw")]
+        Type::Raw => &["|00|"],
+        #[cfg(feature = "chrono")]
+        Type::DateTime => &["2000-01-01T00:00:00Z"],
+        Type::StringList => &["[\"\"]"],
+        Type::NumberList => &["[0]"],
+        Type::BooleanList => &["[true, false]"],
+        #[cfg(feature = "raw")]
+        Type::RawList => &["[|00|]"],
+        #[cfg(feature = "chrono")]
+        Type::DateTimeList => &["[2000-01-01T00:00:00Z, 2000-01-01T00:00:00Z]"],
+        Type::Null => &["null"],
+    }
+}
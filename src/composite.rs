@@ -0,0 +1,132 @@
+use alloc::{boxed::Box, format, rc::Rc, string::String, vec::Vec};
+
+use crate::{
+    conjuncts::referenced_fields,
+    engine::{self, ExecutionError, FieldList, ValidationError},
+    expression::{Expression, FieldName},
+    schema::{Field, Schema, Type},
+};
+
+// Validates and executes expressions against pairs of related targets, e.g. a
+// user and their order, without merging them into one struct. The fields of
+// each schema are named after its prefix, `user:country` is the `country` field
+// of the first target when its prefix is `user`. The prefixes must differ.
+pub struct CompositeEngine<A, B> {
+    first: Part<A>,
+    second: Part<B>,
+}
+
+struct Part<T> {
+    prefix: String,
+    schema: Rc<Schema<T>>,
+}
+
+impl<T> Part<T> {
+    fn new(prefix: &str, schema: Schema<T>) -> Self {
+        Self {
+            prefix: String::from(prefix),
+            schema: Rc::new(schema),
+        }
+    }
+
+    // The name of the field in the schema, if `field_name` has the prefix.
+    fn strip_prefix<'a>(&self, field_name: &'a str) -> Option<&'a str> {
+        field_name
+            .strip_prefix(self.prefix.as_str())?
+            .strip_prefix(':')
+    }
+
+    fn get_field(&self, field_name: &str) -> Option<&Field<T>> {
+        self.schema.get_field(self.strip_prefix(field_name)?)
+    }
+
+    fn get_field_names(&self) -> impl Iterator<Item = String> + '_ {
+        self.schema
+            .get_field_names()
+            .map(|field_name| format!("{}:{}", self.prefix, field_name))
+    }
+}
+
+impl<A: 'static, B: 'static> CompositeEngine<A, B> {
+    pub fn new(
+        first_prefix: &str,
+        first: Schema<A>,
+        second_prefix: &str,
+        second: Schema<B>,
+    ) -> Self {
+        Self {
+            first: Part::new(first_prefix, first),
+            second: Part::new(second_prefix, second),
+        }
+    }
+
+    pub fn get_field_type(&self, field_name: &str) -> Option<Type> {
+        self.first
+            .get_field(field_name)
+            .map(|field| field.field_type)
+            .or_else(|| {
+                self.second
+                    .get_field(field_name)
+                    .map(|field| field.field_type)
+            })
+    }
+
+    // The prefixed names of the fields of both schemas.
+    pub fn get_field_names(&self) -> Vec<String> {
+        self.first
+            .get_field_names()
+            .chain(self.second.get_field_names())
+            .collect()
+    }
+
+    pub fn validate(&self, expression: &Expression) -> Result<(), ValidationError> {
+        engine::validate(expression, &|field_name| self.get_field_type(field_name)).map_err(|e| {
+            let field_names = self.get_field_names();
+            engine::suggest_field(e, field_names.iter().map(String::as_str))
+        })
+    }
+
+    pub fn execute(
+        &self,
+        expression: &Expression,
+        targets: (&A, &B),
+    ) -> Result<bool, ExecutionError> {
+        // Extractors for the fields of the expression, reading from either
+        // target.
+        let fields = referenced_fields(expression)
+            .into_iter()
+            .filter_map(|field_name| {
+                let field = self.field(&field_name)?;
+
+                Some((FieldName::from(field_name), field))
+            })
+            .collect();
+
+        engine::execute(expression, &targets, &FieldList(fields))
+    }
+
+    fn field<'a>(&self, field_name: &str) -> Option<Field<(&'a A, &'a B)>> {
+        if let Some(field) = self.first.get_field(field_name) {
+            let schema = Rc::clone(&self.first.schema);
+            let name = FieldName::from(self.first.strip_prefix(field_name)?);
+
+            return Some(Field::new(
+                field.field_type,
+                Box::new(move |(first, _): &(&A, &B)| {
+                    (schema.get_field(&name).unwrap().field_extractor)(first)
+                }),
+            ));
+        }
+
+        let field = self.second.get_field(field_name)?;
+        let schema = Rc::clone(&self.second.schema);
+        let name = FieldName::from(self.second.strip_prefix(field_name)?);
+
+        Some(Field::new(
+            field.field_type,
+            Box::new(move |(_, second): &(&A, &B)| {
+                (schema.get_field(&name).unwrap().field_extractor)(second)
+            }),
+        ))
+    }
+}
@@ -0,0 +1,393 @@
+use alloc::{string::String, vec::Vec};
+
+use crate::{
+    engine::{
+        ExecutionError, Fields, ValidationError, execute_at, extract_literal_at, literal_type_at,
+        validate_at,
+    },
+    expression::{BitwiseOperator, Computed, Literal, MAX_INTEGER},
+    schema::{Type, Value},
+};
+
+// `depth` is that of the operation the computation is an operand of, the
+// conditions of `if()` are nested one level below it.
+pub(crate) fn extract_computed<T>(
+    computed: &Computed,
+    target: &T,
+    fields: &Fields<T>,
+    depth: usize,
+) -> Result<Value, ExecutionError> {
+    match computed {
+        Computed::Bitwise(first, rest) => {
+            let Some(mut result) = extract_integer(first, target, fields, depth)? else {
+                return Ok(Value::Null);
+            };
+            for (op, operand) in rest {
+                let Some(value) = extract_integer(operand, target, fields, depth)? else {
+                    return Ok(Value::Null);
+                };
+                result = bitwise(result, *op, value)?;
+            }
+            if !in_range(result) {
+                return Err(ExecutionError::IntegerOutOfRangeError);
+            }
+
+            Ok(Value::Number(result as f64))
+        }
+        Computed::Concat(operands) => {
+            let mut result = String::new();
+            for operand in operands {
+                match extract_literal_at(operand, target, fields, depth)? {
+                    Value::String(val) => result.push_str(&val),
+                    Value::Null => return Ok(Value::Null),
+                    value => {
+                        return Err(ExecutionError::InvalidOperandError(
+                            "concatenation",
+                            value.get_type(),
+                        ));
+                    }
+                }
+            }
+
+            Ok(Value::String(result))
+        }
+        // Later operands are only extracted when the earlier ones are null.
+        Computed::Coalesce(operands) => {
+            for operand in operands {
+                let value = extract_literal_at(operand, target, fields, depth)?;
+                if !value.is_null() {
+                    return Ok(value);
+                }
+            }
+
+            Ok(Value::Null)
+        }
+        Computed::If(condition, then, otherwise) => {
+            if execute_at(condition, target, fields, depth + 1)? {
+                extract_literal_at(then, target, fields, depth)
+            } else {
+                extract_literal_at(otherwise, target, fields, depth)
+            }
+        }
+        Computed::First(operand) => list_item(
+            extract_literal_at(operand, target, fields, depth)?,
+            End::First,
+        ),
+        Computed::Last(operand) => list_item(
+            extract_literal_at(operand, target, fields, depth)?,
+            End::Last,
+        ),
+        Computed::Slice(operand, start, end) => {
+            let (start, end) = (*start, *end);
+
+            Ok(match extract_literal_at(operand, target, fields, depth)? {
+                Value::StringList(items) => Value::StringList(slice(items, start, end)),
+                Value::NumberList(items) => Value::NumberList(slice(items, start, end)),
+                Value::BooleanList(items) => Value::BooleanList(slice(items, start, end)),
+                #[cfg(feature = "raw")]
+                Value::RawList(items) => Value::RawList(slice(items, start, end)),
+                #[cfg(feature = "chrono")]
+                Value::DateTimeList(items) => Value::DateTimeList(slice(items, start, end)),
+                Value::Null => Value::Null,
+                value => {
+                    return Err(ExecutionError::InvalidOperandError(
+                        "slicing",
+                        value.get_type(),
+                    ));
+                }
+            })
+        }
+    }
+}
+
+// The end of a list that `first()` and `last()` take their item from.
+#[derive(Clone, Copy)]
+enum End {
+    First,
+    Last,
+}
+
+impl End {
+    fn pick<I>(self, items: Vec<I>) -> Option<I> {
+        match self {
+            End::First => items.into_iter().next(),
+            End::Last => items.into_iter().next_back(),
+        }
+    }
+
+    // The name of the function, for errors.
+    fn name(self) -> &'static str {
+        match self {
+            End::First => "first",
+            End::Last => "last",
+        }
+    }
+}
+
+fn list_item(value: Value, end: End) -> Result<Value, ExecutionError> {
+    Ok(match value {
+        Value::StringList(items) => end.pick(items).map_or(Value::Null, Value::String),
+        Value::NumberList(items) => end.pick(items).map_or(Value::Null, Value::Number),
+        Value::BooleanList(items) => end.pick(items).map_or(Value::Null, Value::Boolean),
+        #[cfg(feature = "raw")]
+        Value::RawList(items) => end.pick(items).map_or(Value::Null, Value::Raw),
+        #[cfg(feature = "chrono")]
+        Value::DateTimeList(items) => end.pick(items).map_or(Value::Null, Value::DateTime),
+        Value::Null => Value::Null,
+        value => {
+            return Err(ExecutionError::InvalidOperandError(
+                end.name(),
+                value.get_type(),
+            ));
+        }
+    })
+}
+
+fn slice<I>(mut items: Vec<I>, start: usize, end: usize) -> Vec<I> {
+    items.truncate(end);
+    items.drain(..start.min(items.len()));
+
+    items
+}
+
+// None for null operands, which make the whole computation null like a missing
+// field does in comparisons.
+fn extract_integer<T>(
+    literal: &Literal,
+    target: &T,
+    fields: &Fields<T>,
+    depth: usize,
+) -> Result<Option<i64>, ExecutionError> {
+    match extract_literal_at(literal, target, fields, depth)? {
+        Value::Number(num) => match to_integer(num) {
+            Ok(integer) => Ok(Some(integer)),
+            Err(IntegerError::Fraction) => Err(ExecutionError::NotAnIntegerError(num)),
+            Err(IntegerError::OutOfRange) => Err(ExecutionError::IntegerOutOfRangeError),
+        },
+        Value::Null => Ok(None),
+        value => Err(ExecutionError::InvalidOperandError(
+            "bitwise operators",
+            value.get_type(),
+        )),
+    }
+}
+
+fn bitwise(lhs: i64, op: BitwiseOperator, rhs: i64) -> Result<i64, ExecutionError> {
+    let shift = || {
+        u32::try_from(rhs)
+            .ok()
+            .filter(|bits| *bits < i64::BITS)
+            .ok_or(ExecutionError::ShiftOutOfRangeError(rhs))
+    };
+
+    Ok(match op {
+        BitwiseOperator::And => lhs & rhs,
+        BitwiseOperator::Or => lhs | rhs,
+        BitwiseOperator::Xor => lhs ^ rhs,
+        BitwiseOperator::ShiftLeft => lhs << shift()?,
+        BitwiseOperator::ShiftRight => lhs >> shift()?,
+    })
+}
+
+enum IntegerError {
+    Fraction,
+    OutOfRange,
+}
+
+// Numbers are floats, which hold integers exactly only up to `MAX_INTEGER`.
+// NaN fails as a fraction.
+fn to_integer(num: f64) -> Result<i64, IntegerError> {
+    let limit = MAX_INTEGER as f64;
+    if num < -limit || num > limit {
+        Err(IntegerError::OutOfRange)
+    } else if num as i64 as f64 != num {
+        Err(IntegerError::Fraction)
+    } else {
+        Ok(num as i64)
+    }
+}
+
+fn in_range(integer: i64) -> bool {
+    (-MAX_INTEGER..=MAX_INTEGER).contains(&integer)
+}
+
+pub(crate) fn computed_type(
+    computed: &Computed,
+    field_type: &dyn Fn(&str) -> Option<Type>,
+    depth: usize,
+) -> Result<Type, ValidationError> {
+    match computed {
+        Computed::Bitwise(_, _) => {
+            for operand in computed.get_operands() {
+                if let Literal::LiteralValue(Value::Number(num)) = operand {
+                    match to_integer(*num) {
+                        Ok(_) => {}
+                        Err(IntegerError::Fraction) => {
+                            return Err(ValidationError::NotAnIntegerError(*num));
+                        }
+                        Err(IntegerError::OutOfRange) => {
+                            return Err(ValidationError::IntegerOutOfRangeError);
+                        }
+                    }
+                }
+                match literal_type_at(operand, field_type, depth)? {
+                    Type::Number | Type::Null => {}
+                    operand_type => {
+                        return Err(ValidationError::InvalidOperandError(
+                            "bitwise operators",
+                            operand_type,
+                        ));
+                    }
+                }
+            }
+
+            Ok(Type::Number)
+        }
+        Computed::Concat(operands) => {
+            for operand in operands {
+                match literal_type_at(operand, field_type, depth)? {
+                    Type::String | Type::Null => {}
+                    operand_type => {
+                        return Err(ValidationError::InvalidOperandError(
+                            "concatenation",
+                            operand_type,
+                        ));
+                    }
+                }
+            }
+
+            Ok(Type::String)
+        }
+        Computed::Coalesce(operands) => common_type(operands.iter(), field_type, depth),
+        Computed::If(condition, then, otherwise) => {
+            validate_at(condition, field_type, depth + 1)?;
+            common_type([then, otherwise].into_iter(), field_type, depth)
+        }
+        Computed::First(operand) => item_type(
+            literal_type_at(operand, field_type, depth)?,
+            End::First.name(),
+        ),
+        Computed::Last(operand) => item_type(
+            literal_type_at(operand, field_type, depth)?,
+            End::Last.name(),
+        ),
+        Computed::Slice(operand, _, _) => {
+            let list_type = literal_type_at(operand, field_type, depth)?;
+            item_type(list_type, "slicing")?;
+
+            Ok(list_type)
+        }
+    }
+}
+
+// `label` names the operation in errors.
+fn item_type(list_type: Type, label: &'static str) -> Result<Type, ValidationError> {
+    Ok(match list_type {
+        Type::StringList => Type::String,
+        Type::NumberList => Type::Number,
+        Type::BooleanList => Type::Boolean,
+        #[cfg(feature = "raw")]
+        Type::RawList => Type::Raw,
+        #[cfg(feature = "chrono")]
+        Type::DateTimeList => Type::DateTime,
+        Type::Null => Type::Null,
+        list_type => return Err(ValidationError::InvalidOperandError(label, list_type)),
+    })
+}
+
+// Every operand that can't be null must have the same type.
+fn common_type<'a>(
+    operands: impl Iterator<Item = &'a Literal>,
+    field_type: &dyn Fn(&str) -> Option<Type>,
+    depth: usize,
+) -> Result<Type, ValidationError> {
+    let mut result = Type::Null;
+    for operand in operands {
+        let operand_type = literal_type_at(operand, field_type, depth)?;
+        if result.is_null() {
+            result = operand_type;
+        } else if !operand_type.is_null() && operand_type != result {
+            return Err(ValidationError::IncompatibleOperandsError(
+                result,
+                operand_type,
+            ));
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::{Engine, Parser, SchemaBuilder, parser::ParseError};
+
+    use super::*;
+
+    fn engine() -> Engine<f64> {
+        Engine::new(
+            SchemaBuilder::<f64>::new()
+                .with_number_field("x", |x| Some(*x))
+                .build(),
+        )
+    }
+
+    fn execute(input: &str, x: f64) -> Result<bool, ExecutionError> {
+        engine().execute(&Parser::parse(input).unwrap(), &x)
+    }
+
+    const LIMIT: f64 = MAX_INTEGER as f64;
+
+    #[test]
+    fn hex_literals_stop_at_2_pow_53() {
+        assert_eq!(
+            Parser::parse("x == 0x20000000000000").unwrap(),
+            Parser::parse("x == 9007199254740992").unwrap()
+        );
+        for input in ["x == 0x20000000000001", "(x & 0xffffffffffffffffff) == 1"] {
+            assert!(matches!(
+                Parser::parse(input),
+                Err(ParseError::HexOutOfRangeError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn operands_stop_at_2_pow_53() {
+        assert!(execute("(x & 1) == 0", LIMIT).unwrap());
+        assert!(execute("(x & 1) == 0", -LIMIT).unwrap());
+        assert!(matches!(
+            execute("(x & 1) == 0", LIMIT + 2.0),
+            Err(ExecutionError::IntegerOutOfRangeError)
+        ));
+        assert!(matches!(
+            execute("(x & 1) == 0", 1e300),
+            Err(ExecutionError::IntegerOutOfRangeError)
+        ));
+        assert!(matches!(
+            execute("(x & 1) == 0", 0.5),
+            Err(ExecutionError::NotAnIntegerError(_))
+        ));
+
+        let expression = Parser::parse("(x & 1e16) == 0").unwrap();
+        assert!(matches!(
+            engine().validate(&expression),
+            Err(ValidationError::IntegerOutOfRangeError)
+        ));
+        let expression = Parser::parse("(x & 0x20000000000000) == 0").unwrap();
+        engine().validate(&expression).unwrap();
+    }
+
+    #[test]
+    fn results_stop_at_2_pow_53() {
+        assert!(execute("(x << 1) == 0x20000000000000", LIMIT / 2.0).unwrap());
+        assert!(matches!(
+            execute("(x << 1) == 0", LIMIT),
+            Err(ExecutionError::IntegerOutOfRangeError)
+        ));
+        assert!(matches!(
+            execute("(x | 1) == 0", LIMIT),
+            Err(ExecutionError::IntegerOutOfRangeError)
+        ));
+        assert!(execute("(x | 1) == 0x1fffffffffffff", LIMIT - 2.0).unwrap());
+    }
+}
@@ -0,0 +1,53 @@
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::expression::{And, Expression, Literal};
+
+// Top-level conjuncts keyed by the set of fields each references. Nested Ands
+// are flattened, so `(a == 1 AND (b == 2 AND a > 0))` yields `{a}` with two
+// conjuncts and `{b}` with one. Conjuncts without fields use the empty set.
+pub fn split_conjuncts(expression: &Expression) -> BTreeMap<BTreeSet<String>, Vec<Expression>> {
+    let mut groups: BTreeMap<BTreeSet<String>, Vec<Expression>> = BTreeMap::new();
+    for conjunct in conjuncts(expression) {
+        groups
+            .entry(referenced_fields(conjunct))
+            .or_default()
+            .push(conjunct.clone());
+    }
+
+    groups
+}
+
+pub(crate) fn conjuncts(expression: &Expression) -> Vec<&Expression> {
+    match expression {
+        Expression::And(and) => and
+            .get_subexpressions()
+            .iter()
+            .flat_map(conjuncts)
+            .collect(),
+        expression => vec![expression],
+    }
+}
+
+pub fn referenced_fields(expression: &Expression) -> BTreeSet<String> {
+    expression
+        .operations()
+        .flat_map(|operation| [&operation.lhs, &operation.rhs])
+        .flat_map(Literal::get_field_operands)
+        .map(|(field_name, _)| field_name.to_string())
+        .collect()
+}
+
+// The inverse of splitting: ANDs the conjuncts back together, or returns None
+// when there are none left.
+pub fn join_conjuncts(mut conjuncts: Vec<Expression>) -> Option<Expression> {
+    match conjuncts.len() {
+        0 => None,
+        1 => conjuncts.pop(),
+        _ => Some(Expression::And(And::new(conjuncts))),
+    }
+}
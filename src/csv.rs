@@ -0,0 +1,87 @@
+use chrono::{DateTime, Utc};
+use csv::StringRecord;
+use thiserror::Error;
+
+use crate::schema::{Schema, SchemaBuilder, Type};
+
+#[derive(Error, Debug)]
+pub enum CsvError {
+    #[error("The CSV file has no column '{0}'")]
+    MissingColumnError(String),
+    #[error("The field '{0}' is a {1}, which CSV cells can't hold")]
+    UnsupportedTypeError(String, &'static str),
+}
+
+// A schema over the rows of a CSV file, with the fields read from the columns
+// of the same name in `headers`. Cells are parsed into the type of their
+// field: numbers as decimals, booleans as `true` or `false` in any case, raw
+// values as hex digits and datetimes as RFC 3339. Empty cells and cells that
+// don't parse are null, except for strings. Lists, regexes and nulls can't be
+// read from a cell.
+pub fn schema(
+    headers: &StringRecord,
+    fields: &[(&'static str, Type)],
+) -> Result<Schema<StringRecord>, CsvError> {
+    let mut builder = SchemaBuilder::new();
+    for (name, field_type) in fields.iter().copied() {
+        let column = headers
+            .iter()
+            .position(|header| header == name)
+            .ok_or_else(|| CsvError::MissingColumnError(String::from(name)))?;
+
+        builder = match field_type {
+            Type::String => {
+                builder.with_string_field(name, move |row| cell(row, column).map(String::from))
+            }
+            Type::Number => {
+                builder.with_number_field(name, move |row| cell(row, column)?.parse().ok())
+            }
+            Type::Boolean => {
+                builder.with_boolean_field(name, move |row| boolean(cell(row, column)?))
+            }
+            Type::Raw => builder.with_raw_field(name, move |row| raw(cell(row, column)?)),
+            Type::DateTime => {
+                builder.with_datetime_field(name, move |row| datetime(cell(row, column)?))
+            }
+            field_type => {
+                return Err(CsvError::UnsupportedTypeError(
+                    String::from(name),
+                    field_type.variant_name(),
+                ));
+            }
+        };
+    }
+
+    Ok(builder.build())
+}
+
+fn cell(row: &StringRecord, column: usize) -> Option<&str> {
+    row.get(column).map(str::trim)
+}
+
+fn boolean(cell: &str) -> Option<bool> {
+    if cell.eq_ignore_ascii_case("true") {
+        Some(true)
+    } else if cell.eq_ignore_ascii_case("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+fn raw(cell: &str) -> Option<Vec<u8>> {
+    if cell.is_empty() || !cell.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..cell.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(cell.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn datetime(cell: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(cell)
+        .ok()
+        .map(|datetime| datetime.to_utc())
+}
@@ -0,0 +1,440 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::cmp::Ordering;
+
+use hashbrown::HashMap;
+use thiserror::Error;
+
+use crate::{
+    conjuncts::conjuncts,
+    engine::{Engine, ExecutionError},
+    expression::{Expression, Literal, Operator},
+    misc::hash_key,
+    schema::{Schema, Type, Value},
+};
+
+#[derive(Error, Debug)]
+pub enum IndexError {
+    #[error("A field with the name '{0}' does not exist")]
+    InvalidFieldError(String),
+    #[error("Fields of type {0} cannot be indexed")]
+    UnsupportedTypeError(&'static str),
+}
+
+// Positions of the items by their value of one field.
+struct Index {
+    field_type: Type,
+    // For Eq and IN lists. Nulls are kept apart and NaN never equals anything,
+    // so it is left out.
+    by_value: HashMap<Value, Vec<usize>>,
+    nulls: Vec<usize>,
+    // Numbers and datetimes in ascending order, for range conditions.
+    sorted: Vec<(Value, usize)>,
+}
+
+impl Index {
+    fn build(field_type: Type, values: impl Iterator<Item = Value>) -> Self {
+        let mut index = Index {
+            field_type,
+            by_value: HashMap::new(),
+            nulls: Vec::new(),
+            sorted: Vec::new(),
+        };
+
+        for (i, value) in values.enumerate() {
+            if value.is_null() {
+                index.nulls.push(i);
+                continue;
+            }
+            if is_ordered(field_type) && compare(&value, &value).is_some() {
+                index.sorted.push((value.clone(), i));
+            }
+            if let Some(key) = hash_key(value) {
+                index.by_value.entry(key).or_default().push(i);
+            }
+        }
+        index
+            .sorted
+            .sort_by(|(a, _), (b, _)| compare(a, b).unwrap_or(Ordering::Equal));
+
+        index
+    }
+
+    // The positions matching `field <op> value`, in ascending order, or None
+    // when the condition can't be answered from the index.
+    fn lookup(&self, op: &Operator, value: &Value) -> Option<Vec<usize>> {
+        if value.is_null() {
+            return match op {
+                Operator::Eq => Some(self.nulls.clone()),
+                _ => None,
+            };
+        }
+
+        match op {
+            Operator::Eq if value.get_type() == self.field_type => Some(self.get(value)),
+            Operator::Gt | Operator::Gte | Operator::Lt | Operator::Lte
+                if is_ordered(self.field_type) && value.get_type() == self.field_type =>
+            {
+                // Nothing compares with NaN.
+                compare(value, value)?;
+
+                let before = |strict: bool| {
+                    self.sorted
+                        .partition_point(|(item, _)| match compare(item, value) {
+                            Some(Ordering::Less) => true,
+                            Some(Ordering::Equal) => !strict,
+                            _ => false,
+                        })
+                };
+                let range = match op {
+                    Operator::Gt => before(false)..self.sorted.len(),
+                    Operator::Gte => before(true)..self.sorted.len(),
+                    Operator::Lt => 0..before(true),
+                    _ => 0..before(false),
+                };
+
+                Some(positions(&self.sorted[range]))
+            }
+            #[cfg(feature = "chrono")]
+            Operator::In if self.field_type == Type::DateTime => {
+                let Value::DateTimeList(range) = value else {
+                    return None;
+                };
+                let [from, until] = range.as_slice() else {
+                    return None;
+                };
+
+                let start = self.sorted.partition_point(
+                    |(item, _)| matches!(item, Value::DateTime(item) if item < from),
+                );
+                let end = self.sorted.partition_point(
+                    |(item, _)| matches!(item, Value::DateTime(item) if item < until),
+                );
+
+                Some(positions(&self.sorted[start..end.max(start)]))
+            }
+            Operator::In => {
+                let mut found = list_items(value, self.field_type)?
+                    .iter()
+                    .flat_map(|item| self.get(item))
+                    .collect::<Vec<usize>>();
+                found.sort_unstable();
+                found.dedup();
+
+                Some(found)
+            }
+            _ => None,
+        }
+    }
+
+    fn get(&self, value: &Value) -> Vec<usize> {
+        hash_key(value.clone())
+            .and_then(|key| self.by_value.get(&key))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+// Keeps a list of items along with indexes on some of their fields. Queries
+// use the indexes for the top-level conjuncts comparing an indexed field with
+// a literal (`==`, `IN` lists, ranges) and evaluate the other conjuncts with
+// the engine on the remaining candidates. Results are the same as executing
+// the expression on every item, in the order the items were given.
+pub struct Dataset<T> {
+    engine: Engine<T>,
+    items: Vec<T>,
+    indexes: HashMap<String, Index>,
+}
+
+impl<T> Dataset<T> {
+    pub fn new(schema: Schema<T>, items: Vec<T>) -> Self {
+        Self {
+            engine: Engine::new(schema),
+            items,
+            indexes: HashMap::new(),
+        }
+    }
+
+    // Strings, numbers, booleans, raw values and datetimes can be indexed.
+    pub fn with_index(mut self, field_name: &str) -> Result<Self, IndexError> {
+        let field = self
+            .engine
+            .get_schema()
+            .get_field(field_name)
+            .ok_or_else(|| IndexError::InvalidFieldError(field_name.to_string()))?;
+        if !is_scalar(field.field_type) {
+            return Err(IndexError::UnsupportedTypeError(
+                field.field_type.variant_name(),
+            ));
+        }
+
+        let values = self.items.iter().map(|item| (field.field_extractor)(item));
+        let index = Index::build(field.field_type, values);
+        self.indexes.insert(String::from(field_name), index);
+
+        Ok(self)
+    }
+
+    pub fn get_items(&self) -> &[T] {
+        &self.items
+    }
+
+    pub fn get_engine(&self) -> &Engine<T> {
+        &self.engine
+    }
+
+    pub fn query(&self, expression: &Expression) -> Result<Vec<&T>, ExecutionError> {
+        let mut candidates: Option<Vec<usize>> = None;
+        let mut rest = Vec::new();
+        for conjunct in conjuncts(expression) {
+            match self.lookup(conjunct) {
+                Some(found) => {
+                    candidates = Some(match candidates {
+                        Some(candidates) => intersect(&candidates, &found),
+                        None => found,
+                    });
+                }
+                None => rest.push(conjunct),
+            }
+        }
+
+        let mut results = Vec::new();
+        let candidates = candidates.unwrap_or_else(|| (0..self.items.len()).collect());
+        'items: for i in candidates {
+            let item = &self.items[i];
+            for conjunct in &rest {
+                if !self.engine.execute(conjunct, item)? {
+                    continue 'items;
+                }
+            }
+
+            results.push(item);
+        }
+
+        Ok(results)
+    }
+
+    fn lookup(&self, conjunct: &Expression) -> Option<Vec<usize>> {
+        let Expression::Operation(operation) = conjunct else {
+            return None;
+        };
+
+        // Comparisons are turned around so the field is on the left. `IN` is
+        // not symmetric, with the field on the right it is a containment check.
+        let (field_name, op, value) = match (&operation.lhs, &operation.rhs) {
+            (Literal::LiteralField(field_name), Literal::LiteralValue(value)) => {
+                (field_name, operation.op.clone(), value)
+            }
+            (Literal::LiteralValue(value), Literal::LiteralField(field_name)) => {
+                let op = match operation.op {
+                    Operator::Eq => Operator::Eq,
+                    Operator::Gt => Operator::Lt,
+                    Operator::Gte => Operator::Lte,
+                    Operator::Lt => Operator::Gt,
+                    Operator::Lte => Operator::Gte,
+                    _ => return None,
+                };
+
+                (field_name, op, value)
+            }
+            _ => return None,
+        };
+
+        self.indexes.get(field_name.as_str())?.lookup(&op, value)
+    }
+}
+
+fn is_scalar(field_type: Type) -> bool {
+    match field_type {
+        Type::String | Type::Number | Type::Boolean => true,
+        #[cfg(feature = "raw")]
+        Type::Raw => true,
+        #[cfg(feature = "chrono")]
+        Type::DateTime => true,
+        _ => false,
+    }
+}
+
+fn is_ordered(field_type: Type) -> bool {
+    match field_type {
+        Type::Number => true,
+        #[cfg(feature = "chrono")]
+        Type::DateTime => true,
+        _ => false,
+    }
+}
+
+fn compare(lhs: &Value, rhs: &Value) -> Option<Ordering> {
+    match (lhs, rhs) {
+        (Value::Number(lhv), Value::Number(rhv)) => lhv.partial_cmp(rhv),
+        #[cfg(feature = "chrono")]
+        (Value::DateTime(lhv), Value::DateTime(rhv)) => Some(lhv.cmp(rhv)),
+        _ => None,
+    }
+}
+
+// The items of a list literal whose items have the given type.
+fn list_items(list: &Value, item_type: Type) -> Option<Vec<Value>> {
+    let items = match (list, item_type) {
+        (Value::StringList(items), Type::String) => {
+            items.iter().cloned().map(Value::String).collect()
+        }
+        (Value::NumberList(items), Type::Number) => {
+            items.iter().copied().map(Value::Number).collect()
+        }
+        (Value::BooleanList(items), Type::Boolean) => {
+            items.iter().copied().map(Value::Boolean).collect()
+        }
+        #[cfg(feature = "raw")]
+        (Value::RawList(items), Type::Raw) => items.iter().cloned().map(Value::Raw).collect(),
+        _ => return None,
+    };
+
+    Some(items)
+}
+
+fn positions(entries: &[(Value, usize)]) -> Vec<usize> {
+    let mut positions = entries.iter().map(|(_, i)| *i).collect::<Vec<usize>>();
+    positions.sort_unstable();
+
+    positions
+}
+
+// Both sides are in ascending order.
+fn intersect(lhs: &[usize], rhs: &[usize]) -> Vec<usize> {
+    let mut out = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < lhs.len() && j < rhs.len() {
+        match lhs[i].cmp(&rhs[j]) {
+            Ordering::Less => i += 1,
+            Ordering::Greater => j += 1,
+            Ordering::Equal => {
+                out.push(lhs[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+
+    out
+}
+
+// Indexed lookups must agree with executing the expression on every item,
+// including for items where the indexed field is null.
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::{Parser, SchemaBuilder};
+
+    use super::*;
+
+    struct Item {
+        name: Option<&'static str>,
+        score: Option<f64>,
+    }
+
+    fn dataset() -> Dataset<Item> {
+        let schema = SchemaBuilder::<Item>::new()
+            .with_string_field("name", |item| item.name.map(String::from))
+            .with_number_field("score", |item| item.score)
+            .build();
+        let items = vec![
+            Item {
+                name: Some("a"),
+                score: Some(1.0),
+            },
+            Item {
+                name: None,
+                score: Some(2.0),
+            },
+            Item {
+                name: Some("b"),
+                score: None,
+            },
+            Item {
+                name: Some("a"),
+                score: Some(f64::NAN),
+            },
+            Item {
+                name: None,
+                score: None,
+            },
+            Item {
+                name: Some("c"),
+                score: Some(3.0),
+            },
+        ];
+
+        Dataset::new(schema, items)
+            .with_index("name")
+            .unwrap()
+            .with_index("score")
+            .unwrap()
+    }
+
+    // Positions of the matching items, from the indexes and from a full scan.
+    fn query(dataset: &Dataset<Item>, input: &str) -> (Vec<usize>, Vec<usize>) {
+        let expression = Parser::parse(input).unwrap();
+        let position = |found: &Item| {
+            dataset
+                .get_items()
+                .iter()
+                .position(|item| core::ptr::eq(item, found))
+                .unwrap()
+        };
+        let indexed = dataset
+            .query(&expression)
+            .unwrap()
+            .into_iter()
+            .map(position)
+            .collect();
+        let scanned = dataset
+            .get_items()
+            .iter()
+            .filter(|item| dataset.get_engine().execute(&expression, item).unwrap())
+            .map(position)
+            .collect();
+
+        (indexed, scanned)
+    }
+
+    #[test]
+    fn null_fields_match_like_the_engine() {
+        let dataset = dataset();
+        for input in [
+            r#"name == "a""#,
+            r#""a" == name"#,
+            r#"name != "a""#,
+            r#"!(name == "a")"#,
+            r#"name in ["a", "c"]"#,
+            "name == null",
+            "null == name",
+            "name != null",
+            "score > 1",
+            "1 < score",
+            "score >= 2",
+            "score < 3",
+            "score <= 2",
+            "!(score > 1)",
+            "score == null",
+            "score > null",
+            "score in [1, 3]",
+            r#"(name == null and score >= 2)"#,
+            r#"(name == "a" and score == null)"#,
+            r#"(name != "b" and score < 3)"#,
+        ] {
+            let (indexed, scanned) = query(&dataset, input);
+            assert_eq!(indexed, scanned, "{}", input);
+        }
+    }
+
+    #[test]
+    fn indexed_lookups_skip_nulls_and_nan() {
+        let dataset = dataset();
+        assert_eq!(query(&dataset, "name == null").0, [1, 4]);
+        assert_eq!(query(&dataset, "score >= 1").0, [0, 1, 5]);
+        assert_eq!(query(&dataset, "score < 2").0, [0]);
+        assert_eq!(query(&dataset, "score > null").0, Vec::<usize>::new());
+    }
+}
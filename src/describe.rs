@@ -0,0 +1,192 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::{
+    expression::{Computed, Expression, Literal, Operation, Operator},
+    schema::{Schema, Value},
+    serialize::Serialize,
+};
+
+pub fn describe<T>(expression: &Expression, schema: &Schema<T>) -> String {
+    match expression {
+        Expression::And(and) => describe_all(and.get_subexpressions(), schema, " AND "),
+        Expression::Or(or) => describe_all(or.get_subexpressions(), schema, " OR "),
+        Expression::Not(not) => format!("NOT ({})", describe(not.get_subexpression(), schema)),
+        Expression::Operation(operation) => describe_operation(operation, schema),
+        Expression::Reference(reference) => format!("rule {} holds", reference.get_rule_name()),
+    }
+}
+
+fn describe_all<T>(subexpressions: &[Expression], schema: &Schema<T>, separator: &str) -> String {
+    subexpressions
+        .iter()
+        .map(|subexpression| match subexpression {
+            // Nested groups keep their parentheses so the sentence stays unambiguous.
+            Expression::And(_) | Expression::Or(_) => {
+                format!("({})", describe(subexpression, schema))
+            }
+            _ => describe(subexpression, schema),
+        })
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+fn describe_operation<T>(operation: &Operation, schema: &Schema<T>) -> String {
+    let (lhs, op, rhs) = match (&operation.lhs, &operation.op, &operation.rhs) {
+        // Comparisons read better with the field as the subject, e.g. `25 <= age`
+        // becomes "age is at least 25".
+        (Literal::LiteralValue(_), op, Literal::LiteralField(_)) if !matches!(op, Operator::In) => {
+            let mirrored = match op {
+                Operator::Gt => Operator::Lt,
+                Operator::Gte => Operator::Lte,
+                Operator::Lt => Operator::Gt,
+                Operator::Lte => Operator::Gte,
+                op => op.clone(),
+            };
+
+            (&operation.rhs, mirrored, &operation.lhs)
+        }
+        (lhs, op, rhs) => (lhs, op.clone(), rhs),
+    };
+
+    let subject = describe_literal(lhs, schema);
+
+    match (op, rhs) {
+        (Operator::Eq, Literal::LiteralValue(Value::Null)) => format!("{} is not set", subject),
+        (Operator::Ne, Literal::LiteralValue(Value::Null)) => format!("{} is set", subject),
+        (Operator::Eq, rhs) => format!("{} equals {}", subject, describe_literal(rhs, schema)),
+        (Operator::Ne, rhs) => format!(
+            "{} does not equal {}",
+            subject,
+            describe_literal(rhs, schema)
+        ),
+        (Operator::Gt, rhs) => format!(
+            "{} is greater than {}",
+            subject,
+            describe_literal(rhs, schema)
+        ),
+        (Operator::Gte, rhs) => {
+            format!("{} is at least {}", subject, describe_literal(rhs, schema))
+        }
+        (Operator::Lt, rhs) => {
+            format!("{} is less than {}", subject, describe_literal(rhs, schema))
+        }
+        (Operator::Lte, rhs) => format!("{} is at most {}", subject, describe_literal(rhs, schema)),
+        #[cfg(feature = "chrono")]
+        (Operator::In, Literal::LiteralValue(Value::DateTimeList(range))) if range.len() == 2 => {
+            format!(
+                "{} is between {} and {}",
+                subject,
+                describe_value(&Value::DateTime(range[0])),
+                describe_value(&Value::DateTime(range[1]))
+            )
+        }
+        (Operator::In, Literal::LiteralValue(list)) if is_list(list) => {
+            format!("{} is one of {}", subject, describe_value(list))
+        }
+        (Operator::In, rhs) => match lhs {
+            #[cfg(feature = "regex")]
+            Literal::LiteralValue(Value::Regex(_)) => {
+                format!("{} matches {}", describe_literal(rhs, schema), subject)
+            }
+            _ => format!("{} contains {}", describe_literal(rhs, schema), subject),
+        },
+    }
+}
+
+// Fields are named by their schema description when one is registered.
+fn describe_literal<T>(literal: &Literal, schema: &Schema<T>) -> String {
+    match literal {
+        Literal::LiteralValue(value) => describe_value(value),
+        Literal::LiteralField(field_name) => schema
+            .get_description(field_name)
+            .unwrap_or(field_name)
+            .to_string(),
+        #[cfg(feature = "chrono")]
+        Literal::LiteralAge(field_name) => format!(
+            "the seconds since {}",
+            schema.get_description(field_name).unwrap_or(field_name)
+        ),
+        Literal::LiteralComputed(computed) => describe_computed(computed, schema),
+    }
+}
+
+// Computations keep their operators, with nested ones in parentheses.
+fn describe_computed<T>(computed: &Computed, schema: &Schema<T>) -> String {
+    let operand = |literal: &Literal| match literal {
+        Literal::LiteralComputed(_) => format!("({})", describe_literal(literal, schema)),
+        _ => describe_literal(literal, schema),
+    };
+
+    match computed {
+        Computed::Bitwise(first, rest) => {
+            let mut description = operand(first);
+            for (op, literal) in rest {
+                description.push_str(&format!(" {} {}", op.fmt_static(), operand(literal)));
+            }
+
+            description
+        }
+        Computed::Concat(operands) => operands
+            .iter()
+            .map(operand)
+            .collect::<Vec<String>>()
+            .join(" + "),
+        Computed::Coalesce(operands) => operands
+            .iter()
+            .map(operand)
+            .collect::<Vec<String>>()
+            .join(" ?? "),
+        Computed::If(condition, then, otherwise) => format!(
+            "{} if {}, else {}",
+            operand(then),
+            describe(condition, schema),
+            operand(otherwise)
+        ),
+        Computed::First(list) => format!("the first item of {}", operand(list)),
+        Computed::Last(list) => format!("the last item of {}", operand(list)),
+        Computed::Slice(list, start, end) => {
+            format!("items {} up to {} of {}", start, end, operand(list))
+        }
+    }
+}
+
+fn describe_value(value: &Value) -> String {
+    match value {
+        Value::String(val) => format!("'{}'", val),
+        Value::StringList(items) => describe_list(items.iter().map(|val| format!("'{}'", val))),
+        Value::NumberList(items) => describe_list(items.iter().map(|val| format!("{}", val))),
+        Value::BooleanList(items) => describe_list(items.iter().map(|val| format!("{}", val))),
+        #[cfg(feature = "raw")]
+        Value::RawList(items) => describe_list(
+            items
+                .iter()
+                .map(|val| describe_value(&Value::Raw(val.clone()))),
+        ),
+        #[cfg(feature = "chrono")]
+        Value::DateTimeList(items) => describe_list(
+            items
+                .iter()
+                .map(|val| describe_value(&Value::DateTime(*val))),
+        ),
+        value => Serialize::fmt(value),
+    }
+}
+
+fn describe_list(items: impl Iterator<Item = String>) -> String {
+    format!("[{}]", items.collect::<Vec<String>>().join(", "))
+}
+
+fn is_list(value: &Value) -> bool {
+    match value {
+        Value::StringList(_) | Value::NumberList(_) | Value::BooleanList(_) => true,
+        #[cfg(feature = "raw")]
+        Value::RawList(_) => true,
+        #[cfg(feature = "chrono")]
+        Value::DateTimeList(_) => true,
+        _ => false,
+    }
+}
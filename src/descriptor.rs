@@ -0,0 +1,125 @@
+use alloc::{string::String, vec::Vec};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "json")]
+use serde_json::Value as Json;
+
+use crate::{
+    engine::{self, ValidationError},
+    expression::Expression,
+    schema::Type,
+};
+
+// A schema without its extractors, describing which fields exist and their
+// types. It can be shared with clients that never see the target type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SchemaDescriptor {
+    pub fields: Vec<FieldDescriptor>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FieldDescriptor {
+    pub name: String,
+    pub field_type: Type,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub description: Option<String>,
+}
+
+impl SchemaDescriptor {
+    pub fn get_field(&self, field_name: &str) -> Option<&FieldDescriptor> {
+        self.fields.iter().find(|field| field.name == field_name)
+    }
+
+    // Proposes the fields of documents like `sample`, as a starting point to
+    // edit. Nested fields are named with `:`, e.g. `address:city`, and RFC 3339
+    // strings are datetimes. Nulls, empty lists and lists of mixed types or of
+    // objects say nothing about their type and are left out.
+    #[cfg(feature = "json")]
+    pub fn infer_from_json(sample: &Json) -> Self {
+        let mut fields = Vec::new();
+        if let Json::Object(object) = sample {
+            infer_fields(object, "", &mut fields);
+        }
+
+        Self { fields }
+    }
+}
+
+#[cfg(feature = "json")]
+fn infer_fields(
+    object: &serde_json::Map<String, Json>,
+    prefix: &str,
+    fields: &mut Vec<FieldDescriptor>,
+) {
+    for (key, value) in object {
+        let name = format!("{}{}", prefix, key);
+        if let Json::Object(object) = value {
+            infer_fields(object, &format!("{}:", name), fields);
+            continue;
+        }
+
+        if let Some(field_type) = infer_type(value) {
+            fields.push(FieldDescriptor {
+                name,
+                field_type,
+                description: None,
+            });
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+fn infer_type(value: &Json) -> Option<Type> {
+    match value {
+        Json::String(val) if chrono::DateTime::parse_from_rfc3339(val).is_ok() => {
+            Some(Type::DateTime)
+        }
+        Json::String(_) => Some(Type::String),
+        Json::Number(_) => Some(Type::Number),
+        Json::Bool(_) => Some(Type::Boolean),
+        Json::Array(items) if items.is_empty() => None,
+        Json::Array(items) if items.iter().all(Json::is_string) => {
+            match items
+                .iter()
+                .all(|item| infer_type(item) == Some(Type::DateTime))
+            {
+                true => Some(Type::DateTimeList),
+                false => Some(Type::StringList),
+            }
+        }
+        Json::Array(items) if items.iter().all(Json::is_number) => Some(Type::NumberList),
+        Json::Array(items) if items.iter().all(Json::is_boolean) => Some(Type::BooleanList),
+        Json::Array(_) => None,
+        Json::Null | Json::Object(_) => None,
+    }
+}
+
+// Validates expressions against a descriptor with the same rules as
+// `Engine::validate`.
+pub struct ClientValidator {
+    descriptor: SchemaDescriptor,
+}
+
+impl ClientValidator {
+    pub fn new(descriptor: SchemaDescriptor) -> Self {
+        Self { descriptor }
+    }
+
+    pub fn get_descriptor(&self) -> &SchemaDescriptor {
+        &self.descriptor
+    }
+
+    pub fn validate(&self, expression: &Expression) -> Result<(), ValidationError> {
+        engine::validate(expression, &|field_name| {
+            self.descriptor
+                .get_field(field_name)
+                .map(|field| field.field_type)
+        })
+    }
+}
@@ -0,0 +1,132 @@
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::fmt::{Display, Formatter};
+
+use crate::{expression::Expression, serialize::Serialize};
+
+// The differences between two expressions as a tree. Groups of the same kind
+// on both sides are compared by their subexpressions, which are matched
+// regardless of their order since it doesn't change the result. Operations on
+// the same left-hand side are treated as changed rather than removed and added.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Diff {
+    Unchanged(Expression),
+    Added(Expression),
+    Removed(Expression),
+    // The old and the new expression.
+    Changed(Expression, Expression),
+    And(Vec<Diff>),
+    Or(Vec<Diff>),
+    Not(Box<Diff>),
+}
+
+impl Diff {
+    pub fn is_unchanged(&self) -> bool {
+        matches!(self, Diff::Unchanged(_))
+    }
+}
+
+pub fn diff(old: &Expression, new: &Expression) -> Diff {
+    if old == new {
+        return Diff::Unchanged(old.clone());
+    }
+
+    match (old, new) {
+        (Expression::And(old), Expression::And(new)) => {
+            Diff::And(diff_all(old.get_subexpressions(), new.get_subexpressions()))
+        }
+        (Expression::Or(old), Expression::Or(new)) => {
+            Diff::Or(diff_all(old.get_subexpressions(), new.get_subexpressions()))
+        }
+        (Expression::Not(old), Expression::Not(new)) => Diff::Not(Box::new(diff(
+            old.get_subexpression(),
+            new.get_subexpression(),
+        ))),
+        (old, new) => Diff::Changed(old.clone(), new.clone()),
+    }
+}
+
+// Subexpressions in the old order, followed by the added ones. Equal
+// subexpressions are matched first, then similar ones.
+fn diff_all(old: &[Expression], new: &[Expression]) -> Vec<Diff> {
+    let mut matched = vec![false; new.len()];
+    let mut diffs = vec![None; old.len()];
+    for (i, old) in old.iter().enumerate() {
+        if let Some(j) = (0..new.len()).find(|&j| !matched[j] && new[j] == *old) {
+            matched[j] = true;
+            diffs[i] = Some(Diff::Unchanged(old.clone()));
+        }
+    }
+    for (i, old) in old.iter().enumerate() {
+        if diffs[i].is_some() {
+            continue;
+        }
+
+        diffs[i] = Some(
+            match (0..new.len()).find(|&j| !matched[j] && similar(old, &new[j])) {
+                Some(j) => {
+                    matched[j] = true;
+                    diff(old, &new[j])
+                }
+                None => Diff::Removed(old.clone()),
+            },
+        );
+    }
+
+    diffs
+        .into_iter()
+        .flatten()
+        .chain(
+            new.iter()
+                .zip(matched)
+                .filter(|(_, matched)| !matched)
+                .map(|(new, _)| Diff::Added(new.clone())),
+        )
+        .collect()
+}
+
+fn similar(old: &Expression, new: &Expression) -> bool {
+    match (old, new) {
+        (Expression::And(_), Expression::And(_))
+        | (Expression::Or(_), Expression::Or(_))
+        | (Expression::Not(_), Expression::Not(_)) => true,
+        (Expression::Operation(old), Expression::Operation(new)) => old.lhs == new.lhs,
+        _ => false,
+    }
+}
+
+// One line per subexpression, indented by depth and marked with `-` when
+// removed and `+` when added, like a unified diff.
+impl Display for Diff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write_diff(self, f, 0)
+    }
+}
+
+fn write_diff(diff: &Diff, f: &mut Formatter<'_>, depth: usize) -> core::fmt::Result {
+    let indent = depth * 2;
+    let (keyword, diffs) = match diff {
+        Diff::Unchanged(expression) => {
+            return writeln!(f, "  {:indent$}{}", "", Serialize::fmt(expression));
+        }
+        Diff::Added(expression) => {
+            return writeln!(f, "+ {:indent$}{}", "", Serialize::fmt(expression));
+        }
+        Diff::Removed(expression) => {
+            return writeln!(f, "- {:indent$}{}", "", Serialize::fmt(expression));
+        }
+        Diff::Changed(old, new) => {
+            writeln!(f, "- {:indent$}{}", "", Serialize::fmt(old))?;
+            return writeln!(f, "+ {:indent$}{}", "", Serialize::fmt(new));
+        }
+        Diff::And(diffs) => ("AND", diffs.as_slice()),
+        Diff::Or(diffs) => ("OR", diffs.as_slice()),
+        Diff::Not(diff) => ("NOT", core::slice::from_ref(diff.as_ref())),
+    };
+
+    writeln!(f, "  {:indent$}{}", "", keyword)?;
+    for diff in diffs {
+        write_diff(diff, f, depth + 1)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::{expression::Expression, serialize::Serialize};
+
+pub fn to_dot(expression: &Expression) -> String {
+    let mut lines = vec![
+        String::from("digraph expression {"),
+        String::from("    node [fontname=\"monospace\"];"),
+    ];
+    let mut next_id = 0;
+    write_node(expression, &mut lines, &mut next_id);
+    lines.push(String::from("}"));
+
+    lines.join("\n")
+}
+
+// Writes the node and its children, returning the id of the node.
+fn write_node(expression: &Expression, lines: &mut Vec<String>, next_id: &mut usize) -> usize {
+    let id = *next_id;
+    *next_id += 1;
+
+    let (label, shape, children) = match expression {
+        Expression::And(and) => (
+            "AND".to_string(),
+            "ellipse",
+            and.get_subexpressions().iter().collect(),
+        ),
+        Expression::Or(or) => (
+            "OR".to_string(),
+            "ellipse",
+            or.get_subexpressions().iter().collect(),
+        ),
+        Expression::Not(not) => ("NOT".to_string(), "ellipse", vec![not.get_subexpression()]),
+        Expression::Operation(operation) => (Serialize::fmt(operation), "box", vec![]),
+        Expression::Reference(reference) => {
+            (format!("@{}", reference.get_rule_name()), "box", vec![])
+        }
+    };
+
+    lines.push(format!(
+        "    n{} [label=\"{}\", shape={}];",
+        id,
+        escape_label(&label),
+        shape
+    ));
+
+    for child in children {
+        let child_id = write_node(child, lines, next_id);
+        lines.push(format!("    n{} -> n{};", id, child_id));
+    }
+
+    id
+}
+
+fn escape_label(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
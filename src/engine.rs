@@ -1,20 +1,79 @@
-use std::fmt::{Debug, Display};
+#[cfg(feature = "chrono")]
+use alloc::boxed::Box;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    cell::RefCell,
+    fmt::{Debug, Display},
+};
 
-use regex::Regex;
+#[cfg(all(feature = "std", feature = "chrono"))]
+use std::time::SystemTime;
+
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+use hashbrown::HashMap;
+#[cfg(feature = "regex")]
+use regex::{Regex, RegexBuilder};
 use thiserror::Error;
 
+#[cfg(feature = "std")]
+use crate::audit::{Audit, AuditRecord};
+#[cfg(feature = "chrono")]
+use crate::clock::Clock;
+#[cfg(all(feature = "chrono", feature = "std"))]
+use crate::clock::SystemClock;
+#[cfg(feature = "metrics")]
+use crate::metrics;
+#[cfg(feature = "raw")]
+use crate::misc::is_sublist;
 use crate::{
-    expression::{Expression, Literal, Operation, Operator},
-    misc::is_sublist,
-    schema::{Schema, Type, Value},
+    budget::{self, Budget},
+    compiled::{self, CompiledExpression},
+    computed::{computed_type, extract_computed},
+    explain::{self, Failure},
+    expression::{Expression, FieldName, Literal, MAX_DEPTH, Operation, Operator},
+    misc::closest,
+    multi::MultiMatcher,
+    overlay::Overlay,
+    schema::{Field, Schema, Type, Value},
+    stream::EvaluateStream,
 };
 
 #[derive(Error, Debug)]
 pub enum ValidationError {
-    #[error("A field with the name '{0}' does not exist")]
-    InvalidFieldError(String),
+    // The second field is the closest field name, if one is close enough.
+    #[error("A field with the name '{0}' does not exist{suggestion}", suggestion = did_you_mean(.1))]
+    InvalidFieldError(String, Option<String>),
     #[error("Cannot check if {0}")]
     InvalidOperatorError(InvalidOperatorError),
+    #[error("Expressions can be nested at most {0} levels deep")]
+    TooDeepError(usize),
+    #[error("The reference to rule '{0}' must be resolved through a rule set")]
+    UnresolvedReferenceError(String),
+    #[error("Expressions can have at most {0} nodes")]
+    TooManyNodesError(usize),
+    #[error("Expressions can have at most {0} regexes")]
+    TooManyRegexesError(usize),
+    #[error("Lists can have at most {0} items")]
+    ListTooLongError(usize),
+    #[cfg(feature = "regex")]
+    #[error("Invalid regex: {0}")]
+    InvalidRegexError(regex::Error),
+    #[cfg(feature = "chrono")]
+    #[error("The age of '{0}' can't be taken, it isn't a datetime")]
+    NotADateTimeError(String),
+    #[error("Cannot apply {0} to {operand}", operand = .1.variant_name())]
+    InvalidOperandError(&'static str, Type),
+    #[error("{0} is not an integer")]
+    NotAnIntegerError(f64),
+    #[error("Integers must be between -2^53 and 2^53")]
+    IntegerOutOfRangeError,
+    #[error("Operands of types {first} and {second} can't be mixed", first = .0.variant_name(), second = .1.variant_name())]
+    IncompatibleOperandsError(Type, Type),
 }
 
 #[derive(Error, Debug)]
@@ -25,12 +84,33 @@ pub enum ExecutionError {
     InvalidOperatorError(InvalidOperatorError),
     #[error("Invalid date range")]
     InvalidDateRangeError,
+    #[cfg(feature = "regex")]
+    #[error("Invalid regex: {0}")]
+    InvalidRegexError(regex::Error),
+    #[error("Expressions can be nested at most {0} levels deep")]
+    TooDeepError(usize),
+    #[error("The reference to rule '{0}' must be resolved through a rule set")]
+    UnresolvedReferenceError(String),
+    #[cfg(feature = "chrono")]
+    #[error("The age of '{0}' can't be taken, it isn't a datetime")]
+    NotADateTimeError(String),
+    #[cfg(feature = "chrono")]
+    #[error("The current time is unknown, the engine needs a clock")]
+    UnknownTimeError,
+    #[error("Cannot apply {0} to {operand}", operand = .1.variant_name())]
+    InvalidOperandError(&'static str, Type),
+    #[error("{0} is not an integer")]
+    NotAnIntegerError(f64),
+    #[error("Integers must be between -2^53 and 2^53")]
+    IntegerOutOfRangeError,
+    #[error("Cannot shift by {0} bits")]
+    ShiftOutOfRangeError(i64),
 }
 
 pub struct InvalidOperatorError(Type, Operator, Type);
 
 impl Debug for InvalidOperatorError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(
             f,
             "{} {} {}",
@@ -42,396 +122,889 @@ impl Debug for InvalidOperatorError {
 }
 
 impl Display for InvalidOperatorError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         Debug::fmt(&self, f)
     }
 }
 
 pub struct Engine<T> {
     schema: Schema<T>,
+    #[cfg(feature = "std")]
+    audit: Option<Audit<T>>,
+    #[cfg(feature = "chrono")]
+    clock: Option<Box<dyn Clock>>,
 }
 
 impl<T> Engine<T> {
     pub fn new(schema: Schema<T>) -> Self {
-        Self { schema }
+        Self {
+            schema,
+            #[cfg(feature = "std")]
+            audit: None,
+            #[cfg(feature = "chrono")]
+            clock: None,
+        }
     }
 
-    pub fn validate(&self, expression: &Expression) -> Result<(), ValidationError> {
-        match expression {
-            Expression::And(and) => and
-                .get_subexpressions()
-                .iter()
-                .try_for_each(|i| self.validate(i)),
-            Expression::Or(or) => or
-                .get_subexpressions()
-                .iter()
-                .try_for_each(|i| self.validate(i)),
-            Expression::Not(not) => self.validate(not.get_subexpression()),
-            Expression::Operation(operation) => self.validate_operation(operation),
+    // Takes the current time from `clock` instead of the system time, e.g. a
+    // `FixedClock` in tests. It's used for `age_of` and audit timestamps.
+    // Without std the engine needs a clock to execute `age_of`.
+    #[cfg(feature = "chrono")]
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Some(Box::new(clock));
+
+        self
+    }
+
+    // Reports every `execute` call to `sink`, with the target identified by
+    // `key_extractor`.
+    #[cfg(feature = "std")]
+    pub fn with_audit(
+        mut self,
+        key_extractor: impl Fn(&T) -> String + 'static,
+        sink: impl Fn(&AuditRecord) + 'static,
+    ) -> Self {
+        self.audit = Some(Audit {
+            key_extractor: Box::new(key_extractor),
+            sink: Box::new(sink),
+        });
+
+        self
+    }
+
+    pub fn get_schema(&self) -> &Schema<T> {
+        &self.schema
+    }
+
+    #[cfg(feature = "chrono")]
+    pub(crate) fn now(&self) -> Option<DateTime<Utc>> {
+        match &self.clock {
+            Some(clock) => Some(clock.now()),
+            None => system_now(),
         }
     }
 
-    fn validate_operation(&self, operation: &Operation) -> Result<(), ValidationError> {
-        let lhs = self.extract_literal_type(&operation.lhs)?;
-        let rhs = self.extract_literal_type(&operation.rhs)?;
+    pub fn validate(&self, expression: &Expression) -> Result<(), ValidationError> {
+        let result = self.validate_schema(expression);
 
-        let operator_error = || {
-            ValidationError::InvalidOperatorError(InvalidOperatorError(
-                lhs,
-                operation.op.clone(),
-                rhs,
-            ))
-        };
+        #[cfg(feature = "metrics")]
+        metrics::record_validation(&result);
 
-        if rhs.is_null() || lhs.is_null() {
-            return match operation.op {
-                Operator::Eq | Operator::Ne | Operator::In => Ok(()),
-                _ => Err(operator_error()),
-            };
+        result
+    }
+
+    // `validate` without recording metrics, for callers recording their own.
+    fn validate_schema(&self, expression: &Expression) -> Result<(), ValidationError> {
+        validate(expression, &|field_name| {
+            self.schema
+                .get_field(field_name)
+                .map(|field| field.field_type)
+        })
+        .map_err(|e| suggest_field(e, self.schema.get_field_names()))
+    }
+
+    // Validates the expression and prepares it for repeated execution, see
+    // `CompiledExpression`.
+    pub fn compile(&self, expression: &Expression) -> Result<CompiledExpression, ValidationError> {
+        self.validate(expression)?;
+
+        Ok(compiled::compile(expression))
+    }
+
+    pub fn execute_compiled(
+        &self,
+        compiled: &CompiledExpression,
+        target: &T,
+    ) -> Result<bool, ExecutionError> {
+        let execute = || compiled.execute(target, &EngineFields(self));
+
+        #[cfg(feature = "metrics")]
+        return metrics::record_execution(compiled.get_expression(), execute);
+        #[cfg(not(feature = "metrics"))]
+        execute()
+    }
+
+    // Executes on every target, giving the same results as `execute_compiled`
+    // on each, see `CompiledExpression`.
+    pub fn execute_compiled_batch(
+        &self,
+        compiled: &CompiledExpression,
+        targets: &[T],
+    ) -> Result<Vec<bool>, ExecutionError> {
+        compiled.execute_batch(targets, &EngineFields(self))
+    }
+
+    // Validates every expression and prepares them for executing together, see
+    // `MultiMatcher`.
+    pub fn compile_multi(
+        &self,
+        expressions: Vec<Expression>,
+    ) -> Result<MultiMatcher, ValidationError> {
+        for expression in &expressions {
+            self.validate(expression)?;
         }
 
-        match lhs {
-            Type::String => match rhs {
-                Type::String => match operation.op {
-                    Operator::Eq | Operator::Ne | Operator::In => Ok(()),
-                    // Invalid operation
-                    _ => Err(operator_error()),
-                },
-                Type::StringList => match operation.op {
-                    Operator::In => Ok(()),
-                    _ => Err(operator_error()),
-                },
-                _ => Err(operator_error()),
-            },
-            Type::Regex => match rhs {
-                Type::String => match operation.op {
-                    Operator::In => Ok(()),
-                    _ => Err(operator_error()),
-                },
-                Type::StringList => match operation.op {
-                    Operator::In => Ok(()),
-                    _ => Err(operator_error()),
-                },
-                _ => Err(operator_error()),
-            },
-            Type::Number => match rhs {
-                Type::Number => match operation.op {
-                    Operator::Eq
-                    | Operator::Ne
-                    | Operator::Gt
-                    | Operator::Gte
-                    | Operator::Lt
-                    | Operator::Lte => Ok(()),
-                    _ => Err(operator_error()),
-                },
-                Type::NumberList => match operation.op {
-                    Operator::In => Ok(()),
-                    _ => Err(operator_error()),
-                },
-                _ => Err(operator_error()),
-            },
-            Type::Boolean => match rhs {
-                Type::Boolean => match operation.op {
-                    Operator::Eq | Operator::Ne => Ok(()),
-                    _ => Err(operator_error()),
-                },
-                Type::BooleanList => match operation.op {
-                    Operator::In => Ok(()),
-                    _ => Err(operator_error()),
-                },
-                _ => Err(operator_error()),
-            },
-            Type::Raw => match rhs {
-                Type::Raw => match operation.op {
-                    Operator::Eq | Operator::Ne | Operator::In => Ok(()),
-                    _ => Err(operator_error()),
-                },
-                Type::RawList => match operation.op {
-                    Operator::In => Ok(()),
-                    _ => Err(operator_error()),
-                },
-                _ => Err(operator_error()),
-            },
-            Type::DateTime => match rhs {
-                Type::DateTime => match operation.op {
-                    Operator::Eq
-                    | Operator::Ne
-                    | Operator::Gt
-                    | Operator::Gte
-                    | Operator::Lt
-                    | Operator::Lte => Ok(()),
-                    _ => Err(operator_error()),
-                },
-                Type::DateTimeList => match operation.op {
-                    Operator::In => Ok(()),
-                    _ => Err(operator_error()),
-                },
-                _ => Err(operator_error()),
-            },
-            Type::StringList => match rhs {
-                Type::StringList => match operation.op {
-                    Operator::Eq | Operator::Ne => Ok(()),
-                    _ => Err(operator_error()),
-                },
-                _ => Err(operator_error()),
-            },
-            Type::NumberList => match rhs {
-                Type::NumberList => match operation.op {
-                    Operator::Eq | Operator::Ne => Ok(()),
-                    _ => Err(operator_error()),
-                },
-                _ => Err(operator_error()),
-            },
-            Type::BooleanList => match rhs {
-                Type::BooleanList => match operation.op {
-                    Operator::Eq | Operator::Ne => Ok(()),
-                    _ => Err(operator_error()),
-                },
-                _ => Err(operator_error()),
-            },
-            Type::RawList => match rhs {
-                Type::RawList => match operation.op {
-                    Operator::Eq | Operator::Ne => Ok(()),
-                    _ => Err(operator_error()),
-                },
-                _ => Err(operator_error()),
-            },
-            Type::DateTimeList => match rhs {
-                Type::DateTimeList => match operation.op {
-                    Operator::Eq | Operator::Ne => Ok(()),
-                    _ => Err(operator_error()),
-                },
-                _ => Err(operator_error()),
-            },
-            Type::Null => Ok(()),
+        Ok(MultiMatcher::new(expressions))
+    }
+
+    // The indices of the matcher's expressions that are true for the target,
+    // the same as executing each of them. Like with `execute_all`, each field
+    // is extracted from the target once.
+    pub fn execute_multi(
+        &self,
+        matcher: &MultiMatcher,
+        target: &T,
+    ) -> Result<Vec<usize>, ExecutionError> {
+        matcher.execute(target, &CachedFields::new(&EngineFields(self)))
+    }
+
+    // The ids of the rules whose expressions are true for the target, in order.
+    // Each field is extracted from the target once, however many of the
+    // expressions use it.
+    pub fn execute_all<R: Clone>(
+        &self,
+        rules: &[(R, Expression)],
+        target: &T,
+    ) -> Result<Vec<R>, ExecutionError> {
+        let engine_fields = EngineFields(self);
+        let fields = CachedFields::new(&engine_fields);
+        let mut matches = Vec::new();
+        for (rule_id, expression) in rules {
+            if execute(expression, target, &fields)? {
+                matches.push(rule_id.clone());
+            }
         }
+
+        Ok(matches)
+    }
+
+    // Validates the expression after checking it stays within `budget`.
+    pub fn validate_with_budget(
+        &self,
+        expression: &Expression,
+        budget: &Budget,
+    ) -> Result<(), ValidationError> {
+        let result =
+            budget::check(expression, budget).and_then(|()| self.validate_schema(expression));
+
+        // Recorded once for both checks.
+        #[cfg(feature = "metrics")]
+        metrics::record_validation(&result);
+
+        result
     }
 
     pub fn execute(&self, expression: &Expression, target: &T) -> Result<bool, ExecutionError> {
-        match expression {
-            Expression::And(and) => {
-                for i in and.get_subexpressions() {
-                    if !self.execute(i, target)? {
-                        return Ok(false);
-                    }
-                }
+        let execute = || execute(expression, target, &EngineFields(self));
+        #[cfg(feature = "metrics")]
+        let execute = || metrics::record_execution(expression, execute);
 
-                return Ok(true);
-            }
-            Expression::Or(or) => {
-                for i in or.get_subexpressions() {
-                    if self.execute(i, target)? {
-                        return Ok(true);
-                    }
+        #[cfg(feature = "std")]
+        if let Some(audit) = &self.audit {
+            #[cfg(feature = "chrono")]
+            let now = self.now().map(SystemTime::from);
+            #[cfg(not(feature = "chrono"))]
+            let now = None;
+
+            return audit.record(expression, target, now, execute);
+        }
+
+        execute()
+    }
+
+    // The operations that made `expression` false for `target`, empty if it's
+    // true, see `Failure`.
+    pub fn explain_failure(
+        &self,
+        expression: &Expression,
+        target: &T,
+    ) -> Result<Vec<Failure>, ExecutionError> {
+        explain::explain_failure(expression, target, &EngineFields(self))
+    }
+
+    // Layers the extra fields of `schema` on top of the engine's, see `Overlay`.
+    pub fn overlay<'a>(&'a self, schema: &'a Schema<T>) -> Overlay<'a, T> {
+        Overlay::new(self, schema)
+    }
+
+    // Lazily filters `items`, counting what was scanned and matched along the
+    // way, see `EvaluateStream`.
+    pub fn evaluate_stream<'a, I: IntoIterator<Item = T>>(
+        &'a self,
+        expression: &'a Expression,
+        items: I,
+    ) -> EvaluateStream<'a, T, I::IntoIter> {
+        EvaluateStream::new(self, expression, items.into_iter())
+    }
+}
+
+// Looks up the fields of the target, in the engine's schema or in an overlay
+// on top of it, and tells the time `age_of` is measured at.
+pub(crate) trait FieldLookup<T> {
+    fn get_field(&self, field_name: &str) -> Option<&Field<T>>;
+
+    #[cfg(feature = "chrono")]
+    fn now(&self) -> Option<DateTime<Utc>> {
+        system_now()
+    }
+
+    fn extract(&self, _field_name: &str, field: &Field<T>, target: &T) -> Value {
+        (field.field_extractor)(target)
+    }
+}
+
+// A schema on its own uses the system clock.
+impl<T> FieldLookup<T> for Schema<T> {
+    fn get_field(&self, field_name: &str) -> Option<&Field<T>> {
+        Schema::get_field(self, field_name)
+    }
+}
+
+pub(crate) type Fields<'a, T> = dyn FieldLookup<T> + 'a;
+
+// Fields built for one execution, for targets without a schema of their own.
+pub(crate) struct FieldList<T>(pub(crate) Vec<(FieldName, Field<T>)>);
+
+impl<T> FieldLookup<T> for FieldList<T> {
+    fn get_field(&self, field_name: &str) -> Option<&Field<T>> {
+        self.0
+            .iter()
+            .find(|(name, _)| name == field_name)
+            .map(|(_, field)| field)
+    }
+}
+
+// The engine's schema, at the time of the engine's clock.
+pub(crate) struct EngineFields<'a, T>(pub(crate) &'a Engine<T>);
+
+impl<T> FieldLookup<T> for EngineFields<'_, T> {
+    fn get_field(&self, field_name: &str) -> Option<&Field<T>> {
+        self.0.schema.get_field(field_name)
+    }
+
+    #[cfg(feature = "chrono")]
+    fn now(&self) -> Option<DateTime<Utc>> {
+        self.0.now()
+    }
+}
+
+// Fields extracting each value at most once, for executing several expressions
+// on the same target.
+pub(crate) struct CachedFields<'a, T> {
+    fields: &'a Fields<'a, T>,
+    values: RefCell<HashMap<FieldName, Value>>,
+}
+
+impl<'a, T> CachedFields<'a, T> {
+    pub(crate) fn new(fields: &'a Fields<'a, T>) -> Self {
+        CachedFields {
+            fields,
+            values: RefCell::new(HashMap::new()),
+        }
+    }
+}
+
+impl<T> FieldLookup<T> for CachedFields<'_, T> {
+    fn get_field(&self, field_name: &str) -> Option<&Field<T>> {
+        self.fields.get_field(field_name)
+    }
+
+    #[cfg(feature = "chrono")]
+    fn now(&self) -> Option<DateTime<Utc>> {
+        self.fields.now()
+    }
+
+    fn extract(&self, field_name: &str, field: &Field<T>, target: &T) -> Value {
+        if let Some(value) = self.values.borrow().get(field_name) {
+            return value.clone();
+        }
+
+        let value = self.fields.extract(field_name, field, target);
+        self.values
+            .borrow_mut()
+            .insert(FieldName::from(field_name), value.clone());
+
+        value
+    }
+}
+
+// Without std there is no system clock, the time is only known from a clock
+// set on the engine.
+#[cfg(feature = "chrono")]
+fn system_now() -> Option<DateTime<Utc>> {
+    #[cfg(feature = "std")]
+    return Some(SystemClock.now());
+    #[cfg(not(feature = "std"))]
+    None
+}
+
+// Like validation, execution is shared with schemas layered on the engine's.
+pub(crate) fn execute<T>(
+    expression: &Expression,
+    target: &T,
+    fields: &Fields<T>,
+) -> Result<bool, ExecutionError> {
+    execute_at(expression, target, fields, 0)
+}
+
+pub(crate) fn execute_at<T>(
+    expression: &Expression,
+    target: &T,
+    fields: &Fields<T>,
+    depth: usize,
+) -> Result<bool, ExecutionError> {
+    if depth > MAX_DEPTH {
+        return Err(ExecutionError::TooDeepError(MAX_DEPTH));
+    }
+
+    match expression {
+        Expression::And(and) => {
+            for i in and.get_subexpressions() {
+                if !execute_at(i, target, fields, depth + 1)? {
+                    return Ok(false);
                 }
+            }
 
-                return Ok(false);
+            Ok(true)
+        }
+        Expression::Or(or) => {
+            for i in or.get_subexpressions() {
+                if execute_at(i, target, fields, depth + 1)? {
+                    return Ok(true);
+                }
             }
-            Expression::Not(not) => self
-                .execute(not.get_subexpression(), target)
-                .map(|result| !result),
-            Expression::Operation(operation) => self.execute_operation(operation, target),
+
+            Ok(false)
         }
+        Expression::Not(not) => {
+            execute_at(not.get_subexpression(), target, fields, depth + 1).map(|result| !result)
+        }
+        Expression::Operation(operation) => execute_operation_at(operation, target, fields, depth),
+        Expression::Reference(reference) => Err(ExecutionError::UnresolvedReferenceError(
+            reference.get_rule_name().to_string(),
+        )),
     }
+}
 
-    fn execute_operation(&self, operation: &Operation, target: &T) -> Result<bool, ExecutionError> {
-        let lhs = self.extract_literal(&operation.lhs, target)?;
-        let rhs = self.extract_literal(&operation.rhs, target)?;
+pub(crate) fn execute_operation<T>(
+    operation: &Operation,
+    target: &T,
+    fields: &Fields<T>,
+) -> Result<bool, ExecutionError> {
+    execute_operation_at(operation, target, fields, 0)
+}
 
-        let operator_error = || {
-            ExecutionError::InvalidOperatorError(InvalidOperatorError(
-                lhs.get_type(),
-                operation.op.clone(),
-                rhs.get_type(),
-            ))
-        };
+// The depth of the operation, which the conditions of its operands are nested
+// below.
+fn execute_operation_at<T>(
+    operation: &Operation,
+    target: &T,
+    fields: &Fields<T>,
+    depth: usize,
+) -> Result<bool, ExecutionError> {
+    let lhs = extract_literal_at(&operation.lhs, target, fields, depth)?;
+    let rhs = extract_literal_at(&operation.rhs, target, fields, depth)?;
 
-        if lhs.is_null() {
-            if rhs.is_null() {
-                return Ok(match operation.op {
-                    Operator::Eq => true,
-                    _ => false,
-                });
-            } else {
-                return Ok(match operation.op {
-                    Operator::Ne => true,
-                    _ => false,
-                });
-            }
-        } else if rhs.is_null() {
-            return Ok(match operation.op {
-                Operator::Ne => true,
-                _ => false,
-            });
+    let operator_error = || {
+        ExecutionError::InvalidOperatorError(InvalidOperatorError(
+            lhs.get_type(),
+            operation.op.clone(),
+            rhs.get_type(),
+        ))
+    };
+
+    if lhs.is_null() {
+        if rhs.is_null() {
+            return Ok(matches!(operation.op, Operator::Eq));
+        } else {
+            return Ok(matches!(operation.op, Operator::Ne));
         }
+    } else if rhs.is_null() {
+        return Ok(matches!(operation.op, Operator::Ne));
+    }
 
-        Ok(match &lhs {
-            Value::String(lhv) => match &rhs {
-                Value::String(rhv) => match operation.op {
-                    Operator::Eq => lhv == rhv,
-                    Operator::Ne => lhv != rhv,
-                    Operator::In => rhv.contains(lhv),
-                    _ => return Err(operator_error()),
-                },
-                Value::StringList(rhv) => match operation.op {
-                    Operator::In => rhv.contains(&lhv),
-                    _ => return Err(operator_error()),
-                },
+    Ok(match &lhs {
+        Value::String(lhv) => match &rhs {
+            Value::String(rhv) => match operation.op {
+                Operator::Eq => lhv == rhv,
+                Operator::Ne => lhv != rhv,
+                Operator::In => rhv.contains(lhv),
                 _ => return Err(operator_error()),
             },
-            Value::Regex(lhv) => match &rhs {
-                Value::String(rhv) => match operation.op {
-                    Operator::In => {
-                        let regex = Regex::new(lhv).unwrap();
-
-                        regex.is_match(&rhv)
-                    }
-                    _ => return Err(operator_error()),
-                },
-                Value::StringList(rhv) => match operation.op {
-                    Operator::In => {
-                        let regex = Regex::new(lhv).unwrap();
-
-                        rhv.iter().any(|v| regex.is_match(v))
-                    }
-                    _ => return Err(operator_error()),
-                },
+            Value::StringList(rhv) => match operation.op {
+                Operator::In => rhv.contains(lhv),
                 _ => return Err(operator_error()),
             },
-            Value::Number(lhv) => match &rhs {
-                Value::Number(rhv) => match operation.op {
-                    Operator::Eq => lhv == rhv,
-                    Operator::Ne => lhv != rhv,
-                    Operator::Gt => lhv > rhv,
-                    Operator::Gte => lhv >= rhv,
-                    Operator::Lt => lhv < rhv,
-                    Operator::Lte => lhv <= rhv,
-                    _ => return Err(operator_error()),
-                },
-                Value::NumberList(rhv) => match operation.op {
-                    Operator::In => rhv.contains(lhv),
-                    _ => return Err(operator_error()),
-                },
+            _ => return Err(operator_error()),
+        },
+        #[cfg(feature = "regex")]
+        Value::Regex(lhv) => match &rhs {
+            Value::String(rhv) => match operation.op {
+                Operator::In => {
+                    let regex = compile_regex(lhv).map_err(ExecutionError::InvalidRegexError)?;
+
+                    regex.is_match(rhv)
+                }
                 _ => return Err(operator_error()),
             },
-            Value::Boolean(lhv) => match &rhs {
-                Value::Boolean(rhv) => match operation.op {
-                    Operator::Eq => lhv == rhv,
-                    Operator::Ne => lhv != rhv,
-                    _ => return Err(operator_error()),
-                },
-                Value::BooleanList(rhv) => match operation.op {
-                    Operator::In => rhv.contains(lhv),
-                    _ => return Err(operator_error()),
-                },
+            Value::StringList(rhv) => match operation.op {
+                Operator::In => {
+                    let regex = compile_regex(lhv).map_err(ExecutionError::InvalidRegexError)?;
+
+                    rhv.iter().any(|v| regex.is_match(v))
+                }
                 _ => return Err(operator_error()),
             },
-            Value::Raw(lhv) => match &rhs {
-                Value::Raw(rhv) => match operation.op {
-                    Operator::Eq => lhv == rhv,
-                    Operator::Ne => lhv != rhv,
-                    Operator::In => is_sublist(&rhv, &lhv),
-                    _ => return Err(operator_error()),
-                },
-                Value::RawList(rhv) => match operation.op {
-                    Operator::In => rhv.iter().any(|v| lhv == v),
-                    _ => return Err(operator_error()),
-                },
+            _ => return Err(operator_error()),
+        },
+        Value::Number(lhv) => match &rhs {
+            Value::Number(rhv) => match operation.op {
+                Operator::Eq => lhv == rhv,
+                Operator::Ne => lhv != rhv,
+                Operator::Gt => lhv > rhv,
+                Operator::Gte => lhv >= rhv,
+                Operator::Lt => lhv < rhv,
+                Operator::Lte => lhv <= rhv,
                 _ => return Err(operator_error()),
             },
-            Value::DateTime(lhv) => match &rhs {
-                Value::DateTime(rhv) => match operation.op {
-                    Operator::Eq => lhv == rhv,
-                    Operator::Ne => lhv != rhv,
-                    Operator::Gt => lhv > rhv,
-                    Operator::Gte => lhv >= rhv,
-                    Operator::Lt => lhv < rhv,
-                    Operator::Lte => lhv <= rhv,
-                    _ => return Err(operator_error()),
-                },
-                Value::DateTimeList(rhv) => match operation.op {
-                    Operator::In => {
-                        if rhv.len() != 2 {
-                            return Err(ExecutionError::InvalidDateRangeError);
-                        }
-
-                        let from = rhv.get(0).unwrap();
-                        let until = rhv.get(1).unwrap();
-
-                        lhv >= from && lhv < until
-                    }
-                    _ => return Err(operator_error()),
-                },
+            Value::NumberList(rhv) => match operation.op {
+                Operator::In => rhv.contains(lhv),
                 _ => return Err(operator_error()),
             },
-            Value::StringList(lhv) => match &rhs {
-                Value::StringList(rhv) => match operation.op {
-                    Operator::Eq => lhv == rhv,
-                    Operator::Ne => lhv != rhv,
-                    _ => return Err(operator_error()),
-                },
+            _ => return Err(operator_error()),
+        },
+        Value::Boolean(lhv) => match &rhs {
+            Value::Boolean(rhv) => match operation.op {
+                Operator::Eq => lhv == rhv,
+                Operator::Ne => lhv != rhv,
                 _ => return Err(operator_error()),
             },
-            Value::NumberList(lhv) => match &rhs {
-                Value::NumberList(rhv) => match operation.op {
-                    Operator::Eq => lhv == rhv,
-                    Operator::Ne => lhv != rhv,
-                    _ => return Err(operator_error()),
-                },
+            Value::BooleanList(rhv) => match operation.op {
+                Operator::In => rhv.contains(lhv),
                 _ => return Err(operator_error()),
             },
-            Value::BooleanList(lhv) => match &rhs {
-                Value::BooleanList(rhv) => match operation.op {
-                    Operator::Eq => lhv == rhv,
-                    Operator::Ne => lhv != rhv,
-                    _ => return Err(operator_error()),
-                },
+            _ => return Err(operator_error()),
+        },
+        #[cfg(feature = "raw")]
+        Value::Raw(lhv) => match &rhs {
+            Value::Raw(rhv) => match operation.op {
+                Operator::Eq => lhv == rhv,
+                Operator::Ne => lhv != rhv,
+                Operator::In => is_sublist(rhv, lhv),
                 _ => return Err(operator_error()),
             },
-            Value::RawList(lhv) => match &rhs {
-                Value::RawList(rhv) => match operation.op {
-                    Operator::Eq => lhv == rhv,
-                    Operator::Ne => lhv != rhv,
-                    _ => return Err(operator_error()),
-                },
+            Value::RawList(rhv) => match operation.op {
+                Operator::In => rhv.iter().any(|v| lhv == v),
                 _ => return Err(operator_error()),
             },
-            Value::DateTimeList(lhv) => match &rhs {
-                Value::DateTimeList(rhv) => match operation.op {
-                    Operator::Eq => lhv == rhv,
-                    Operator::Ne => lhv != rhv,
-                    _ => return Err(operator_error()),
-                },
+            _ => return Err(operator_error()),
+        },
+        #[cfg(feature = "chrono")]
+        Value::DateTime(lhv) => match &rhs {
+            Value::DateTime(rhv) => match operation.op {
+                Operator::Eq => lhv == rhv,
+                Operator::Ne => lhv != rhv,
+                Operator::Gt => lhv > rhv,
+                Operator::Gte => lhv >= rhv,
+                Operator::Lt => lhv < rhv,
+                Operator::Lte => lhv <= rhv,
                 _ => return Err(operator_error()),
             },
-            Value::Null => unreachable!(),
-        })
-    }
+            Value::DateTimeList(rhv) => match operation.op {
+                Operator::In => {
+                    let [from, until] = rhv.as_slice() else {
+                        return Err(ExecutionError::InvalidDateRangeError);
+                    };
+
+                    lhv >= from && lhv < until
+                }
+                _ => return Err(operator_error()),
+            },
+            _ => return Err(operator_error()),
+        },
+        Value::StringList(lhv) => match &rhs {
+            Value::StringList(rhv) => match operation.op {
+                Operator::Eq => lhv == rhv,
+                Operator::Ne => lhv != rhv,
+                _ => return Err(operator_error()),
+            },
+            _ => return Err(operator_error()),
+        },
+        Value::NumberList(lhv) => match &rhs {
+            Value::NumberList(rhv) => match operation.op {
+                Operator::Eq => lhv == rhv,
+                Operator::Ne => lhv != rhv,
+                _ => return Err(operator_error()),
+            },
+            _ => return Err(operator_error()),
+        },
+        Value::BooleanList(lhv) => match &rhs {
+            Value::BooleanList(rhv) => match operation.op {
+                Operator::Eq => lhv == rhv,
+                Operator::Ne => lhv != rhv,
+                _ => return Err(operator_error()),
+            },
+            _ => return Err(operator_error()),
+        },
+        #[cfg(feature = "raw")]
+        Value::RawList(lhv) => match &rhs {
+            Value::RawList(rhv) => match operation.op {
+                Operator::Eq => lhv == rhv,
+                Operator::Ne => lhv != rhv,
+                _ => return Err(operator_error()),
+            },
+            _ => return Err(operator_error()),
+        },
+        #[cfg(feature = "chrono")]
+        Value::DateTimeList(lhv) => match &rhs {
+            Value::DateTimeList(rhv) => match operation.op {
+                Operator::Eq => lhv == rhv,
+                Operator::Ne => lhv != rhv,
+                _ => return Err(operator_error()),
+            },
+            _ => return Err(operator_error()),
+        },
+        Value::Null => unreachable!(),
+    })
+}
 
-    fn extract_literal_type(&self, literal: &Literal) -> Result<Type, ValidationError> {
-        Ok(match &literal {
-            Literal::LiteralValue(value) => value.get_type(),
-            Literal::LiteralField(field_name) => {
-                self.schema
-                    .get_field(field_name)
-                    .ok_or_else(|| ValidationError::InvalidFieldError(field_name.to_string()))?
-                    .field_type
+pub(crate) fn extract_literal<T>(
+    literal: &Literal,
+    target: &T,
+    fields: &Fields<T>,
+) -> Result<Value, ExecutionError> {
+    extract_literal_at(literal, target, fields, 0)
+}
+
+pub(crate) fn extract_literal_at<T>(
+    literal: &Literal,
+    target: &T,
+    fields: &Fields<T>,
+    depth: usize,
+) -> Result<Value, ExecutionError> {
+    Ok(match &literal {
+        Literal::LiteralValue(value) => value.clone(),
+        Literal::LiteralField(field_name) => extract_field(field_name, target, fields)?,
+        #[cfg(feature = "chrono")]
+        Literal::LiteralAge(field_name) => match extract_field(field_name, target, fields)? {
+            Value::DateTime(datetime) => {
+                let now = fields.now().ok_or(ExecutionError::UnknownTimeError)?;
+
+                Value::Number((now - datetime).as_seconds_f64())
             }
-        })
+            Value::Null => Value::Null,
+            _ => return Err(ExecutionError::NotADateTimeError(field_name.to_string())),
+        },
+        Literal::LiteralComputed(computed) => extract_computed(computed, target, fields, depth)?,
+    })
+}
+
+fn extract_field<T>(
+    field_name: &str,
+    target: &T,
+    fields: &Fields<T>,
+) -> Result<Value, ExecutionError> {
+    let field = fields
+        .get_field(field_name)
+        .ok_or_else(|| ExecutionError::InvalidFieldError(field_name.to_string()))?;
+
+    Ok(fields.extract(field_name, field, target))
+}
+
+// Regexes come from users, so their compiled size is bounded and patterns
+// above the limits fail to compile instead of taking unbounded memory and
+// time. Validation compiles them too, rejecting them before execution.
+#[cfg(feature = "regex")]
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+#[cfg(feature = "regex")]
+const REGEX_DFA_SIZE_LIMIT: usize = 1 << 20;
+#[cfg(feature = "regex")]
+const REGEX_NEST_LIMIT: u32 = 64;
+
+// Regex literals are compiled on execution, so bad patterns are rejected when
+// validating, also those inside computed operands. The conditions of `if()`
+// are validated as expressions of their own.
+#[cfg(feature = "regex")]
+fn check_regexes(literal: &Literal) -> Result<(), ValidationError> {
+    match literal {
+        Literal::LiteralValue(Value::Regex(pattern)) => compile_regex(pattern)
+            .map(|_| ())
+            .map_err(ValidationError::InvalidRegexError),
+        Literal::LiteralComputed(computed) => computed
+            .get_operands()
+            .into_iter()
+            .try_for_each(check_regexes),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(feature = "regex")]
+pub(crate) fn compile_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .dfa_size_limit(REGEX_DFA_SIZE_LIMIT)
+        .nest_limit(REGEX_NEST_LIMIT)
+        .build()
+}
+
+fn did_you_mean(suggestion: &Option<String>) -> String {
+    match suggestion {
+        Some(field_name) => format!(", did you mean '{}'?", field_name),
+        None => String::new(),
     }
+}
+
+// Fills in the closest of `field_names` to an unknown field, for validators
+// that know which fields exist.
+pub(crate) fn suggest_field<'a>(
+    error: ValidationError,
+    field_names: impl IntoIterator<Item = &'a str>,
+) -> ValidationError {
+    match error {
+        ValidationError::InvalidFieldError(field_name, None) => {
+            let suggestion = closest(&field_name, field_names).map(String::from);
 
-    fn extract_literal(&self, literal: &Literal, target: &T) -> Result<Value, ExecutionError> {
-        Ok(match &literal {
-            Literal::LiteralValue(value) => value.clone(),
-            Literal::LiteralField(field_name) => {
-                let field_extractor = &self
-                    .schema
-                    .get_field(field_name)
-                    .ok_or_else(|| ExecutionError::InvalidFieldError(field_name.to_string()))?
-                    .field_extractor;
+            ValidationError::InvalidFieldError(field_name, suggestion)
+        }
+        e => e,
+    }
+}
+
+// Validation only depends on the field types, so it is shared with validators
+// that don't have a concrete schema.
+pub(crate) fn validate(
+    expression: &Expression,
+    field_type: &dyn Fn(&str) -> Option<Type>,
+) -> Result<(), ValidationError> {
+    validate_at(expression, field_type, 0)
+}
 
-                (*field_extractor)(target)
+pub(crate) fn validate_at(
+    expression: &Expression,
+    field_type: &dyn Fn(&str) -> Option<Type>,
+    depth: usize,
+) -> Result<(), ValidationError> {
+    if depth > MAX_DEPTH {
+        return Err(ValidationError::TooDeepError(MAX_DEPTH));
+    }
+
+    match expression {
+        Expression::And(and) => and
+            .get_subexpressions()
+            .iter()
+            .try_for_each(|i| validate_at(i, field_type, depth + 1)),
+        Expression::Or(or) => or
+            .get_subexpressions()
+            .iter()
+            .try_for_each(|i| validate_at(i, field_type, depth + 1)),
+        Expression::Not(not) => validate_at(not.get_subexpression(), field_type, depth + 1),
+        Expression::Operation(operation) => validate_operation(operation, field_type, depth),
+        Expression::Reference(reference) => Err(ValidationError::UnresolvedReferenceError(
+            reference.get_rule_name().to_string(),
+        )),
+    }
+}
+
+fn validate_operation(
+    operation: &Operation,
+    field_type: &dyn Fn(&str) -> Option<Type>,
+    depth: usize,
+) -> Result<(), ValidationError> {
+    #[cfg(feature = "regex")]
+    for literal in [&operation.lhs, &operation.rhs] {
+        check_regexes(literal)?;
+    }
+
+    let lhs = literal_type_at(&operation.lhs, field_type, depth)?;
+    let rhs = literal_type_at(&operation.rhs, field_type, depth)?;
+
+    let operator_error = || {
+        ValidationError::InvalidOperatorError(InvalidOperatorError(lhs, operation.op.clone(), rhs))
+    };
+
+    if rhs.is_null() || lhs.is_null() {
+        return match operation.op {
+            Operator::Eq | Operator::Ne | Operator::In => Ok(()),
+            _ => Err(operator_error()),
+        };
+    }
+
+    match lhs {
+        Type::String => match rhs {
+            Type::String => match operation.op {
+                Operator::Eq | Operator::Ne | Operator::In => Ok(()),
+                // Invalid operation
+                _ => Err(operator_error()),
+            },
+            Type::StringList => match operation.op {
+                Operator::In => Ok(()),
+                _ => Err(operator_error()),
+            },
+            _ => Err(operator_error()),
+        },
+        #[cfg(feature = "regex")]
+        Type::Regex => match rhs {
+            Type::String => match operation.op {
+                Operator::In => Ok(()),
+                _ => Err(operator_error()),
+            },
+            Type::StringList => match operation.op {
+                Operator::In => Ok(()),
+                _ => Err(operator_error()),
+            },
+            _ => Err(operator_error()),
+        },
+        Type::Number => match rhs {
+            Type::Number => match operation.op {
+                Operator::Eq
+                | Operator::Ne
+                | Operator::Gt
+                | Operator::Gte
+                | Operator::Lt
+                | Operator::Lte => Ok(()),
+                _ => Err(operator_error()),
+            },
+            Type::NumberList => match operation.op {
+                Operator::In => Ok(()),
+                _ => Err(operator_error()),
+            },
+            _ => Err(operator_error()),
+        },
+        Type::Boolean => match rhs {
+            Type::Boolean => match operation.op {
+                Operator::Eq | Operator::Ne => Ok(()),
+                _ => Err(operator_error()),
+            },
+            Type::BooleanList => match operation.op {
+                Operator::In => Ok(()),
+                _ => Err(operator_error()),
+            },
+            _ => Err(operator_error()),
+        },
+        #[cfg(feature = "raw")]
+        Type::Raw => match rhs {
+            Type::Raw => match operation.op {
+                Operator::Eq | Operator::Ne | Operator::In => Ok(()),
+                _ => Err(operator_error()),
+            },
+            Type::RawList => match operation.op {
+                Operator::In => Ok(()),
+                _ => Err(operator_error()),
+            },
+            _ => Err(operator_error()),
+        },
+        #[cfg(feature = "chrono")]
+        Type::DateTime => match rhs {
+            Type::DateTime => match operation.op {
+                Operator::Eq
+                | Operator::Ne
+                | Operator::Gt
+                | Operator::Gte
+                | Operator::Lt
+                | Operator::Lte => Ok(()),
+                _ => Err(operator_error()),
+            },
+            Type::DateTimeList => match operation.op {
+                Operator::In => Ok(()),
+                _ => Err(operator_error()),
+            },
+            _ => Err(operator_error()),
+        },
+        Type::StringList => match rhs {
+            Type::StringList => match operation.op {
+                Operator::Eq | Operator::Ne => Ok(()),
+                _ => Err(operator_error()),
+            },
+            _ => Err(operator_error()),
+        },
+        Type::NumberList => match rhs {
+            Type::NumberList => match operation.op {
+                Operator::Eq | Operator::Ne => Ok(()),
+                _ => Err(operator_error()),
+            },
+            _ => Err(operator_error()),
+        },
+        Type::BooleanList => match rhs {
+            Type::BooleanList => match operation.op {
+                Operator::Eq | Operator::Ne => Ok(()),
+                _ => Err(operator_error()),
+            },
+            _ => Err(operator_error()),
+        },
+        #[cfg(feature = "raw")]
+        Type::RawList => match rhs {
+            Type::RawList => match operation.op {
+                Operator::Eq | Operator::Ne => Ok(()),
+                _ => Err(operator_error()),
+            },
+            _ => Err(operator_error()),
+        },
+        #[cfg(feature = "chrono")]
+        Type::DateTimeList => match rhs {
+            Type::DateTimeList => match operation.op {
+                Operator::Eq | Operator::Ne => Ok(()),
+                _ => Err(operator_error()),
+            },
+            _ => Err(operator_error()),
+        },
+        Type::Null => Ok(()),
+    }
+}
+
+pub(crate) fn literal_type_at(
+    literal: &Literal,
+    field_type: &dyn Fn(&str) -> Option<Type>,
+    depth: usize,
+) -> Result<Type, ValidationError> {
+    Ok(match &literal {
+        Literal::LiteralValue(value) => value.get_type(),
+        Literal::LiteralField(field_name) => field_type(field_name)
+            .ok_or_else(|| ValidationError::InvalidFieldError(field_name.to_string(), None))?,
+        #[cfg(feature = "chrono")]
+        Literal::LiteralAge(field_name) => match field_type(field_name) {
+            Some(Type::DateTime) => Type::Number,
+            Some(_) => return Err(ValidationError::NotADateTimeError(field_name.to_string())),
+            None => {
+                return Err(ValidationError::InvalidFieldError(
+                    field_name.to_string(),
+                    None,
+                ));
             }
-        })
+        },
+        Literal::LiteralComputed(computed) => computed_type(computed, field_type, depth)?,
+    })
+}
+
+#[cfg(all(test, feature = "std", feature = "regex"))]
+mod tests {
+    use crate::{Parser, SchemaBuilder};
+
+    use super::*;
+
+    #[test]
+    fn rejects_invalid_regexes_inside_computed_operands() {
+        let engine = Engine::new(
+            SchemaBuilder::<String>::new()
+                .with_string_field("name", |name| Some(name.clone()))
+                .build(),
+        );
+        for input in [
+            "(/(/ ?? /a/) IN name",
+            "(/a/ ?? /(/) IN name",
+            r#"if(name == "a", /(/, /a/) IN name"#,
+            r#"if(/(/ IN name, /a/, /b/) IN name"#,
+        ] {
+            let expression = Parser::parse(input).unwrap();
+            assert!(
+                matches!(
+                    engine.validate(&expression),
+                    Err(ValidationError::InvalidRegexError(_))
+                ),
+                "{}",
+                input
+            );
+        }
+        let expression = Parser::parse("(/a/ ?? /b/) IN name").unwrap();
+        engine.validate(&expression).unwrap();
     }
 }
@@ -0,0 +1,132 @@
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt::{Display, Formatter};
+
+use crate::{
+    engine::{ExecutionError, Fields, execute_operation, extract_literal},
+    expression::{Expression, FieldName, MAX_DEPTH, Operation},
+    schema::Value,
+    serialize::Serialize,
+};
+
+// An operation that made an expression fail, with the values its fields had.
+// Operations under an odd number of negations fail by holding, those are
+// `negated`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Failure {
+    pub operation: Operation,
+    pub negated: bool,
+    pub values: Vec<(FieldName, Value)>,
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self.negated {
+            true => write!(f, "!({}) failed", Serialize::fmt(&self.operation))?,
+            false => write!(f, "{} failed", Serialize::fmt(&self.operation))?,
+        }
+
+        match self.values.as_slice() {
+            [] => Ok(()),
+            [(_, value)] => write!(f, ": actual value {}", Serialize::fmt(value)),
+            values => {
+                let values = values
+                    .iter()
+                    .map(|(field_name, value)| {
+                        let mut out = field_name.to_string();
+                        out.push_str(" = ");
+                        out.push_str(&Serialize::fmt(value));
+                        out
+                    })
+                    .collect::<Vec<String>>();
+
+                write!(f, ": actual values {}", values.join(", "))
+            }
+        }
+    }
+}
+
+// Executes like the engine, skipping the same subexpressions, and keeps the
+// operations deciding the result: the first deciding subexpression of a group,
+// or all of them when none decides early.
+pub(crate) fn explain_failure<T>(
+    expression: &Expression,
+    target: &T,
+    fields: &Fields<T>,
+) -> Result<Vec<Failure>, ExecutionError> {
+    let (result, operations) = explain_at(expression, target, fields, 0)?;
+    if result {
+        return Ok(Vec::new());
+    }
+
+    operations
+        .into_iter()
+        .map(|(operation, result)| {
+            let mut values = Vec::new();
+            for literal in [&operation.lhs, &operation.rhs] {
+                for (field_name, operand) in literal.get_field_operands() {
+                    values.push((
+                        field_name.clone(),
+                        extract_literal(operand, target, fields)?,
+                    ));
+                }
+            }
+
+            Ok(Failure {
+                operation: operation.clone(),
+                negated: result,
+                values,
+            })
+        })
+        .collect()
+}
+
+type Deciding<'a> = Vec<(&'a Operation, bool)>;
+
+fn explain_at<'a, T>(
+    expression: &'a Expression,
+    target: &T,
+    fields: &Fields<T>,
+    depth: usize,
+) -> Result<(bool, Deciding<'a>), ExecutionError> {
+    if depth > MAX_DEPTH {
+        return Err(ExecutionError::TooDeepError(MAX_DEPTH));
+    }
+
+    let (subexpressions, decisive) = match expression {
+        Expression::And(and) => (and.get_subexpressions(), false),
+        Expression::Or(or) => (or.get_subexpressions(), true),
+        Expression::Not(not) => {
+            let (result, operations) =
+                explain_at(not.get_subexpression(), target, fields, depth + 1)?;
+
+            return Ok((!result, operations));
+        }
+        Expression::Operation(operation) => {
+            let result = execute_operation(operation, target, fields)?;
+
+            return Ok((result, vec![(operation, result)]));
+        }
+        Expression::Reference(reference) => {
+            return Err(ExecutionError::UnresolvedReferenceError(
+                reference.get_rule_name().to_string(),
+            ));
+        }
+    };
+
+    let mut operations = Vec::new();
+    for subexpression in subexpressions {
+        let (result, subexpression_operations) =
+            explain_at(subexpression, target, fields, depth + 1)?;
+        if result == decisive {
+            return Ok((decisive, subexpression_operations));
+        }
+
+        operations.extend(subexpression_operations);
+    }
+
+    Ok((!decisive, operations))
+}
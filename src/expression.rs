@@ -1,20 +1,189 @@
-use crate::{schema::Value, serialize::Serialize};
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet},
+    string::String,
+    vec,
+    vec::Vec,
+};
+use core::ops;
 
-#[derive(Clone, Debug)]
+use smol_str::SmolStr;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use crate::{
+    binary::{self, DecodeError, Encode},
+    canonical::Canonicalize,
+    conjuncts, describe,
+    diff::{self, Diff},
+    dot,
+    iter::{Operations, OperationsWithPath},
+    misc::fnv1a,
+    normal_form::{self, NormalFormError},
+    schema::{Schema, Value},
+    serialize::{self, Serialize},
+    simplify,
+    transform::{BindField, ExpressionTransformer},
+};
+
+// Field names are short and repeated across expressions. They are stored
+// inline up to 23 bytes and shared by reference count beyond that, so parsing
+// and cloning them doesn't allocate.
+pub type FieldName = SmolStr;
+
+// How many levels of groups, negations, conditionals and parenthesized
+// operands can be nested. The parser, engine and binary decoder are
+// recursive, so deeper expressions are rejected instead of overflowing the
+// stack.
+pub const MAX_DEPTH: usize = 128;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Expression {
     And(And),
     Or(Or),
     Not(Not),
     Operation(Operation),
+    // `@name`, standing for a rule of a `RuleSet`.
+    Reference(Reference),
 }
 
 impl Expression {
+    // Combining with a group of the same kind extends it instead of nesting, so
+    // `a.and(b).and(c)` builds a single And of three.
+    pub fn and(self, other: Expression) -> Expression {
+        let mut subexpressions = match self {
+            Expression::And(and) => and.into_subexpressions(),
+            expression => vec![expression],
+        };
+        match other {
+            Expression::And(and) => subexpressions.extend(and.into_subexpressions()),
+            expression => subexpressions.push(expression),
+        }
+
+        Expression::And(And::new(subexpressions))
+    }
+
+    pub fn or(self, other: Expression) -> Expression {
+        let mut subexpressions = match self {
+            Expression::Or(or) => or.into_subexpressions(),
+            expression => vec![expression],
+        };
+        match other {
+            Expression::Or(or) => subexpressions.extend(or.into_subexpressions()),
+            expression => subexpressions.push(expression),
+        }
+
+        Expression::Or(Or::new(subexpressions))
+    }
+
+    // Negating a negation unwraps it instead of nesting. Also available as `!`
+    // through `ops::Not`, which needs to be in scope for method syntax.
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Expression {
+        match self {
+            Expression::Not(not) => not.into_subexpression(),
+            expression => Expression::Not(Not::new(expression)),
+        }
+    }
+
+    pub fn operations(&self) -> Operations<'_> {
+        Operations::new(self)
+    }
+
+    pub fn operations_with_path(&self) -> OperationsWithPath<'_> {
+        OperationsWithPath::new(self)
+    }
+
+    pub fn split_conjuncts(&self) -> BTreeMap<BTreeSet<String>, Vec<Expression>> {
+        conjuncts::split_conjuncts(self)
+    }
+
+    pub fn join_conjuncts(conjuncts: Vec<Expression>) -> Option<Expression> {
+        conjuncts::join_conjuncts(conjuncts)
+    }
+
     pub fn serialize(&self) -> String {
         Serialize::fmt(self)
     }
+
+    pub fn minify(&self) -> String {
+        serialize::minify(self)
+    }
+
+    pub fn describe<T>(&self, schema: &Schema<T>) -> String {
+        describe::describe(self, schema)
+    }
+
+    pub fn canonicalize(&self) -> Self {
+        Canonicalize::canonicalize(self)
+    }
+
+    // Equal for expressions that only differ in operand order, comparison
+    // direction or formatting. Only the encoded body is hashed, so format
+    // version bumps don't change the fingerprint of existing expressions.
+    pub fn fingerprint(&self) -> u64 {
+        let mut buf = Vec::new();
+        self.canonicalize().encode(&mut buf);
+
+        fnv1a(&buf)
+    }
+
+    // The differences from this expression to `other`, see `Diff`.
+    pub fn diff(&self, other: &Expression) -> Diff {
+        diff::diff(self, other)
+    }
+
+    // Structural equality of the simplified, canonicalized expressions.
+    pub fn equivalent_to(&self, other: &Expression) -> bool {
+        self.simplify().canonicalize() == other.simplify().canonicalize()
+    }
+
+    pub fn simplify(&self) -> Expression {
+        simplify::simplify(self)
+    }
+
+    pub fn to_cnf(&self, max_clauses: usize) -> Result<Expression, NormalFormError> {
+        normal_form::to_cnf(self, max_clauses)
+    }
+
+    pub fn to_dnf(&self, max_clauses: usize) -> Result<Expression, NormalFormError> {
+        normal_form::to_dnf(self, max_clauses)
+    }
+
+    // Fields missing from `renames` keep their name.
+    #[cfg(feature = "std")]
+    pub fn rename_fields(&self, renames: &HashMap<String, String>) -> Expression {
+        crate::transform::RenameFields(renames).transform_expression(self.clone())
+    }
+
+    pub fn bind_field(&self, field_name: &str, value: Value) -> Expression {
+        BindField(field_name, &value).transform_expression(self.clone())
+    }
+
+    pub fn to_dot(&self) -> String {
+        dot::to_dot(self)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        binary::to_bytes(self)
+    }
+
+    pub fn from_bytes(input: &[u8]) -> Result<Self, DecodeError> {
+        binary::from_bytes(input)
+    }
 }
 
-#[derive(Clone, Debug)]
+impl ops::Not for Expression {
+    type Output = Expression;
+
+    fn not(self) -> Expression {
+        Expression::not(self)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct And(Vec<Expression>);
 
 impl And {
@@ -25,9 +194,14 @@ impl And {
     pub fn get_subexpressions(&self) -> &Vec<Expression> {
         &self.0
     }
+
+    pub fn into_subexpressions(self) -> Vec<Expression> {
+        self.0
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Or(Vec<Expression>);
 
 impl Or {
@@ -38,9 +212,14 @@ impl Or {
     pub fn get_subexpressions(&self) -> &Vec<Expression> {
         &self.0
     }
+
+    pub fn into_subexpressions(self) -> Vec<Expression> {
+        self.0
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Not(Box<Expression>);
 
 impl Not {
@@ -51,9 +230,28 @@ impl Not {
     pub fn get_subexpression(&self) -> &Expression {
         &self.0
     }
+
+    pub fn into_subexpression(self) -> Expression {
+        *self.0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Reference(String);
+
+impl Reference {
+    pub fn new(rule_name: &str) -> Self {
+        Self(String::from(rule_name))
+    }
+
+    pub fn get_rule_name(&self) -> &str {
+        &self.0
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Operation {
     pub lhs: Literal,
     pub op: Operator,
@@ -66,13 +264,157 @@ impl Operation {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Literal {
     LiteralValue(Value),
-    LiteralField(String),
+    LiteralField(FieldName),
+    // `age_of(field)`, the seconds from a datetime field to now.
+    #[cfg(feature = "chrono")]
+    LiteralAge(FieldName),
+    // A value computed from other operands, e.g. `flags & 0x04`.
+    LiteralComputed(Box<Computed>),
+}
+
+impl Literal {
+    // The fields and ages the literal reads, each with the field's name,
+    // including those inside computed operands and their conditions.
+    pub fn get_field_operands(&self) -> Vec<(&FieldName, &Literal)> {
+        match self {
+            Literal::LiteralValue(_) => Vec::new(),
+            Literal::LiteralField(field_name) => vec![(field_name, self)],
+            #[cfg(feature = "chrono")]
+            Literal::LiteralAge(field_name) => vec![(field_name, self)],
+            Literal::LiteralComputed(computed) => {
+                let mut field_operands = Vec::new();
+                if let Computed::If(condition, _, _) = &**computed {
+                    for operation in condition.operations() {
+                        field_operands.extend(operation.lhs.get_field_operands());
+                        field_operands.extend(operation.rhs.get_field_operands());
+                    }
+                }
+                for operand in computed.get_operands() {
+                    field_operands.extend(operand.get_field_operands());
+                }
+
+                field_operands
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Computed {
+    // Integers combined from left to right. Parsed chains only mix operators
+    // of the same precedence, see `BitwiseOperator::precedence`.
+    Bitwise(Literal, Vec<(BitwiseOperator, Literal)>),
+    // Strings joined with `+`, e.g. `first_name + " " + last_name`.
+    Concat(Vec<Literal>),
+    // The first operand that isn't null, e.g. `nickname ?? name`.
+    Coalesce(Vec<Literal>),
+    // `if(condition, then, else)`, the second operand when the condition
+    // matches and the third otherwise.
+    If(Expression, Literal, Literal),
+    // `first(list)` and `last(list)`, null for empty lists.
+    First(Literal),
+    Last(Literal),
+    // `list[start..end]`, the items from `start` up to but not including
+    // `end`. Bounds past the end of the list are clamped to its length.
+    Slice(Literal, usize, usize),
+}
+
+impl Computed {
+    pub fn get_operands(&self) -> Vec<&Literal> {
+        match self {
+            Computed::Bitwise(first, rest) => core::iter::once(first)
+                .chain(rest.iter().map(|(_, operand)| operand))
+                .collect(),
+            Computed::Concat(operands) | Computed::Coalesce(operands) => operands.iter().collect(),
+            Computed::If(_, then, otherwise) => vec![then, otherwise],
+            Computed::First(operand) | Computed::Last(operand) | Computed::Slice(operand, _, _) => {
+                vec![operand]
+            }
+        }
+    }
+
+    // The same computation with every operand replaced by `f`. Conditions
+    // are kept as they are.
+    pub fn map_operands(self, mut f: impl FnMut(Literal) -> Literal) -> Computed {
+        match self {
+            Computed::Bitwise(first, rest) => Computed::Bitwise(
+                f(first),
+                rest.into_iter()
+                    .map(|(op, operand)| (op, f(operand)))
+                    .collect(),
+            ),
+            Computed::Concat(operands) => Computed::Concat(operands.into_iter().map(f).collect()),
+            Computed::Coalesce(operands) => {
+                Computed::Coalesce(operands.into_iter().map(f).collect())
+            }
+            Computed::If(condition, then, otherwise) => {
+                Computed::If(condition, f(then), f(otherwise))
+            }
+            Computed::First(operand) => Computed::First(f(operand)),
+            Computed::Last(operand) => Computed::Last(f(operand)),
+            Computed::Slice(operand, start, end) => Computed::Slice(f(operand), start, end),
+        }
+    }
+
+    // How tightly the outermost operator binds its operands. `+` binds
+    // tighter than any bitwise operator, `??` looser. Functions and slices
+    // are delimited, so never need parentheses.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Computed::Bitwise(_, rest) => rest.last().map_or(u8::MAX, |(op, _)| op.precedence()),
+            Computed::Concat(_) => 7,
+            Computed::Coalesce(_) => 2,
+            Computed::If(_, _, _)
+            | Computed::First(_)
+            | Computed::Last(_)
+            | Computed::Slice(_, _, _) => u8::MAX,
+        }
+    }
+}
+
+// Bitwise operators work on integers, which numbers hold exactly only up to
+// 2^53 either way. Operands and results beyond it are rejected, not rounded.
+pub const MAX_INTEGER: i64 = 1 << 53;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BitwiseOperator {
+    And,
+    Or,
+    Xor,
+    ShiftLeft,
+    ShiftRight,
+}
+
+impl BitwiseOperator {
+    pub fn fmt_static(&self) -> &'static str {
+        match self {
+            BitwiseOperator::And => "&",
+            BitwiseOperator::Or => "|",
+            BitwiseOperator::Xor => "^",
+            BitwiseOperator::ShiftLeft => "<<",
+            BitwiseOperator::ShiftRight => ">>",
+        }
+    }
+
+    // Like in C, shifts bind tightest, then `&`, `^` and `|`.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BitwiseOperator::Or => 3,
+            BitwiseOperator::Xor => 4,
+            BitwiseOperator::And => 5,
+            BitwiseOperator::ShiftLeft | BitwiseOperator::ShiftRight => 6,
+        }
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Operator {
     Eq,
     Ne,
@@ -0,0 +1,174 @@
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use crate::{complete::OPERATORS, parser::BITWISE_OPERATORS};
+
+// The grammar of expressions as accepted by the parser with the enabled
+// features, for tooling outside of Rust such as documentation, highlighters or
+// other parsers. Syntax of disabled value types is left out, like in the
+// parser.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Grammar {
+    pub operators: Vec<&'static str>,
+    // Operators combining operands into one, from the loosest binding.
+    pub operand_operators: Vec<&'static str>,
+    // Matched in any case, e.g. `AND` or `and`.
+    pub keywords: Vec<&'static str>,
+    // Functions taking their arguments in parentheses right after the name.
+    pub functions: Vec<&'static str>,
+    pub duration_units: Vec<&'static str>,
+    // Pairs of rule names and their definitions, see `ebnf`.
+    pub rules: Vec<(&'static str, String)>,
+}
+
+pub fn grammar() -> Grammar {
+    Grammar {
+        operators: OPERATORS.iter().map(|op| op.fmt_static()).collect(),
+        operand_operators: ["??"]
+            .into_iter()
+            .chain(BITWISE_OPERATORS.iter().map(|op| op.fmt_static()))
+            .chain(["+"])
+            .collect(),
+        keywords: vec!["and", "or", "null", "true", "false"],
+        functions: FUNCTIONS.to_vec(),
+        duration_units: DURATION_UNITS.to_vec(),
+        rules: rules(),
+    }
+}
+
+// The grammar in W3C EBNF notation, starting at `expression`. Whitespace is
+// allowed between tokens.
+pub fn ebnf() -> String {
+    let rules = rules();
+    let width = rules.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+
+    rules
+        .iter()
+        .map(|(name, definition)| format!("{:width$} ::= {}\n", name, definition, width = width))
+        .collect()
+}
+
+const FUNCTIONS: &[&str] = &[
+    #[cfg(feature = "chrono")]
+    "age_of",
+    #[cfg(feature = "timezone")]
+    "at_tz",
+    "if",
+    "first",
+    "last",
+];
+
+const DURATION_UNITS: [&str; 6] = ["ms", "s", "m", "h", "d", "w"];
+
+// Alternatives are in the order the parser tries them.
+fn rules() -> Vec<(&'static str, String)> {
+    let mut expression = vec!["group", "not"];
+    #[cfg(feature = "timezone")]
+    expression.push("at_tz");
+    expression.extend(["reference", "operation"]);
+
+    let mut literal = vec!["null", "boolean", "string"];
+    #[cfg(feature = "regex")]
+    literal.push("regex");
+    #[cfg(feature = "raw")]
+    literal.push("raw");
+    #[cfg(feature = "chrono")]
+    literal.push("datetime");
+    literal.extend(["hex", "duration", "number", "string_list"]);
+    #[cfg(feature = "raw")]
+    literal.push("raw_list");
+    #[cfg(feature = "chrono")]
+    literal.push("datetime_list");
+    literal.extend(["number_list", "boolean_list"]);
+    #[cfg(feature = "chrono")]
+    literal.push("age");
+    literal.push("field");
+    let expression = expression.join(" | ");
+    let literal = literal.join(" | ");
+
+    let mut rules = vec![
+        ("expression", expression.as_str()),
+        (
+            "group",
+            "'(' expression ( and ( expression and? )+ | or ( expression or? )+ ) ')'",
+        ),
+        ("and", "[Aa] [Nn] [Dd]"),
+        ("or", "[Oo] [Rr]"),
+        ("not", "'!' '(' expression ')'"),
+    ];
+    #[cfg(feature = "timezone")]
+    rules.push(("at_tz", "'at_tz(' string ',' expression ')'"));
+    rules.extend([
+        ("reference", "'@' [a-zA-Z_] [a-zA-Z_0-9]*"),
+        ("operation", "operand operator operand"),
+        (
+            "operator",
+            "'==' | '!=' | '>=' | '<=' | '>' | '<' | [Ii] [Nn]",
+        ),
+        ("operand", "bitwise_or ( '??' bitwise_or )*"),
+        ("bitwise_or", "bitwise_xor ( '|' bitwise_xor )*"),
+        ("bitwise_xor", "bitwise_and ( '^' bitwise_and )*"),
+        ("bitwise_and", "shift ( '&' shift )*"),
+        ("shift", "concat ( ( '<<' | '>>' ) concat )*"),
+        ("concat", "atom ( '+' atom )*"),
+        (
+            "atom",
+            "( conditional | accessor | literal | '(' operand ')' ) slice?",
+        ),
+        (
+            "conditional",
+            "'if(' expression ',' operand ',' operand ')'",
+        ),
+        ("accessor", "( 'first(' | 'last(' ) operand ')'"),
+        ("slice", "'[' digit+ '..' digit+ ']'"),
+        ("literal", literal.as_str()),
+        ("null", "[Nn] [Uu] [Ll] [Ll]"),
+        ("boolean", "[Tt] [Rr] [Uu] [Ee] | [Ff] [Aa] [Ll] [Ss] [Ee]"),
+        ("string", "'\"' ( [^\"\\] | '\\' [\"\\/bfnrt] )* '\"'"),
+    ]);
+    #[cfg(feature = "regex")]
+    rules.push(("regex", "'/' ( [^/] | '\\/' )* '/'"));
+    #[cfg(feature = "raw")]
+    rules.push(("raw", "'|' ( [0-9a-fA-F] [0-9a-fA-F] )+ '|'"));
+    #[cfg(all(feature = "chrono", not(feature = "timezone")))]
+    rules.push(("datetime", "local_datetime offset"));
+    // Without an offset the datetime is in the timezone of the enclosing
+    // `at_tz`, and doesn't parse outside of one.
+    #[cfg(feature = "timezone")]
+    rules.push(("datetime", "local_datetime offset?"));
+    #[cfg(feature = "chrono")]
+    rules.extend([
+        (
+            "local_datetime",
+            "digit digit digit digit '-' digit digit '-' digit digit 'T' digit digit ':' digit digit ':' digit digit ( '.' digit+ )?",
+        ),
+        ("offset", "'Z' | [+-] digit digit ':' digit digit"),
+    ]);
+    rules.extend([
+        ("hex", "'0x' [0-9a-fA-F]+"),
+        ("duration", "number ( 'ms' | 's' | 'm' | 'h' | 'd' | 'w' )"),
+        (
+            "number",
+            "'-'? ( [1-9] digit* | '0' ) ( '.' digit+ )? ( [eE] [+-]? digit+ )?",
+        ),
+        ("digit", "[0-9]"),
+        ("string_list", "'[' string ( ',' string )* ']'"),
+    ]);
+    #[cfg(feature = "raw")]
+    rules.push(("raw_list", "'[' raw ( ',' raw )* ']'"));
+    #[cfg(feature = "chrono")]
+    rules.push(("datetime_list", "'[' datetime ( ',' datetime )* ']'"));
+    rules.extend([
+        ("number_list", "'[' number ( ',' number )* ']'"),
+        ("boolean_list", "'[' boolean ( ',' boolean )* ']'"),
+    ]);
+    #[cfg(feature = "chrono")]
+    rules.push(("age", "'age_of(' field ')'"));
+    rules.push(("field", "[a-zA-Z_] [a-zA-Z_:0-9]*"));
+
+    rules
+        .into_iter()
+        .map(|(name, definition)| (name, String::from(definition)))
+        .collect()
+}
@@ -0,0 +1,193 @@
+use std::ops::Range;
+
+use pom::parser::end;
+
+use crate::parser;
+
+// A byte range into the input.
+pub type Span = Range<usize>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenClass {
+    // `and`, `or`, `!`, `true`, `false`, `null`, `at_tz`, `age_of`, `if`,
+    // `first` and `last`
+    Keyword,
+    Operator,
+    Field,
+    // `@name`
+    Reference,
+    String,
+    Number,
+    #[cfg(feature = "regex")]
+    Regex,
+    #[cfg(feature = "raw")]
+    Raw,
+    #[cfg(feature = "chrono")]
+    DateTime,
+    // Parentheses, brackets, commas and the `..` of slices
+    Punctuation,
+    // Anything the grammar has no place for
+    Invalid,
+}
+
+// Classifies the tokens of an expression for syntax highlighting. It works on
+// tokens alone, so invalid or half-typed input is classified as far as
+// possible instead of failing. Whitespace is not included.
+pub fn highlight(input: &str) -> Vec<(Span, TokenClass)> {
+    let bytes = input.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let class = match bytes[i] {
+            b' ' | b'\t' | b'\r' | b'\n' => {
+                i += 1;
+                continue;
+            }
+            // After an operand `|` is the bitwise operator, elsewhere it opens a
+            // raw value.
+            b'|' if follows_operand(input, &tokens) => {
+                i += 1;
+                TokenClass::Operator
+            }
+            // Unterminated strings, regexes and raw values run to the end.
+            // Regexes and raw values of disabled types are still skipped whole,
+            // but invalid.
+            delimiter @ (b'"' | b'/' | b'|') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != delimiter {
+                    if bytes[i] == b'\\' && delimiter != b'|' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                i = (i + 1).min(bytes.len());
+
+                match delimiter {
+                    b'"' => TokenClass::String,
+                    #[cfg(feature = "regex")]
+                    b'/' => TokenClass::Regex,
+                    #[cfg(feature = "raw")]
+                    b'|' => TokenClass::Raw,
+                    _ => TokenClass::Invalid,
+                }
+            }
+            b'0'..=b'9' | b'-' => {
+                i += 1;
+                // `..` separates the bounds of a slice.
+                while i < bytes.len()
+                    && (bytes[i].is_ascii_alphanumeric() || b".:+-".contains(&bytes[i]))
+                    && !bytes[i..].starts_with(b"..")
+                {
+                    i += 1;
+                }
+
+                let word = &input[start..i];
+                #[cfg(feature = "chrono")]
+                if (parser::datetime() - end()).parse(word.as_bytes()).is_ok() {
+                    tokens.push((start..i, TokenClass::DateTime));
+                    continue;
+                }
+                if (parser::number() - end()).parse(word.as_bytes()).is_ok()
+                    || (parser::hex() - end()).parse(word.as_bytes()).is_ok()
+                    || (parser::duration() - end()).parse(word.as_bytes()).is_ok()
+                {
+                    TokenClass::Number
+                } else {
+                    TokenClass::Invalid
+                }
+            }
+            b'a'..=b'z' | b'A'..=b'Z' | b'_' => {
+                while i < bytes.len()
+                    && (bytes[i].is_ascii_alphanumeric() || b"_:".contains(&bytes[i]))
+                {
+                    i += 1;
+                }
+
+                let word = &input[start..i];
+                if word.eq_ignore_ascii_case("in") {
+                    TokenClass::Operator
+                } else if ["and", "or", "true", "false", "null"]
+                    .iter()
+                    .any(|keyword| word.eq_ignore_ascii_case(keyword))
+                    || (["at_tz", "age_of", "if", "first", "last"].contains(&word)
+                        && bytes.get(i) == Some(&b'('))
+                {
+                    TokenClass::Keyword
+                } else {
+                    TokenClass::Field
+                }
+            }
+            b'@' => {
+                i += 1;
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+
+                match i - start {
+                    1 => TokenClass::Invalid,
+                    _ => TokenClass::Reference,
+                }
+            }
+            b'=' | b'!' | b'<' | b'>' => {
+                let two = &bytes[i..(i + 2).min(bytes.len())];
+                i += match two {
+                    b"==" | b"!=" | b">=" | b"<=" | b"<<" | b">>" => 2,
+                    _ => 1,
+                };
+
+                match &bytes[start..i] {
+                    b"!" => TokenClass::Keyword,
+                    b"=" => TokenClass::Invalid,
+                    _ => TokenClass::Operator,
+                }
+            }
+            b'&' | b'^' | b'+' => {
+                i += 1;
+                TokenClass::Operator
+            }
+            b'?' => {
+                i += 1;
+                match bytes.get(i) {
+                    Some(b'?') => {
+                        i += 1;
+                        TokenClass::Operator
+                    }
+                    _ => TokenClass::Invalid,
+                }
+            }
+            b'(' | b')' | b'[' | b']' | b',' => {
+                i += 1;
+                TokenClass::Punctuation
+            }
+            b'.' if bytes[i..].starts_with(b"..") => {
+                i += 2;
+                TokenClass::Punctuation
+            }
+            _ => {
+                i += input[i..].chars().next().map_or(1, char::len_utf8);
+                TokenClass::Invalid
+            }
+        };
+
+        tokens.push((start..i, class));
+    }
+
+    tokens
+}
+
+// Whether the previous token ends an operand.
+fn follows_operand(input: &str, tokens: &[(Span, TokenClass)]) -> bool {
+    let Some((span, class)) = tokens.last() else {
+        return false;
+    };
+
+    match class {
+        TokenClass::Keyword => ["true", "false", "null"]
+            .iter()
+            .any(|keyword| input[span.clone()].eq_ignore_ascii_case(keyword)),
+        TokenClass::Punctuation => matches!(&input[span.clone()], ")" | "]"),
+        TokenClass::Operator | TokenClass::Invalid => false,
+        _ => true,
+    }
+}
@@ -0,0 +1,84 @@
+use alloc::{vec, vec::Vec};
+
+use crate::expression::{Expression, Operation};
+
+// Iterates over the operations of an expression from left to right.
+pub struct Operations<'a> {
+    stack: Vec<&'a Expression>,
+}
+
+impl<'a> Operations<'a> {
+    pub fn new(expression: &'a Expression) -> Self {
+        Self {
+            stack: vec![expression],
+        }
+    }
+}
+
+impl<'a> Iterator for Operations<'a> {
+    type Item = &'a Operation;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.pop()? {
+                Expression::And(and) => self.stack.extend(and.get_subexpressions().iter().rev()),
+                Expression::Or(or) => self.stack.extend(or.get_subexpressions().iter().rev()),
+                Expression::Not(not) => self.stack.push(not.get_subexpression()),
+                Expression::Operation(operation) => return Some(operation),
+                // Referenced rules aren't resolved, so their operations are skipped.
+                Expression::Reference(_) => {}
+            }
+        }
+    }
+}
+
+// One step from a node to its child.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    And(usize),
+    Or(usize),
+    Not,
+}
+
+// Like `Operations`, additionally yielding the path from the root to each operation.
+pub struct OperationsWithPath<'a> {
+    stack: Vec<(&'a Expression, Vec<PathSegment>)>,
+}
+
+impl<'a> OperationsWithPath<'a> {
+    pub fn new(expression: &'a Expression) -> Self {
+        Self {
+            stack: vec![(expression, Vec::new())],
+        }
+    }
+}
+
+impl<'a> Iterator for OperationsWithPath<'a> {
+    type Item = (Vec<PathSegment>, &'a Operation);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (expression, path) = self.stack.pop()?;
+            let (subexpressions, segment): (&[Expression], fn(usize) -> PathSegment) =
+                match expression {
+                    Expression::And(and) => (and.get_subexpressions(), PathSegment::And),
+                    Expression::Or(or) => (or.get_subexpressions(), PathSegment::Or),
+                    Expression::Not(not) => {
+                        let mut path = path;
+                        path.push(PathSegment::Not);
+                        self.stack.push((not.get_subexpression(), path));
+
+                        continue;
+                    }
+                    Expression::Operation(operation) => return Some((path, operation)),
+                    Expression::Reference(_) => continue,
+                };
+
+            for (i, subexpression) in subexpressions.iter().enumerate().rev() {
+                let mut path = path.clone();
+                path.push(segment(i));
+                self.stack.push((subexpression, path));
+            }
+        }
+    }
+}
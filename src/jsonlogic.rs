@@ -0,0 +1,165 @@
+use serde_json::{Map, Value as Json};
+use thiserror::Error;
+
+use crate::{
+    expression::{And, Expression, Literal, Not, Operation, Operator, Or},
+    parser::ExpressionParser,
+    schema::Value,
+};
+
+#[derive(Error, Debug)]
+pub enum JsonLogicError {
+    #[error("Expected a rule object with a single operator, found {0}")]
+    InvalidRuleError(Json),
+    #[error("The operator '{0}' is not supported")]
+    UnsupportedOperatorError(String),
+    #[error("The operator '{0}' expects {1} arguments")]
+    InvalidArgumentsError(String, &'static str),
+    #[error("Cannot use {0} as an operand")]
+    InvalidOperandError(Json),
+}
+
+impl ExpressionParser {
+    pub fn from_jsonlogic(rule: &Json) -> Result<Expression, JsonLogicError> {
+        let (op, args) = split_rule(rule)?;
+
+        match op {
+            "and" | "or" => {
+                let mut subexpressions =
+                    args.iter()
+                        .map(Self::from_jsonlogic)
+                        .collect::<Result<Vec<Expression>, JsonLogicError>>()?;
+
+                match subexpressions.len() {
+                    0 => Err(JsonLogicError::InvalidArgumentsError(
+                        op.to_string(),
+                        "at least 1",
+                    )),
+                    1 => Ok(subexpressions.remove(0)),
+                    _ if op == "and" => Ok(Expression::And(And::new(subexpressions))),
+                    _ => Ok(Expression::Or(Or::new(subexpressions))),
+                }
+            }
+            "!" => match args.as_slice() {
+                [arg] => Ok(Expression::Not(Not::new(Self::from_jsonlogic(arg)?))),
+                _ => Err(JsonLogicError::InvalidArgumentsError(op.to_string(), "1")),
+            },
+            "==" | "===" => binary(op, Operator::Eq, &args),
+            "!=" | "!==" => binary(op, Operator::Ne, &args),
+            ">" => binary(op, Operator::Gt, &args),
+            ">=" => binary(op, Operator::Gte, &args),
+            "in" => binary(op, Operator::In, &args),
+            // `{"<": [a, b, c]}` is JsonLogic's "between" form.
+            "<" | "<=" => {
+                let operator = if op == "<" {
+                    Operator::Lt
+                } else {
+                    Operator::Lte
+                };
+
+                match args.as_slice() {
+                    [_, _] => binary(op, operator, &args),
+                    [lower, value, upper] => Ok(Expression::And(And::new(vec![
+                        Expression::Operation(Operation::new(
+                            operand(lower)?,
+                            operator.clone(),
+                            operand(value)?,
+                        )),
+                        Expression::Operation(Operation::new(
+                            operand(value)?,
+                            operator,
+                            operand(upper)?,
+                        )),
+                    ]))),
+                    _ => Err(JsonLogicError::InvalidArgumentsError(
+                        op.to_string(),
+                        "2 or 3",
+                    )),
+                }
+            }
+            op => Err(JsonLogicError::UnsupportedOperatorError(op.to_string())),
+        }
+    }
+}
+
+fn split_rule(rule: &Json) -> Result<(&str, Vec<Json>), JsonLogicError> {
+    let object = rule
+        .as_object()
+        .filter(|object| object.len() == 1)
+        .ok_or_else(|| JsonLogicError::InvalidRuleError(rule.clone()))?;
+    let (op, args) = object.iter().next().unwrap();
+
+    // Unary operators may be written without the argument array.
+    let args = match args {
+        Json::Array(args) => args.clone(),
+        arg => vec![arg.clone()],
+    };
+
+    Ok((op.as_str(), args))
+}
+
+fn binary(op: &str, operator: Operator, args: &[Json]) -> Result<Expression, JsonLogicError> {
+    match args {
+        [lhs, rhs] => Ok(Expression::Operation(Operation::new(
+            operand(lhs)?,
+            operator,
+            operand(rhs)?,
+        ))),
+        _ => Err(JsonLogicError::InvalidArgumentsError(op.to_string(), "2")),
+    }
+}
+
+fn operand(json: &Json) -> Result<Literal, JsonLogicError> {
+    let invalid = || JsonLogicError::InvalidOperandError(json.clone());
+
+    Ok(match json {
+        Json::Object(object) => Literal::LiteralField(var_name(object).ok_or_else(invalid)?.into()),
+        Json::Null => Literal::LiteralValue(Value::Null),
+        Json::Bool(val) => Literal::LiteralValue(Value::Boolean(*val)),
+        Json::Number(val) => {
+            Literal::LiteralValue(Value::Number(val.as_f64().ok_or_else(invalid)?))
+        }
+        Json::String(val) => Literal::LiteralValue(Value::String(val.clone())),
+        Json::Array(items) => Literal::LiteralValue(list(items).ok_or_else(invalid)?),
+    })
+}
+
+// Only plain `{"var": "a.b"}` references are supported; JsonLogic's dotted paths
+// map onto this crate's `:`-separated nested field names.
+fn var_name(object: &Map<String, Json>) -> Option<String> {
+    if object.len() != 1 {
+        return None;
+    }
+
+    let name = match object.get("var")? {
+        Json::String(name) => name,
+        Json::Array(args) if args.len() == 1 => args[0].as_str()?,
+        _ => return None,
+    };
+
+    Some(name.replace('.', ":"))
+}
+
+fn list(items: &[Json]) -> Option<Value> {
+    match items.first()? {
+        Json::String(_) => Some(Value::StringList(
+            items
+                .iter()
+                .map(|item| item.as_str().map(String::from))
+                .collect::<Option<Vec<String>>>()?,
+        )),
+        Json::Number(_) => Some(Value::NumberList(
+            items
+                .iter()
+                .map(Json::as_f64)
+                .collect::<Option<Vec<f64>>>()?,
+        )),
+        Json::Bool(_) => Some(Value::BooleanList(
+            items
+                .iter()
+                .map(Json::as_bool)
+                .collect::<Option<Vec<bool>>>()?,
+        )),
+        _ => None,
+    }
+}
@@ -1,12 +1,85 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 pub use engine::Engine;
 pub use expression::Expression;
+#[cfg(feature = "std")]
 pub use parser::ExpressionParser as Parser;
+pub use rules::RuleSet;
 pub use schema::{Schema, SchemaBuilder};
 
+// Used by code generated by `expression_macros::expr!`.
+#[cfg(feature = "chrono")]
+#[doc(hidden)]
+pub use chrono;
+
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "std")]
+pub mod audit;
+pub mod binary;
+pub mod budget;
+pub mod builder;
+pub mod canonical;
+#[cfg(feature = "chrono")]
+pub mod clock;
+pub mod compiled;
+#[cfg(feature = "std")]
+pub mod complete;
+pub mod composite;
+pub mod conjuncts;
+#[cfg(feature = "csv")]
+pub mod csv;
+pub mod dataset;
+pub mod describe;
+pub mod descriptor;
+pub mod diff;
+pub mod dot;
 pub mod engine;
+pub mod explain;
 pub mod expression;
+#[cfg(feature = "std")]
+pub mod grammar;
+#[cfg(feature = "std")]
+pub mod highlight;
+pub mod iter;
+#[cfg(feature = "jsonlogic")]
+pub mod jsonlogic;
+#[cfg(feature = "library")]
+pub mod library;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+pub mod multi;
+pub mod normal_form;
+#[cfg(feature = "std")]
+pub mod odata;
+pub mod overlay;
+#[cfg(feature = "std")]
 pub mod parser;
+pub mod row;
+pub mod rules;
 pub mod schema;
 pub mod serialize;
+pub mod simplify;
+pub mod stream;
+pub mod testing;
+pub mod transform;
+#[cfg(feature = "std")]
+pub mod transpile;
+#[cfg(feature = "std")]
+pub mod typescript;
+pub mod union;
+pub mod untyped;
+pub mod visit;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "watch")]
+pub mod watch;
 
+mod computed;
+#[cfg(feature = "metrics")]
+mod metrics;
 mod misc;
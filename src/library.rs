@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    Engine, Parser, RuleSet, engine::ValidationError, expression::Expression, parser::ParseError,
+    rules::RuleError, serialize,
+};
+
+#[derive(Error, Debug)]
+pub enum LibraryError {
+    #[error("Invalid JSON: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[cfg(feature = "toml")]
+    #[error("Invalid TOML: {0}")]
+    TomlReadError(#[from] toml::de::Error),
+    #[cfg(feature = "toml")]
+    #[error("Failed to write TOML: {0}")]
+    TomlWriteError(#[from] toml::ser::Error),
+    #[cfg(feature = "yaml")]
+    #[error("Invalid YAML: {0}")]
+    YamlError(#[from] serde_yaml_ng::Error),
+}
+
+// Why an entry of a library failed to load, each naming the entry.
+#[derive(Error, Debug)]
+pub enum EntryError {
+    #[error("Entry '{0}' is defined more than once")]
+    DuplicateEntryError(String),
+    #[error("Entry '{0}' doesn't parse: {1}")]
+    ParseError(String, ParseError),
+    #[error("Entry '{0}' is for schema version {1}, not {2}")]
+    SchemaVersionError(String, u32, u32),
+    #[error("Entry '{0}' has an invalid reference: {1}")]
+    ReferenceError(String, RuleError),
+    #[error("Entry '{0}' is invalid: {1}")]
+    InvalidEntryError(String, ValidationError),
+}
+
+// A collection of named expressions to store outside of the program, in JSON
+// or, with their features, TOML or YAML. Entries can use each other through
+// `@name` references, like the rules of a `RuleSet`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExpressionLibrary {
+    pub entries: Vec<LibraryEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LibraryEntry {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    // The version of the schema the expression was written against.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u32>,
+    #[serde(flatten)]
+    pub source: EntrySource,
+}
+
+// Stored as an `expression` key holding the text, or an `ast` key holding the
+// expression's structure.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntrySource {
+    Expression(String),
+    Ast(Expression),
+}
+
+impl ExpressionLibrary {
+    // Entries for the rules of the set, as text.
+    pub fn from_rules<A>(rules: &RuleSet<A>) -> Self {
+        let entries = rules
+            .rule_names()
+            .filter_map(|rule_name| {
+                rules.get_rule(rule_name).map(|expression| LibraryEntry {
+                    name: String::from(rule_name),
+                    description: None,
+                    schema_version: None,
+                    source: EntrySource::Expression(serialize::Serialize::fmt(expression)),
+                })
+            })
+            .collect();
+
+        Self { entries }
+    }
+
+    pub fn from_json(input: &str) -> Result<Self, LibraryError> {
+        Ok(serde_json::from_str(input)?)
+    }
+
+    pub fn to_json(&self) -> Result<String, LibraryError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn from_toml(input: &str) -> Result<Self, LibraryError> {
+        Ok(toml::from_str(input)?)
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn to_toml(&self) -> Result<String, LibraryError> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    // YAML goes through JSON values, so enums of the AST are maps like in
+    // JSON rather than YAML tags, which can't be nested.
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(input: &str) -> Result<Self, LibraryError> {
+        let json = serde_yaml_ng::from_str::<serde_json::Value>(input)?;
+
+        Ok(serde_json::from_value(json)?)
+    }
+
+    #[cfg(feature = "yaml")]
+    pub fn to_yaml(&self) -> Result<String, LibraryError> {
+        Ok(serde_yaml_ng::to_string(&serde_json::to_value(self)?)?)
+    }
+
+    // Parses every entry and validates it with the engine, giving the entries
+    // as rules. Fails with the errors of every invalid entry.
+    // Entries with a schema version other than `schema_version` are invalid,
+    // unless it's None.
+    pub fn load<T>(
+        &self,
+        engine: &Engine<T>,
+        schema_version: Option<u32>,
+    ) -> Result<RuleSet, Vec<EntryError>> {
+        let mut errors = Vec::new();
+        let mut rules = RuleSet::new();
+        let mut loaded = Vec::new();
+        for entry in &self.entries {
+            match parse_entry(entry, schema_version) {
+                Ok(_) if rules.get_rule(&entry.name).is_some() => {
+                    errors.push(EntryError::DuplicateEntryError(entry.name.clone()));
+                }
+                Ok(expression) => {
+                    rules.insert(&entry.name, expression);
+                    loaded.push(&entry.name);
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+
+        for name in loaded {
+            let expression = match rules.resolve(name) {
+                Ok(expression) => expression,
+                Err(e) => {
+                    errors.push(EntryError::ReferenceError(name.clone(), e));
+                    continue;
+                }
+            };
+            if let Err(e) = engine.validate(&expression) {
+                errors.push(EntryError::InvalidEntryError(name.clone(), e));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(rules)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+fn parse_entry(
+    entry: &LibraryEntry,
+    schema_version: Option<u32>,
+) -> Result<Expression, EntryError> {
+    if let (Some(expected), Some(version)) = (schema_version, entry.schema_version)
+        && expected != version
+    {
+        return Err(EntryError::SchemaVersionError(
+            entry.name.clone(),
+            version,
+            expected,
+        ));
+    }
+
+    match &entry.source {
+        EntrySource::Expression(text) => {
+            Parser::parse(text).map_err(|e| EntryError::ParseError(entry.name.clone(), e))
+        }
+        EntrySource::Ast(expression) => Ok(expression.clone()),
+    }
+}
@@ -0,0 +1,398 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
+
+use serde_json::{Value as Json, json};
+
+use crate::{
+    complete::{Suggestion, SuggestionKind, complete},
+    descriptor::SchemaDescriptor,
+    engine::{self, ValidationError},
+    expression::Expression,
+    highlight::{Span, TokenClass, highlight},
+    parser::{ExpressionParser, ParseError},
+};
+
+// Spans are byte ranges into the document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub span: Span,
+    pub message: String,
+}
+
+// A language server for documents holding a single expression, checked against
+// a descriptor. The analyses are usable on their own, `run` serves them over
+// the Language Server Protocol on a stdio-like stream.
+pub struct LanguageServer {
+    descriptor: SchemaDescriptor,
+    documents: HashMap<String, String>,
+}
+
+impl LanguageServer {
+    pub fn new(descriptor: SchemaDescriptor) -> Self {
+        Self {
+            descriptor,
+            documents: HashMap::new(),
+        }
+    }
+
+    pub fn get_descriptor(&self) -> &SchemaDescriptor {
+        &self.descriptor
+    }
+
+    pub fn diagnostics(&self, text: &str) -> Vec<Diagnostic> {
+        let expression = match ExpressionParser::parse(text) {
+            Ok(expression) => expression,
+            Err(e) => return vec![parse_diagnostic(text, e)],
+        };
+
+        // Operations are visited in source order, as are the field tokens, so
+        // each operation's fields are the next matching tokens.
+        let tokens = field_tokens(text);
+        let mut next_token = 0;
+        let mut diagnostics = Vec::new();
+        for operation in expression.operations() {
+            let mut fields = Vec::new();
+            let literals = [&operation.lhs, &operation.rhs];
+            for (field_name, _) in literals
+                .iter()
+                .flat_map(|literal| literal.get_field_operands())
+            {
+                if let Some(i) = tokens[next_token..]
+                    .iter()
+                    .position(|(_, name)| name == field_name)
+                {
+                    next_token += i + 1;
+                    fields.push(&tokens[next_token - 1]);
+                }
+            }
+
+            let result = engine::validate(&Expression::Operation(operation.clone()), &|name| {
+                self.descriptor
+                    .get_field(name)
+                    .map(|field| field.field_type)
+            })
+            .map_err(|e| {
+                let field_names = self
+                    .descriptor
+                    .fields
+                    .iter()
+                    .map(|field| field.name.as_str());
+                engine::suggest_field(e, field_names)
+            });
+            let Err(e) = result else {
+                continue;
+            };
+
+            // Unknown fields point at the field itself, type errors at the
+            // whole operation. Operations without fields can't be located.
+            let span = match &e {
+                ValidationError::InvalidFieldError(field_name, _) => fields
+                    .iter()
+                    .find(|(_, name)| name == field_name)
+                    .map(|(span, _)| span.clone()),
+                ValidationError::InvalidOperatorError(_)
+                | ValidationError::InvalidOperandError(_, _)
+                | ValidationError::NotAnIntegerError(_)
+                | ValidationError::IntegerOutOfRangeError
+                | ValidationError::IncompatibleOperandsError(_, _) => {
+                    match (fields.first(), fields.last()) {
+                        (Some((first, _)), Some((last, _))) => Some(first.start..last.end),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
+
+            diagnostics.push(Diagnostic {
+                span: span.unwrap_or(0..text.len()),
+                message: e.to_string(),
+            });
+        }
+
+        diagnostics
+    }
+
+    // Markdown describing the field under the cursor.
+    pub fn hover(&self, text: &str, offset: usize) -> Option<String> {
+        let (_, name) = field_tokens(text)
+            .into_iter()
+            .find(|(span, _)| span.start <= offset && offset <= span.end)?;
+        let field = self.descriptor.get_field(&name)?;
+
+        Some(match &field.description {
+            Some(description) => format!(
+                "**{}**: {}\n\n{}",
+                field.name,
+                field.field_type.variant_name(),
+                description
+            ),
+            None => format!("**{}**: {}", field.name, field.field_type.variant_name()),
+        })
+    }
+
+    pub fn completions(&self, text: &str, offset: usize) -> Vec<Suggestion> {
+        complete(text, offset, &self.descriptor)
+    }
+
+    // Serves requests until the client sends `exit` or closes the stream.
+    pub fn run(&mut self, mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        while let Some(message) = read_message(&mut input)? {
+            let method = message["method"].as_str().unwrap_or_default();
+            let params = &message["params"];
+
+            let result = match method {
+                "initialize" => json!({
+                    "capabilities": {
+                        "textDocumentSync": 1,
+                        "hoverProvider": true,
+                        "completionProvider": {},
+                    },
+                }),
+                "textDocument/didOpen" => {
+                    let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                    let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                    self.update(uri, text, &mut output)?;
+                    continue;
+                }
+                "textDocument/didChange" => {
+                    let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                    // Full sync, the last change holds the whole document.
+                    let text = params["contentChanges"]
+                        .as_array()
+                        .and_then(|changes| changes.last())
+                        .and_then(|change| change["text"].as_str())
+                        .unwrap_or_default();
+                    self.update(uri, text, &mut output)?;
+                    continue;
+                }
+                "textDocument/didClose" => {
+                    let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                    self.documents.remove(uri);
+                    write_message(
+                        &mut output,
+                        &json!({
+                            "jsonrpc": "2.0",
+                            "method": "textDocument/publishDiagnostics",
+                            "params": { "uri": uri, "diagnostics": [] },
+                        }),
+                    )?;
+                    continue;
+                }
+                "textDocument/hover" => match self.document_offset(params) {
+                    Some((text, offset)) => match self.hover(text, offset) {
+                        Some(contents) => {
+                            json!({ "contents": { "kind": "markdown", "value": contents } })
+                        }
+                        None => Json::Null,
+                    },
+                    None => Json::Null,
+                },
+                "textDocument/completion" => match self.document_offset(params) {
+                    Some((text, offset)) => Json::Array(
+                        self.completions(text, offset)
+                            .into_iter()
+                            .map(|suggestion| {
+                                let range = json!({
+                                    "start": position(text, suggestion.span.start),
+                                    "end": position(text, suggestion.span.end),
+                                });
+
+                                json!({
+                                    "label": suggestion.label,
+                                    // The protocol's CompletionItemKind.
+                                    "kind": match suggestion.kind {
+                                        SuggestionKind::Field => 5,
+                                        SuggestionKind::Operator => 24,
+                                        SuggestionKind::Keyword => 14,
+                                        SuggestionKind::Template => 15,
+                                    },
+                                    "detail": suggestion.detail,
+                                    "textEdit": { "range": range, "newText": suggestion.insert_text },
+                                })
+                            })
+                            .collect(),
+                    ),
+                    None => Json::Array(Vec::new()),
+                },
+                "shutdown" => Json::Null,
+                "exit" => return Ok(()),
+                _ => {
+                    // Unknown notifications are ignored, unknown requests fail.
+                    if message.get("id").is_some() {
+                        write_message(
+                            &mut output,
+                            &json!({
+                                "jsonrpc": "2.0",
+                                "id": message["id"],
+                                "error": { "code": -32601, "message": "Method not found" },
+                            }),
+                        )?;
+                    }
+                    continue;
+                }
+            };
+
+            write_message(
+                &mut output,
+                &json!({ "jsonrpc": "2.0", "id": message["id"], "result": result }),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn update(&mut self, uri: &str, text: &str, output: &mut impl Write) -> io::Result<()> {
+        let diagnostics = self
+            .diagnostics(text)
+            .into_iter()
+            .map(|diagnostic| {
+                json!({
+                    "range": {
+                        "start": position(text, diagnostic.span.start),
+                        "end": position(text, diagnostic.span.end),
+                    },
+                    "severity": 1,
+                    "message": diagnostic.message,
+                })
+            })
+            .collect::<Vec<Json>>();
+        self.documents.insert(String::from(uri), String::from(text));
+
+        write_message(
+            output,
+            &json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/publishDiagnostics",
+                "params": { "uri": uri, "diagnostics": diagnostics },
+            }),
+        )
+    }
+
+    fn document_offset(&self, params: &Json) -> Option<(&str, usize)> {
+        let text = self
+            .documents
+            .get(params["textDocument"]["uri"].as_str()?)?;
+        let line = params["position"]["line"].as_u64()? as usize;
+        let character = params["position"]["character"].as_u64()? as usize;
+
+        Some((text, offset(text, line, character)))
+    }
+}
+
+fn parse_diagnostic(text: &str, error: ParseError) -> Diagnostic {
+    let start = match &error {
+        ParseError::ParsingError(inner) => error_position(inner, text.len()).min(text.len()),
+        ParseError::TooDeepError(_) => 0,
+        ParseError::HexOutOfRangeError(hex) => text.find(hex.as_str()).unwrap_or(0),
+    };
+    // Highlight up to the end of the offending word, or a single character.
+    let end = text[start..]
+        .find(char::is_whitespace)
+        .map_or(text.len(), |len| start + len)
+        .max(
+            text[start..]
+                .chars()
+                .next()
+                .map_or(start, |c| start + c.len_utf8()),
+        );
+
+    Diagnostic {
+        span: start..end,
+        message: error.to_string(),
+    }
+}
+
+// The furthest position the parser got to, which is where the input went wrong.
+fn error_position(error: &pom::Error, len: usize) -> usize {
+    match error {
+        pom::Error::Incomplete => len,
+        pom::Error::Mismatch { position, .. } | pom::Error::Conversion { position, .. } => {
+            *position
+        }
+        pom::Error::Expect {
+            position, inner, ..
+        }
+        | pom::Error::Custom {
+            position,
+            inner: Some(inner),
+            ..
+        } => (*position).max(error_position(inner, len)),
+        pom::Error::Custom { position, .. } => *position,
+    }
+}
+
+fn field_tokens(text: &str) -> Vec<(Span, String)> {
+    highlight(text)
+        .into_iter()
+        .filter(|(_, class)| *class == TokenClass::Field)
+        .map(|(span, _)| (span.clone(), String::from(&text[span])))
+        .collect()
+}
+
+// Protocol positions count UTF-16 code units within a line.
+fn position(text: &str, offset: usize) -> Json {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+    json!({
+        "line": before.matches('\n').count(),
+        "character": before[line_start..].encode_utf16().count(),
+    })
+}
+
+fn offset(text: &str, line: usize, character: usize) -> usize {
+    let line_start = match line {
+        0 => 0,
+        _ => text
+            .match_indices('\n')
+            .nth(line - 1)
+            .map_or(text.len(), |(i, _)| i + 1),
+    };
+
+    let mut units = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+
+    text.len()
+}
+
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Json>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(len) = header.strip_prefix("Content-Length:") {
+            content_length = len.trim().parse::<usize>().ok();
+        }
+    }
+
+    let content_length = content_length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header")
+    })?;
+    let mut body = vec![0; content_length];
+    input.read_exact(&mut body)?;
+
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+
+    output.flush()
+}
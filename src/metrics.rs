@@ -0,0 +1,83 @@
+use std::time::Instant;
+
+use ::metrics::{counter, histogram};
+
+use crate::{
+    engine::{ExecutionError, ValidationError},
+    expression::Expression,
+    parser::ParseError,
+};
+
+// Metrics reported through the `metrics` facade, for whichever recorder the
+// application installs:
+//
+// - `expression_parse_seconds`, a histogram of `Parser::parse` durations, and
+//   `expression_parse_errors_total`.
+// - `expression_validation_failures_total`, labeled with the `kind` of error.
+// - `expression_execute_seconds`, a histogram of `Engine::execute` and
+//   `Engine::execute_compiled` durations.
+// - `expression_executions_total`, `expression_matches_total` and
+//   `expression_execution_errors_total`, labeled with the expression's
+//   `fingerprint` in hex, so the match rate of each rule is matches over
+//   executions.
+
+pub(crate) fn record_parse(
+    parse: impl FnOnce() -> Result<Expression, ParseError>,
+) -> Result<Expression, ParseError> {
+    let start = Instant::now();
+    let result = parse();
+    histogram!("expression_parse_seconds").record(start.elapsed().as_secs_f64());
+    if result.is_err() {
+        counter!("expression_parse_errors_total").increment(1);
+    }
+
+    result
+}
+
+pub(crate) fn record_validation(result: &Result<(), ValidationError>) {
+    if let Err(e) = result {
+        counter!("expression_validation_failures_total", "kind" => kind(e)).increment(1);
+    }
+}
+
+// Fingerprints are only computed when recording metrics.
+pub(crate) fn record_execution(
+    expression: &Expression,
+    execute: impl FnOnce() -> Result<bool, ExecutionError>,
+) -> Result<bool, ExecutionError> {
+    let start = Instant::now();
+    let result = execute();
+    histogram!("expression_execute_seconds").record(start.elapsed().as_secs_f64());
+
+    let fingerprint = format!("{:016x}", expression.fingerprint());
+    counter!("expression_executions_total", "fingerprint" => fingerprint.clone()).increment(1);
+    match result {
+        Ok(true) => counter!("expression_matches_total", "fingerprint" => fingerprint).increment(1),
+        Ok(false) => {}
+        Err(_) => {
+            counter!("expression_execution_errors_total", "fingerprint" => fingerprint).increment(1)
+        }
+    }
+
+    result
+}
+
+fn kind(error: &ValidationError) -> &'static str {
+    match error {
+        ValidationError::InvalidFieldError(_, _) => "invalid_field",
+        ValidationError::InvalidOperatorError(_) => "invalid_operator",
+        ValidationError::TooDeepError(_) => "too_deep",
+        ValidationError::UnresolvedReferenceError(_) => "unresolved_reference",
+        ValidationError::TooManyNodesError(_) => "too_many_nodes",
+        ValidationError::TooManyRegexesError(_) => "too_many_regexes",
+        ValidationError::ListTooLongError(_) => "list_too_long",
+        #[cfg(feature = "regex")]
+        ValidationError::InvalidRegexError(_) => "invalid_regex",
+        #[cfg(feature = "chrono")]
+        ValidationError::NotADateTimeError(_) => "not_a_datetime",
+        ValidationError::InvalidOperandError(_, _) => "invalid_operand",
+        ValidationError::NotAnIntegerError(_) => "not_an_integer",
+        ValidationError::IntegerOutOfRangeError => "integer_out_of_range",
+        ValidationError::IncompatibleOperandsError(_, _) => "incompatible_operands",
+    }
+}
@@ -1,4 +1,9 @@
-pub fn is_sublist<T: PartialEq>(list: &Vec<T>, sublist: &Vec<T>) -> bool {
+use alloc::vec::Vec;
+
+use crate::schema::Value;
+
+#[cfg(feature = "raw")]
+pub fn is_sublist<T: PartialEq>(list: &[T], sublist: &[T]) -> bool {
     if sublist.is_empty() {
         return true;
     }
@@ -15,3 +20,62 @@ pub fn is_sublist<T: PartialEq>(list: &Vec<T>, sublist: &Vec<T>) -> bool {
 
     false
 }
+
+// The key of a value in hash maps and sets. Values hash and compare by their
+// bits, while the engine compares numbers with `==`, so -0 is folded into 0
+// and NaN, equal to nothing, has no key.
+pub fn hash_key(value: Value) -> Option<Value> {
+    match value {
+        Value::Number(val) if val.is_nan() => None,
+        Value::Number(0.0) => Some(Value::Number(0.0)),
+        value => Some(value),
+    }
+}
+
+// 64-bit FNV-1a. Unlike `DefaultHasher`, its output is fixed across Rust
+// versions and platforms, so it can be persisted.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    hash
+}
+
+// The candidate closest to a misspelled name: one within a third of its length
+// in edits, or that it's a prefix of, ignoring case. Ties go to the first in
+// alphabetical order.
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let name = name.to_lowercase().chars().collect::<Vec<char>>();
+    let max_distance = name.len() / 3;
+
+    candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let lowercase = candidate.to_lowercase().chars().collect::<Vec<char>>();
+            let distance = edit_distance(&name, &lowercase);
+
+            (distance <= max_distance || (!name.is_empty() && lowercase.starts_with(&name)))
+                .then_some((distance, candidate))
+        })
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+// Levenshtein distance, keeping one row of the table.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut row = (0..=b.len()).collect::<Vec<usize>>();
+    for (i, a_char) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a_char != b_char);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
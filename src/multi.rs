@@ -0,0 +1,157 @@
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use hashbrown::HashMap;
+
+use crate::{
+    engine::{ExecutionError, Fields, execute_operation},
+    expression::{Expression, Operation},
+};
+
+// Many expressions prepared for executing together, see `Engine::compile_multi`.
+// The expressions are canonicalized and their subexpressions shared between
+// them, so a condition appearing in several expressions, e.g. `country == "DK"`
+// or a whole group, is executed at most once per target.
+pub struct MultiMatcher {
+    expressions: Vec<Expression>,
+    nodes: Vec<Node>,
+    // The node of each expression.
+    roots: Vec<usize>,
+}
+
+// Subexpressions refer to the nodes of their children, which come before them.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Node {
+    And(Vec<usize>),
+    Or(Vec<usize>),
+    Not(usize),
+    Operation(Operation),
+    Reference(String),
+}
+
+impl MultiMatcher {
+    pub(crate) fn new(expressions: Vec<Expression>) -> Self {
+        let mut builder = Builder::default();
+        let roots = expressions
+            .iter()
+            .map(|expression| builder.add(&expression.canonicalize()))
+            .collect();
+
+        MultiMatcher {
+            expressions,
+            nodes: builder.nodes,
+            roots,
+        }
+    }
+
+    pub fn get_expressions(&self) -> &[Expression] {
+        &self.expressions
+    }
+
+    // The number of distinct subexpressions, at most the total over all the
+    // expressions.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    // The indices of the expressions true for the target, in order.
+    pub(crate) fn execute<T>(
+        &self,
+        target: &T,
+        fields: &Fields<T>,
+    ) -> Result<Vec<usize>, ExecutionError> {
+        let mut results = vec![None; self.nodes.len()];
+        let mut matches = Vec::new();
+        for (i, &root) in self.roots.iter().enumerate() {
+            if self.execute_node(root, target, fields, &mut results)? {
+                matches.push(i);
+            }
+        }
+
+        Ok(matches)
+    }
+
+    fn execute_node<T>(
+        &self,
+        index: usize,
+        target: &T,
+        fields: &Fields<T>,
+        results: &mut [Option<bool>],
+    ) -> Result<bool, ExecutionError> {
+        if let Some(result) = results[index] {
+            return Ok(result);
+        }
+
+        let result = match &self.nodes[index] {
+            Node::And(children) => {
+                let mut result = true;
+                for &child in children {
+                    if !self.execute_node(child, target, fields, results)? {
+                        result = false;
+                        break;
+                    }
+                }
+
+                result
+            }
+            Node::Or(children) => {
+                let mut result = false;
+                for &child in children {
+                    if self.execute_node(child, target, fields, results)? {
+                        result = true;
+                        break;
+                    }
+                }
+
+                result
+            }
+            Node::Not(child) => !self.execute_node(*child, target, fields, results)?,
+            Node::Operation(operation) => execute_operation(operation, target, fields)?,
+            Node::Reference(rule_name) => {
+                return Err(ExecutionError::UnresolvedReferenceError(rule_name.clone()));
+            }
+        };
+        results[index] = Some(result);
+
+        Ok(result)
+    }
+}
+
+// Interns nodes bottom up, so equal subexpressions get the same node.
+#[derive(Default)]
+struct Builder {
+    nodes: Vec<Node>,
+    indices: HashMap<Node, usize>,
+}
+
+impl Builder {
+    fn add(&mut self, expression: &Expression) -> usize {
+        let node = match expression {
+            Expression::And(and) => Node::And(self.add_all(and.get_subexpressions())),
+            Expression::Or(or) => Node::Or(self.add_all(or.get_subexpressions())),
+            Expression::Not(not) => Node::Not(self.add(not.get_subexpression())),
+            Expression::Operation(operation) => Node::Operation(operation.clone()),
+            Expression::Reference(reference) => {
+                Node::Reference(reference.get_rule_name().to_string())
+            }
+        };
+
+        if let Some(&index) = self.indices.get(&node) {
+            return index;
+        }
+        self.indices.insert(node.clone(), self.nodes.len());
+        self.nodes.push(node);
+
+        self.nodes.len() - 1
+    }
+
+    fn add_all(&mut self, expressions: &[Expression]) -> Vec<usize> {
+        expressions
+            .iter()
+            .map(|expression| self.add(expression))
+            .collect()
+    }
+}
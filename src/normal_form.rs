@@ -0,0 +1,105 @@
+use alloc::{vec, vec::Vec};
+
+use thiserror::Error;
+
+use crate::expression::{And, Expression, Or};
+
+#[derive(Error, Debug)]
+pub enum NormalFormError {
+    #[error("The normal form exceeds the limit of {0} clauses")]
+    TooManyClausesError(usize),
+}
+
+// Both forms are computed from the simplified expression, so negations only
+// wrap operations and count as literals. Distributing one connective over the
+// other can grow the expression exponentially, hence the clause limit.
+pub fn to_cnf(expression: &Expression, max_clauses: usize) -> Result<Expression, NormalFormError> {
+    let clauses = clauses(&expression.simplify(), true, max_clauses)?;
+
+    Ok(build(clauses, true))
+}
+
+pub fn to_dnf(expression: &Expression, max_clauses: usize) -> Result<Expression, NormalFormError> {
+    let clauses = clauses(&expression.simplify(), false, max_clauses)?;
+
+    Ok(build(clauses, false))
+}
+
+// For CNF the outer connective is And and each clause is a disjunction of
+// literals; for DNF it is the other way around.
+fn clauses(
+    expression: &Expression,
+    cnf: bool,
+    max_clauses: usize,
+) -> Result<Vec<Vec<Expression>>, NormalFormError> {
+    match expression {
+        Expression::And(and) if cnf => concat(and.get_subexpressions(), cnf, max_clauses),
+        Expression::Or(or) if !cnf => concat(or.get_subexpressions(), cnf, max_clauses),
+        Expression::And(and) => distribute(and.get_subexpressions(), cnf, max_clauses),
+        Expression::Or(or) => distribute(or.get_subexpressions(), cnf, max_clauses),
+        literal => Ok(vec![vec![literal.clone()]]),
+    }
+}
+
+// The outer connective joins the clauses of its children.
+fn concat(
+    subexpressions: &[Expression],
+    cnf: bool,
+    max_clauses: usize,
+) -> Result<Vec<Vec<Expression>>, NormalFormError> {
+    let mut result = Vec::new();
+    for subexpression in subexpressions {
+        result.extend(clauses(subexpression, cnf, max_clauses)?);
+        if result.len() > max_clauses {
+            return Err(NormalFormError::TooManyClausesError(max_clauses));
+        }
+    }
+
+    Ok(result)
+}
+
+// The inner connective is distributed by combining one clause from each child.
+fn distribute(
+    subexpressions: &[Expression],
+    cnf: bool,
+    max_clauses: usize,
+) -> Result<Vec<Vec<Expression>>, NormalFormError> {
+    let mut result = vec![Vec::new()];
+    for subexpression in subexpressions {
+        let child = clauses(subexpression, cnf, max_clauses)?;
+        if result.len().saturating_mul(child.len()) > max_clauses {
+            return Err(NormalFormError::TooManyClausesError(max_clauses));
+        }
+
+        result = result
+            .iter()
+            .flat_map(|clause| {
+                child.iter().map(move |other| {
+                    let mut combined = clause.clone();
+                    combined.extend(other.iter().cloned());
+
+                    combined
+                })
+            })
+            .collect();
+    }
+
+    Ok(result)
+}
+
+fn build(clauses: Vec<Vec<Expression>>, cnf: bool) -> Expression {
+    let clauses = clauses
+        .into_iter()
+        .map(|literals| match cnf {
+            true => Expression::Or(Or::new(literals)),
+            false => Expression::And(And::new(literals)),
+        })
+        .collect();
+
+    // Simplifying removes duplicate literals and unwraps single-element groups.
+    match cnf {
+        true => Expression::And(And::new(clauses)),
+        false => Expression::Or(Or::new(clauses)),
+    }
+    .simplify()
+}
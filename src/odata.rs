@@ -0,0 +1,232 @@
+use core::str;
+
+use pom::parser::*;
+use thiserror::Error;
+
+#[cfg(feature = "chrono")]
+use crate::parser::datetime;
+use crate::{
+    expression::{And, Expression, Literal, Not, Operation, Operator, Or},
+    parser::{ExpressionParser, identifier, keyword, number, space},
+    schema::Value,
+};
+
+#[derive(Error, Debug)]
+pub enum ODataError {
+    #[error("{0}")]
+    ParsingError(#[from] pom::Error),
+    #[error("Unsupported OData construct: {0}")]
+    UnsupportedError(String),
+}
+
+impl ExpressionParser {
+    pub fn from_odata(filter: &str) -> Result<Expression, ODataError> {
+        let node = odata_parser().parse(filter.as_bytes())?;
+
+        into_expression(node)
+    }
+}
+
+// Intermediate parse tree; function calls and bare boolean properties only
+// become operations once it is known they are used as conditions.
+enum Node {
+    And(Vec<Node>),
+    Or(Vec<Node>),
+    Not(Box<Node>),
+    Compare(Literal, Operator, Literal),
+    Call(String, Vec<Literal>),
+    Operand(Literal),
+}
+
+fn into_expression(node: Node) -> Result<Expression, ODataError> {
+    Ok(match node {
+        Node::And(nodes) => Expression::And(And::new(into_expressions(nodes)?)),
+        Node::Or(nodes) => Expression::Or(Or::new(into_expressions(nodes)?)),
+        Node::Not(node) => Expression::Not(Not::new(into_expression(*node)?)),
+        Node::Compare(lhs, op, rhs) => Expression::Operation(Operation::new(lhs, op, rhs)),
+        Node::Call(function, mut args) => {
+            let (haystack, needle) = match args.len() {
+                2 => {
+                    let needle = args.pop().unwrap();
+
+                    (args.pop().unwrap(), needle)
+                }
+                _ => return Err(ODataError::UnsupportedError(format!("{}()", function))),
+            };
+
+            let needle = match (function.as_str(), needle) {
+                ("contains", needle) => needle,
+                #[cfg(feature = "regex")]
+                ("startswith", Literal::LiteralValue(Value::String(prefix))) => {
+                    Literal::LiteralValue(Value::Regex(format!("^{}", regex::escape(&prefix))))
+                }
+                #[cfg(feature = "regex")]
+                ("endswith", Literal::LiteralValue(Value::String(suffix))) => {
+                    Literal::LiteralValue(Value::Regex(format!("{}$", regex::escape(&suffix))))
+                }
+                #[cfg(feature = "regex")]
+                ("matchesPattern", Literal::LiteralValue(Value::String(pattern))) => {
+                    Literal::LiteralValue(Value::Regex(pattern))
+                }
+                (function, _) => {
+                    return Err(ODataError::UnsupportedError(format!("{}()", function)));
+                }
+            };
+
+            Expression::Operation(Operation::new(needle, Operator::In, haystack))
+        }
+        Node::Operand(field @ Literal::LiteralField(_)) => Expression::Operation(Operation::new(
+            field,
+            Operator::Eq,
+            Literal::LiteralValue(Value::Boolean(true)),
+        )),
+        Node::Operand(Literal::LiteralValue(value)) => {
+            return Err(ODataError::UnsupportedError(format!(
+                "{} used as a condition",
+                value.get_type_name()
+            )));
+        }
+        #[cfg(feature = "chrono")]
+        Node::Operand(Literal::LiteralAge(_)) => {
+            return Err(ODataError::UnsupportedError(String::from(
+                "age_of() used as a condition",
+            )));
+        }
+        Node::Operand(Literal::LiteralComputed(_)) => {
+            return Err(ODataError::UnsupportedError(String::from(
+                "computed value used as a condition",
+            )));
+        }
+    })
+}
+
+fn into_expressions(nodes: Vec<Node>) -> Result<Vec<Expression>, ODataError> {
+    nodes.into_iter().map(into_expression).collect()
+}
+
+// Single quotes inside OData strings are escaped by doubling them.
+fn string<'a>() -> Parser<'a, u8, String> {
+    let string = sym(b'\'') * (none_of(b"'") | seq(b"''").map(|_| b'\'')).repeat(0..) - sym(b'\'');
+
+    string.convert(String::from_utf8)
+}
+
+fn boolean<'a>() -> Parser<'a, u8, bool> {
+    keyword(b"true").map(|_| true) | keyword(b"false").map(|_| false)
+}
+
+fn list<'a, T: 'a>(item: fn() -> Parser<'a, u8, T>) -> Parser<'a, u8, Vec<T>> {
+    let items = (item() + ((space() + sym(b',') + space()) * item()).repeat(0..)).map(
+        |(first, mut rest)| {
+            rest.insert(0, first);
+
+            rest
+        },
+    );
+
+    (sym(b'(') + space()) * items - (space() + sym(b')'))
+}
+
+fn value<'a>() -> Parser<'a, u8, Value> {
+    let mut parser = keyword(b"null").map(|_| Value::Null) | boolean().map(Value::Boolean);
+    #[cfg(feature = "chrono")]
+    {
+        parser = parser | datetime().map(Value::DateTime);
+    }
+    parser = parser
+        | string().map(Value::String)
+        | number().map(Value::Number)
+        | list(string).map(Value::StringList);
+    #[cfg(feature = "chrono")]
+    {
+        parser = parser | list(datetime).map(Value::DateTimeList);
+    }
+
+    parser | list(number).map(Value::NumberList) | list(boolean).map(Value::BooleanList)
+}
+
+// Navigation paths like `Address/City` map onto `:`-separated field names.
+fn path<'a>() -> Parser<'a, u8, String> {
+    (identifier() + (sym(b'/') * identifier()).repeat(0..)).map(|(first, mut rest)| {
+        rest.insert(0, first);
+
+        rest.join(":")
+    })
+}
+
+fn argument<'a>() -> Parser<'a, u8, Literal> {
+    value().map(Literal::LiteralValue) | path().map(|path| Literal::LiteralField(path.into()))
+}
+
+fn operand<'a>() -> Parser<'a, u8, Node> {
+    let arguments = (sym(b'(') + space()) * list_arguments() - (space() + sym(b')'));
+
+    (identifier() - space() + arguments).map(|(function, args)| Node::Call(function, args))
+        | argument().map(Node::Operand)
+}
+
+fn list_arguments<'a>() -> Parser<'a, u8, Vec<Literal>> {
+    (argument() + ((space() + sym(b',') + space()) * argument()).repeat(0..)).map(
+        |(first, mut rest)| {
+            rest.insert(0, first);
+
+            rest
+        },
+    )
+}
+
+fn operator<'a>() -> Parser<'a, u8, Operator> {
+    keyword(b"eq").map(|_| Operator::Eq)
+        | keyword(b"ne").map(|_| Operator::Ne)
+        | keyword(b"gt").map(|_| Operator::Gt)
+        | keyword(b"ge").map(|_| Operator::Gte)
+        | keyword(b"lt").map(|_| Operator::Lt)
+        | keyword(b"le").map(|_| Operator::Lte)
+        | keyword(b"in").map(|_| Operator::In)
+}
+
+fn comparison<'a>() -> Parser<'a, u8, Node> {
+    (operand() + (space() * operator() + space() * operand()).opt()).convert(|(lhs, rest)| {
+        match (lhs, rest) {
+            (lhs, None) => Ok(lhs),
+            (Node::Operand(lhs), Some((op, Node::Operand(rhs)))) => Ok(Node::Compare(lhs, op, rhs)),
+            _ => Err("function results cannot be compared"),
+        }
+    })
+}
+
+fn unary<'a>() -> Parser<'a, u8, Node> {
+    (keyword(b"not") * space() * call(unary)).map(|node| Node::Not(Box::new(node)))
+        | (sym(b'(') * space() * call(or) - space() - sym(b')'))
+        | comparison()
+}
+
+fn and<'a>() -> Parser<'a, u8, Node> {
+    (unary() + (space() * keyword(b"and") * space() * unary()).repeat(0..)).map(
+        |(first, mut rest)| match rest.len() {
+            0 => first,
+            _ => {
+                rest.insert(0, first);
+
+                Node::And(rest)
+            }
+        },
+    )
+}
+
+fn or<'a>() -> Parser<'a, u8, Node> {
+    (and() + (space() * keyword(b"or") * space() * and()).repeat(0..)).map(|(first, mut rest)| {
+        match rest.len() {
+            0 => first,
+            _ => {
+                rest.insert(0, first);
+
+                Node::Or(rest)
+            }
+        }
+    })
+}
+
+fn odata_parser<'a>() -> Parser<'a, u8, Node> {
+    space() * or() - space() - end()
+}
@@ -0,0 +1,87 @@
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+
+use crate::{
+    descriptor::SchemaDescriptor,
+    engine::{self, Engine, ExecutionError, FieldLookup, ValidationError},
+    expression::Expression,
+    schema::{Field, Schema},
+};
+
+// A schema of extra fields layered on top of an engine's schema, e.g. the
+// custom fields of one tenant on a schema shared by all of them. Overlays only
+// borrow both schemas, so the engine is built once and every tenant keeps just
+// its own fields. Fields of the base schema take precedence, an overlay can
+// add fields but not change the shared ones.
+pub struct Overlay<'a, T> {
+    engine: &'a Engine<T>,
+    schema: &'a Schema<T>,
+}
+
+impl<'a, T> Overlay<'a, T> {
+    pub fn new(engine: &'a Engine<T>, schema: &'a Schema<T>) -> Self {
+        Self { engine, schema }
+    }
+
+    pub fn get_engine(&self) -> &'a Engine<T> {
+        self.engine
+    }
+
+    pub fn get_schema(&self) -> &'a Schema<T> {
+        self.schema
+    }
+
+    pub fn get_field(&self, field_name: &str) -> Option<&'a Field<T>> {
+        self.engine
+            .get_schema()
+            .get_field(field_name)
+            .or_else(|| self.schema.get_field(field_name))
+    }
+
+    pub fn get_description(&self, field_name: &str) -> Option<&'a str> {
+        match self.engine.get_schema().get_field(field_name) {
+            Some(_) => self.engine.get_schema().get_description(field_name),
+            None => self.schema.get_description(field_name),
+        }
+    }
+
+    pub fn descriptor(&self) -> SchemaDescriptor {
+        let mut fields = self.engine.get_schema().descriptor().fields;
+        fields.extend(
+            self.schema
+                .descriptor()
+                .fields
+                .into_iter()
+                .filter(|field| self.engine.get_schema().get_field(&field.name).is_none()),
+        );
+        fields.sort_by(|a, b| a.name.cmp(&b.name));
+
+        SchemaDescriptor { fields }
+    }
+
+    pub fn validate(&self, expression: &Expression) -> Result<(), ValidationError> {
+        engine::validate(expression, &|field_name| {
+            self.get_field(field_name).map(|field| field.field_type)
+        })
+        .map_err(|e| {
+            let field_names = self.engine.get_schema().get_field_names();
+            engine::suggest_field(e, field_names.chain(self.schema.get_field_names()))
+        })
+    }
+
+    pub fn execute(&self, expression: &Expression, target: &T) -> Result<bool, ExecutionError> {
+        engine::execute(expression, target, self)
+    }
+}
+
+// Executes with the engine's clock.
+impl<T> FieldLookup<T> for Overlay<'_, T> {
+    fn get_field(&self, field_name: &str) -> Option<&Field<T>> {
+        Overlay::get_field(self, field_name)
+    }
+
+    #[cfg(feature = "chrono")]
+    fn now(&self) -> Option<DateTime<Utc>> {
+        self.engine.now()
+    }
+}
@@ -1,12 +1,24 @@
+#[cfg(feature = "chrono")]
 use chrono::{DateTime, Utc};
+#[cfg(feature = "timezone")]
+use chrono::{NaiveDateTime, TimeZone};
+#[cfg(feature = "timezone")]
+use chrono_tz::Tz;
 use pom::{Error, parser::*};
 use thiserror::Error;
 
+use alloc::rc::Rc;
 use core::str;
 use std::str::FromStr;
 
+#[cfg(feature = "metrics")]
+use crate::metrics;
 use crate::{
-    expression::{And, Expression, Literal, Not, Operation, Operator, Or},
+    expression::{
+        And, BitwiseOperator, Computed, Expression, Literal, MAX_DEPTH, MAX_INTEGER, Not,
+        Operation, Operator, Or, Reference,
+    },
+    highlight::{TokenClass, highlight},
     schema::Value,
 };
 
@@ -37,36 +49,120 @@ pub fn seq_nocase<'a, 'b: 'a>(tag: &'b [u8]) -> Parser<'a, u8, &'a [u8]> {
 macro_rules! list_parser {
     ($fn_name:ident, $type_:ty, $value_fn:expr) => {
         fn $fn_name<'a>() -> Parser<'a, u8, Vec<$type_>> {
-            ((sym(b'[') + space()) * ($value_fn() - space())
-                + ((sym(b',') + space()) * $value_fn() - space()).repeat(0..)
-                - sym(b']'))
-            .map(|(first, mut values)| {
-                values.insert(0, first);
-
-                values
-            })
+            list($value_fn)
         }
     };
 }
 
-fn space<'a>() -> Parser<'a, u8, ()> {
+fn list<'a, O: 'a>(value: impl Fn() -> Parser<'a, u8, O>) -> Parser<'a, u8, Vec<O>> {
+    ((sym(b'[') + space()) * (value() - space())
+        + ((sym(b',') + space()) * value() - space()).repeat(0..)
+        - sym(b']'))
+    .map(|(first, mut values)| {
+        values.insert(0, first);
+
+        values
+    })
+}
+
+// Settings that apply to part of an expression.
+#[derive(Clone, Copy, Default)]
+struct Context {
+    // Datetimes without an offset are in this timezone, see `at_tz`. Without
+    // one they don't parse.
+    #[cfg(feature = "timezone")]
+    timezone: Option<Tz>,
+}
+
+pub(crate) fn space<'a>() -> Parser<'a, u8, ()> {
     one_of(b" \t\r\n").repeat(0..).discard().name("space")
 }
 
-fn number<'a>() -> Parser<'a, u8, f64> {
-    let integer = one_of(b"123456789") - one_of(b"0123456789").repeat(0..) | sym(b'0');
+pub(crate) fn identifier<'a>() -> Parser<'a, u8, String> {
+    (one_of(b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_")
+        + one_of(b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_0123456789").repeat(0..))
+    .collect()
+    .convert(str::from_utf8)
+    .map(String::from)
+}
+
+pub(crate) fn keyword<'a>(word: &'static [u8]) -> Parser<'a, u8, ()> {
+    seq(word).discard()
+        - !one_of(b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_0123456789")
+}
+
+pub(crate) fn number<'a>() -> Parser<'a, u8, f64> {
+    let integer = (one_of(b"123456789") - one_of(b"0123456789").repeat(0..)) | sym(b'0');
     let frac = sym(b'.') + one_of(b"0123456789").repeat(1..);
     let exp = one_of(b"eE") + one_of(b"+-").opt() + one_of(b"0123456789").repeat(1..);
     let number = sym(b'-').opt() + integer + frac.opt() + exp.opt();
     number
         .collect()
         .convert(str::from_utf8)
-        .convert(|s| f64::from_str(&s))
+        .convert(f64::from_str)
+        .convert(|num| {
+            if num.is_finite() {
+                Ok(num)
+            } else {
+                Err("number out of range")
+            }
+        })
         .name("number")
 }
 
 list_parser!(number_list, f64, number);
 
+// An integer written in hexadecimal, e.g. `0x04` for a flag. Larger values
+// than `MAX_INTEGER` would be rounded, they fail here and `large_hex` reports
+// them.
+pub(crate) fn hex<'a>() -> Parser<'a, u8, f64> {
+    let parser = seq(b"0x")
+        * one_of(b"0123456789abcdefABCDEF")
+            .repeat(1..=16)
+            .collect()
+            .convert(str::from_utf8)
+            .convert(|digits| u64::from_str_radix(digits, 16))
+            .convert(|num| {
+                if num <= MAX_INTEGER as u64 {
+                    Ok(num as f64)
+                } else {
+                    Err("hex integer above 2^53")
+                }
+            });
+
+    parser.name("hex")
+}
+
+// A number of seconds written with a unit, e.g. `30d` or `500ms`.
+pub(crate) fn duration<'a>() -> Parser<'a, u8, f64> {
+    let unit = seq(b"ms").map(|_| 0.001)
+        | sym(b's').map(|_| 1.0)
+        | sym(b'm').map(|_| 60.0)
+        | sym(b'h').map(|_| 3600.0)
+        | sym(b'd').map(|_| 86400.0)
+        | sym(b'w').map(|_| 604800.0);
+
+    (number() + unit - !one_of(b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_:0123456789"))
+        .convert(|(num, unit)| {
+            let seconds = num * unit;
+            if seconds.is_finite() {
+                Ok(seconds)
+            } else {
+                Err("duration out of range")
+            }
+        })
+        .name("duration")
+}
+
+fn boolean<'a>() -> Parser<'a, u8, bool> {
+    let parser = seq_nocase(b"true").map(|_| true) | seq_nocase(b"false").map(|_| false);
+
+    parser.name("boolean")
+}
+
+list_parser!(boolean_list, bool, boolean);
+
+#[cfg(feature = "raw")]
 fn raw<'a>() -> Parser<'a, u8, Vec<u8>> {
     let parser = (sym(b'|') - space())
         * (one_of(b"0123456789abcdefABCDEF") + one_of(b"0123456789abcdefABCDEF") - space())
@@ -77,6 +173,7 @@ fn raw<'a>() -> Parser<'a, u8, Vec<u8>> {
     parser.name("raw")
 }
 
+#[cfg(feature = "raw")]
 list_parser!(raw_list, Vec<u8>, raw);
 
 fn string<'a>() -> Parser<'a, u8, String> {
@@ -89,18 +186,38 @@ fn string<'a>() -> Parser<'a, u8, String> {
         | sym(b'r').map(|_| b'\r')
         | sym(b't').map(|_| b'\t');
     let escape_sequence = sym(b'\\') * special_char;
-    let string = sym(b'"') * (none_of(b"\\\"") | escape_sequence).repeat(0..) - sym(b'"');
-    string.convert(String::from_utf8).name("string")
+    let escaped = (none_of(b"\\\"") | escape_sequence).repeat(0..) - sym(b'"');
+
+    // Most strings have no escapes. Those are sliced from the input and copied
+    // once, instead of being collected byte by byte.
+    let plain = none_of(b"\\\"").discard().repeat(0..).collect() - sym(b'"');
+
+    let string = sym(b'"') * (plain.convert(plain_string) | escaped.convert(String::from_utf8));
+    string.name("string")
+}
+
+fn plain_string(bytes: &[u8]) -> Result<String, str::Utf8Error> {
+    str::from_utf8(bytes).map(String::from)
 }
 
 list_parser!(string_list, String, string);
 
+#[cfg(feature = "regex")]
 fn regex_string<'a>() -> Parser<'a, u8, String> {
-    let string = sym(b'/') * (seq(b"\\/").map(|_| b'/') | none_of(b"/")).repeat(0..) - sym(b'/');
-    string.convert(String::from_utf8).name("regex_string")
+    let escaped = (seq(b"\\/").map(|_| b'/') | none_of(b"/")).repeat(0..) - sym(b'/');
+
+    // Without escaped slashes the pattern is sliced from the input, see
+    // `string`.
+    let unescaped = none_of(b"\\/").discard() | (sym(b'\\') - !sym(b'/')).discard();
+    let plain = unescaped.repeat(0..).collect() - sym(b'/');
+
+    let string = sym(b'/') * (plain.convert(plain_string) | escaped.convert(String::from_utf8));
+    string.name("regex_string")
 }
 
-fn datetime<'a>() -> Parser<'a, u8, DateTime<Utc>> {
+// The date and time of a datetime, without the offset.
+#[cfg(feature = "chrono")]
+fn local_datetime<'a>() -> Parser<'a, u8, ()> {
     let num = || one_of(b"1234567890");
 
     let parser = num().repeat(4)
@@ -114,7 +231,16 @@ fn datetime<'a>() -> Parser<'a, u8, DateTime<Utc>> {
         + num().repeat(2)
         + sym(b':')
         + num().repeat(2)
-        + (sym(b'.') + num().repeat(1..6)).opt()
+        + (sym(b'.') + num().repeat(1..=9)).opt();
+
+    parser.discard()
+}
+
+#[cfg(feature = "chrono")]
+pub(crate) fn datetime<'a>() -> Parser<'a, u8, DateTime<Utc>> {
+    let num = || one_of(b"1234567890");
+
+    let parser = local_datetime()
         + (sym(b'Z').collect()
             | (one_of(b"+-") + num().repeat(2) + sym(b':') + num().repeat(2)).collect());
 
@@ -124,18 +250,54 @@ fn datetime<'a>() -> Parser<'a, u8, DateTime<Utc>> {
         .convert(|s| DateTime::parse_from_rfc3339(s).map(|date| date.to_utc()))
 }
 
-list_parser!(datetime_list, DateTime<Utc>, datetime);
+// Without an offset, a datetime is in the timezone of the context. Times
+// skipped by a daylight saving change don't parse, and repeated ones are the
+// earliest.
+#[cfg(feature = "chrono")]
+#[cfg_attr(not(feature = "timezone"), allow(unused_variables))]
+fn datetime_in<'a>(context: Context) -> Parser<'a, u8, DateTime<Utc>> {
+    #[cfg(feature = "timezone")]
+    if let Some(timezone) = context.timezone {
+        let local = local_datetime()
+            .collect()
+            .convert(str::from_utf8)
+            .convert(|s| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f"))
+            .convert(move |naive| {
+                timezone
+                    .from_local_datetime(&naive)
+                    .earliest()
+                    .map(|date| date.to_utc())
+                    .ok_or("datetime skipped in the timezone")
+            });
+
+        return datetime() | local;
+    }
+
+    datetime()
+}
+
+#[cfg(feature = "chrono")]
+fn datetime_list<'a>(context: Context) -> Parser<'a, u8, Vec<DateTime<Utc>>> {
+    list(move || datetime_in(context))
+}
 
 fn field<'a>() -> Parser<'a, u8, String> {
     let parser = (one_of(b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_")
         + one_of(b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_:0123456789").repeat(0..))
     .collect()
     .convert(str::from_utf8)
-    .map(|s| String::from(s));
+    .map(String::from);
 
     parser.name("field")
 }
 
+#[cfg(feature = "chrono")]
+fn age<'a>() -> Parser<'a, u8, String> {
+    let parser = (seq(b"age_of(") + space()) * field() - (space() + sym(b')'));
+
+    parser.name("age")
+}
+
 fn operator<'a>() -> Parser<'a, u8, Operator> {
     let parser = seq(b"==").map(|_| Operator::Eq)
         | seq(b"!=").map(|_| Operator::Ne)
@@ -148,92 +310,359 @@ fn operator<'a>() -> Parser<'a, u8, Operator> {
     parser.name("operator")
 }
 
-fn literal<'a>() -> Parser<'a, u8, Literal> {
-    let parser = seq_nocase(b"null").map(|_| Literal::LiteralValue(Value::Null))
-        | seq_nocase(b"true").map(|_| Literal::LiteralValue(Value::Boolean(true)))
-        | seq_nocase(b"false").map(|_| Literal::LiteralValue(Value::Boolean(false)))
-        | string().map(|str| Literal::LiteralValue(Value::String(str)))
-        | regex_string().map(|pattern| Literal::LiteralValue(Value::Regex(pattern)))
-        | raw().map(|bytes| Literal::LiteralValue(Value::Raw(bytes)))
-        | datetime().map(|datetime| Literal::LiteralValue(Value::DateTime(datetime)))
+#[cfg_attr(not(feature = "chrono"), allow(unused_variables))]
+fn literal<'a>(context: Context) -> Parser<'a, u8, Literal> {
+    let mut parser = seq_nocase(b"null").map(|_| Literal::LiteralValue(Value::Null))
+        | boolean().map(|val| Literal::LiteralValue(Value::Boolean(val)))
+        | string().map(|str| Literal::LiteralValue(Value::String(str)));
+    // Alternatives for disabled types are left out, so their syntax doesn't parse.
+    #[cfg(feature = "regex")]
+    {
+        parser =
+            parser | regex_string().map(|pattern| Literal::LiteralValue(Value::Regex(pattern)));
+    }
+    #[cfg(feature = "raw")]
+    {
+        parser = parser | raw().map(|bytes| Literal::LiteralValue(Value::Raw(bytes)));
+    }
+    #[cfg(feature = "chrono")]
+    {
+        parser = parser
+            | datetime_in(context).map(|datetime| Literal::LiteralValue(Value::DateTime(datetime)));
+    }
+    parser = parser
+        | hex().map(|num| Literal::LiteralValue(Value::Number(num)))
+        | duration().map(|num| Literal::LiteralValue(Value::Number(num)))
         | number().map(|num| Literal::LiteralValue(Value::Number(num)))
-        | string_list().map(|str| Literal::LiteralValue(Value::StringList(str)))
-        | raw_list().map(|bytes| Literal::LiteralValue(Value::RawList(bytes)))
-        | datetime_list().map(|datetime| Literal::LiteralValue(Value::DateTimeList(datetime)))
+        | string_list().map(|str| Literal::LiteralValue(Value::StringList(str)));
+    #[cfg(feature = "raw")]
+    {
+        parser = parser | raw_list().map(|bytes| Literal::LiteralValue(Value::RawList(bytes)));
+    }
+    #[cfg(feature = "chrono")]
+    {
+        parser = parser
+            | datetime_list(context)
+                .map(|datetime| Literal::LiteralValue(Value::DateTimeList(datetime)));
+    }
+    parser = parser
         | number_list().map(|num| Literal::LiteralValue(Value::NumberList(num)))
-        | field().map(|field| Literal::LiteralField(field));
+        | boolean_list().map(|val| Literal::LiteralValue(Value::BooleanList(val)));
+    #[cfg(feature = "chrono")]
+    {
+        parser = parser | age().map(|field_name| Literal::LiteralAge(field_name.into()));
+    }
+    parser = parser | field().map(|field_name| Literal::LiteralField(field_name.into()));
 
     parser.name("literal")
 }
 
-fn operation<'a>() -> Parser<'a, u8, Operation> {
-    let parser = ((literal() - space()) + (operator() - space()) + literal())
-        .map(|((lhs, op), rhs)| Operation::new(lhs, op, rhs));
+// Builds a parser once for use in several places, as parsers can't be cloned.
+fn shared<'a, O: 'a>(parser: Parser<'a, u8, O>) -> impl Fn() -> Parser<'a, u8, O> {
+    let parser = Rc::new(parser);
 
-    parser.name("operation")
+    move || {
+        let parser = Rc::clone(&parser);
+        Parser::new(move |input: &'a [u8], start: usize| (parser.method)(input, start))
+    }
 }
 
-fn and<'a>() -> Parser<'a, u8, And> {
-    let parser = ((sym(b'(') - space())
-        * ((call(expression) - space() - seq_nocase(b"and") - space())
-            + (call(expression) - space() - (seq_nocase(b"and") - space()).opt()).repeat(1..))
-        - (space() + sym(b')')))
-    .map(|(first, mut operations)| {
-        operations.insert(0, first);
+// `if(condition, then, else)`, tried before literals as `if` is also a valid
+// field name.
+fn conditional<'a>(context: Context) -> Parser<'a, u8, Literal> {
+    let parser = (seq(b"if(") + space()) * nested(context) - (space() + sym(b',') + space())
+        + nested_operand(context)
+        - (space() + sym(b',') + space())
+        + nested_operand(context)
+        - (space() + sym(b')'));
+
+    parser
+        .map(|((condition, then), otherwise)| {
+            Literal::LiteralComputed(Box::new(Computed::If(condition, then, otherwise)))
+        })
+        .name("conditional")
+}
 
-        And::new(operations)
-    });
+// `first(list)` and `last(list)`, also valid field names like `if`.
+fn accessor<'a>(context: Context) -> Parser<'a, u8, Literal> {
+    let function = seq(b"first(").map(|_| Computed::First as fn(Literal) -> Computed)
+        | seq(b"last(").map(|_| Computed::Last as fn(Literal) -> Computed);
 
-    parser.name("and")
+    let parser = (function - space()) + nested_operand(context) - (space() + sym(b')'));
+
+    parser
+        .map(|(function, list)| Literal::LiteralComputed(Box::new(function(list))))
+        .name("accessor")
 }
 
-fn or<'a>() -> Parser<'a, u8, Or> {
-    let parser = ((sym(b'(') - space())
-        * ((call(expression) - space() - seq_nocase(b"or") - space())
-            + (call(expression) - space() - (seq_nocase(b"or") - space()).opt()).repeat(1..))
-        - (space() + sym(b')')))
-    .map(|(first, mut operations)| {
-        operations.insert(0, first);
+fn index<'a>() -> Parser<'a, u8, usize> {
+    one_of(b"0123456789")
+        .repeat(1..)
+        .collect()
+        .convert(str::from_utf8)
+        .convert(usize::from_str)
+}
 
-        Or::new(operations)
-    });
+// `[start..end]` after an operand.
+fn slice<'a>() -> Parser<'a, u8, (usize, usize)> {
+    let parser = (sym(b'[') + space()) * index() - (space() + seq(b"..") + space()) + index()
+        - (space() + sym(b']'));
 
-    parser.name("or")
+    parser.name("slice")
 }
 
-fn not<'a>() -> Parser<'a, u8, Not> {
-    let parser = ((sym(b'!') + space() + sym(b'(') + space()) * call(expression)
+// A conditional, an accessor, a literal, or an operand in parentheses, each
+// optionally sliced. Slicing a slice needs parentheses, which bound the depth.
+fn atom<'a>(context: Context) -> Parser<'a, u8, Literal> {
+    let parenthesized = (sym(b'(') - space()) * nested_operand(context) - (space() + sym(b')'));
+
+    let parser = (conditional(context) | accessor(context) | literal(context) | parenthesized)
+        + (space() * slice()).opt();
+
+    parser.map(|(operand, slice)| match slice {
+        Some((start, end)) => {
+            Literal::LiteralComputed(Box::new(Computed::Slice(operand, start, end)))
+        }
+        None => operand,
+    })
+}
+
+pub(crate) const BITWISE_OPERATORS: [BitwiseOperator; 5] = [
+    BitwiseOperator::Or,
+    BitwiseOperator::Xor,
+    BitwiseOperator::And,
+    BitwiseOperator::ShiftLeft,
+    BitwiseOperator::ShiftRight,
+];
+
+// Operands joined by the bitwise operators of `precedence` or above. Operators
+// of the same precedence are kept in one flat chain.
+fn bitwise<'a>(context: Context, precedence: u8) -> Parser<'a, u8, Literal> {
+    let Some(operator) = BITWISE_OPERATORS
+        .into_iter()
+        .filter(|op| op.precedence() == precedence)
+        .map(|op| seq(op.fmt_static().as_bytes()).map(move |_| op))
+        .reduce(|a, b| a | b)
+    else {
+        return concat(context);
+    };
+    let operand = shared(bitwise(context, precedence + 1));
+
+    let parser = operand() + (space() * operator + (space() * operand())).repeat(0..);
+    parser.map(|(first, rest)| match rest.is_empty() {
+        true => first,
+        false => Literal::LiteralComputed(Box::new(Computed::Bitwise(first, rest))),
+    })
+}
+
+// Operands joined with `+`, binding tighter than the bitwise operators.
+fn concat<'a>(context: Context) -> Parser<'a, u8, Literal> {
+    let operand = shared(atom(context));
+
+    let parser = operand() + (space() * sym(b'+') * space() * operand()).repeat(0..);
+    parser.map(|(first, mut rest)| match rest.is_empty() {
+        true => first,
+        false => {
+            rest.insert(0, first);
+
+            Literal::LiteralComputed(Box::new(Computed::Concat(rest)))
+        }
+    })
+}
+
+// Operands joined with `??`, binding looser than the bitwise operators.
+fn coalesce<'a>(context: Context) -> Parser<'a, u8, Literal> {
+    let operand = shared(bitwise(context, BitwiseOperator::Or.precedence()));
+
+    let parser = operand() + (space() * seq(b"??") * space() * operand()).repeat(0..);
+    parser.map(|(first, mut rest)| match rest.is_empty() {
+        true => first,
+        false => {
+            rest.insert(0, first);
+
+            Literal::LiteralComputed(Box::new(Computed::Coalesce(rest)))
+        }
+    })
+}
+
+fn operand<'a>(context: Context) -> Parser<'a, u8, Literal> {
+    coalesce(context).name("operand")
+}
+
+// Like `nested`, for operands inside other operands.
+fn nested_operand<'a>(context: Context) -> Parser<'a, u8, Literal> {
+    Parser::new(move |input: &'a [u8], start: usize| (operand(context).method)(input, start))
+}
+
+fn operation<'a>(context: Context) -> Parser<'a, u8, Operation> {
+    let parser = ((operand(context) - space()) + (operator() - space()) + operand(context))
+        .map(|((lhs, op), rhs)| Operation::new(lhs, op, rhs));
+
+    parser.name("operation")
+}
+
+// And and Or groups share their opening, so the kind is decided by the first
+// connective. Trying each kind in turn would reparse the first subexpression
+// once per kind and nesting level, which is exponential on invalid input.
+fn group<'a>(context: Context) -> Parser<'a, u8, Expression> {
+    let connective = seq_nocase(b"and").map(|_| true) | seq_nocase(b"or").map(|_| false);
+
+    let parser = ((sym(b'(') - space()) * (nested(context) - space()) + (connective - space()))
+        >> move |(first, is_and): (Expression, bool)| {
+            let word: &'static [u8] = if is_and { b"and" } else { b"or" };
+
+            ((nested(context) - space() - (seq_nocase(word) - space()).opt()).repeat(1..)
+                - (space() + sym(b')')))
+            .map(move |mut operations| {
+                operations.insert(0, first.clone());
+
+                match is_and {
+                    true => Expression::And(And::new(operations)),
+                    false => Expression::Or(Or::new(operations)),
+                }
+            })
+        };
+
+    parser.name("group")
+}
+
+fn not<'a>(context: Context) -> Parser<'a, u8, Not> {
+    let parser = ((sym(b'!') + space() + sym(b'(') + space()) * nested(context)
         - (space() + sym(b')')))
-    .map(|ex| Not::new(ex));
+    .map(Not::new);
 
     parser.name("not")
 }
 
-fn expression<'a>() -> Parser<'a, u8, Expression> {
-    let expression = and().map(|and| Expression::And(and))
-        | or().map(|or| Expression::Or(or))
-        | not().map(|not| Expression::Not(not))
-        | operation().map(|op| Expression::Operation(op));
+fn reference<'a>() -> Parser<'a, u8, Reference> {
+    let parser = sym(b'@') * identifier().map(|rule_name| Reference::new(&rule_name));
+
+    parser.name("reference")
+}
+
+// `at_tz("Europe/Copenhagen", expression)` puts the datetimes without an
+// offset in the expression in the timezone. The timezone only affects parsing,
+// the datetimes are UTC like any other.
+#[cfg(feature = "timezone")]
+fn at_tz<'a>() -> Parser<'a, u8, Expression> {
+    let timezone = (seq(b"at_tz(") + space()) * string().convert(|name| Tz::from_str(&name))
+        - (space() + sym(b',') + space());
+
+    let parser = timezone
+        >> |timezone| {
+            nested(Context {
+                timezone: Some(timezone),
+            }) - (space() + sym(b')'))
+        };
+
+    parser.name("at_tz")
+}
+
+fn expression<'a>(context: Context) -> Parser<'a, u8, Expression> {
+    let mut expression = group(context) | not(context).map(Expression::Not);
+    #[cfg(feature = "timezone")]
+    {
+        expression = expression | at_tz();
+    }
+    expression = expression
+        | reference().map(Expression::Reference)
+        | operation(context).map(Expression::Operation);
 
     expression.name("expression")
 }
 
-fn parser<'a>() -> Parser<'a, u8, Expression> {
-    space() * expression() - end()
+// Like `call`, building the parser of a subexpression only when it's reached.
+fn nested<'a>(context: Context) -> Parser<'a, u8, Expression> {
+    Parser::new(move |input: &'a [u8], start: usize| (expression(context).method)(input, start))
+}
+
+fn parser<'a>(context: Context) -> Parser<'a, u8, Expression> {
+    space() * expression(context) - end()
 }
 
 #[derive(Error, Debug)]
 pub enum ParseError {
     #[error("{0}")]
     ParsingError(#[from] pom::Error),
+    #[error("Expressions can be nested at most {0} levels deep")]
+    TooDeepError(usize),
+    #[error("The hex integer {0} is above 2^53 and can't be represented exactly")]
+    HexOutOfRangeError(String),
 }
 
 pub struct ExpressionParser;
 
 impl ExpressionParser {
     pub fn parse(input: &str) -> Result<Expression, ParseError> {
-        let expression = parser().parse(input.as_bytes())?;
+        let parse = || {
+            if nesting_depth(input) > MAX_DEPTH {
+                return Err(ParseError::TooDeepError(MAX_DEPTH));
+            }
+            if let Some(hex) = large_hex(input) {
+                return Err(ParseError::HexOutOfRangeError(hex.to_string()));
+            }
+
+            let expression = parser(Context::default()).parse(input.as_bytes())?;
+
+            Ok(expression)
+        };
 
-        Ok(expression)
+        #[cfg(feature = "metrics")]
+        return metrics::record_parse(parse);
+        #[cfg(not(feature = "metrics"))]
+        parse()
     }
+
+    // Parses with the datetimes without an offset in `timezone`, as if the
+    // whole expression was in `at_tz`.
+    #[cfg(feature = "timezone")]
+    pub fn parse_in_timezone(input: &str, timezone: Tz) -> Result<Expression, ParseError> {
+        if nesting_depth(input) > MAX_DEPTH {
+            return Err(ParseError::TooDeepError(MAX_DEPTH));
+        }
+        if let Some(hex) = large_hex(input) {
+            return Err(ParseError::HexOutOfRangeError(hex.to_string()));
+        }
+
+        let context = Context {
+            timezone: Some(timezone),
+        };
+
+        Ok(parser(context).parse(input.as_bytes())?)
+    }
+}
+
+// Every group, negation, conditional and parenthesized operand opens a
+// parenthesis, so their nesting is the deepest parenthesis, not counting any
+// inside literals.
+fn nesting_depth(input: &str) -> usize {
+    let mut depth: usize = 0;
+    let mut max_depth = 0;
+    for (span, class) in highlight(input) {
+        match (class, &input[span]) {
+            (TokenClass::Punctuation, "(") => {
+                depth += 1;
+                max_depth = max_depth.max(depth);
+            }
+            (TokenClass::Punctuation, ")") => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    max_depth
+}
+
+// The first hex integer above `MAX_INTEGER`, found before parsing since the
+// grammar would only report it as unexpected input.
+fn large_hex(input: &str) -> Option<&str> {
+    highlight(input)
+        .into_iter()
+        .map(|(span, _)| &input[span])
+        .find(|word| {
+            word.strip_prefix("0x").is_some_and(|digits| {
+                !digits.is_empty()
+                    && digits.bytes().all(|b| b.is_ascii_hexdigit())
+                    && u64::from_str_radix(digits, 16).map_or(true, |num| num > MAX_INTEGER as u64)
+            })
+        })
 }
@@ -0,0 +1,60 @@
+use alloc::string::String;
+#[cfg(feature = "raw")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+use crate::schema::{Schema, SchemaBuilder, Type};
+
+#[derive(Error, Debug)]
+pub enum RowError {
+    #[error("The field '{0}' is a {1}, which can't be read from a column")]
+    UnsupportedTypeError(String, &'static str),
+}
+
+// Rows of query results, e.g. from a database driver, with their columns
+// read by name. Implementations convert the column's database type, giving
+// None for nulls, missing columns and types that don't convert.
+pub trait ColumnAccess {
+    fn get_string(&self, column: &str) -> Option<String>;
+
+    fn get_number(&self, column: &str) -> Option<f64>;
+
+    fn get_boolean(&self, column: &str) -> Option<bool>;
+
+    #[cfg(feature = "raw")]
+    fn get_raw(&self, column: &str) -> Option<Vec<u8>>;
+
+    #[cfg(feature = "chrono")]
+    fn get_datetime(&self, column: &str) -> Option<DateTime<Utc>>;
+}
+
+// A schema over rows, so query results can be filtered by expressions without
+// converting them to structs first. Fields are read from the columns of the
+// same name. Lists, regexes and nulls can't be read from a column.
+pub fn schema<R: ColumnAccess>(fields: &[(&'static str, Type)]) -> Result<Schema<R>, RowError> {
+    let mut builder = SchemaBuilder::new();
+    for (name, field_type) in fields.iter().copied() {
+        builder = match field_type {
+            Type::String => builder.with_string_field(name, move |row: &R| row.get_string(name)),
+            Type::Number => builder.with_number_field(name, move |row: &R| row.get_number(name)),
+            Type::Boolean => builder.with_boolean_field(name, move |row: &R| row.get_boolean(name)),
+            #[cfg(feature = "raw")]
+            Type::Raw => builder.with_raw_field(name, move |row: &R| row.get_raw(name)),
+            #[cfg(feature = "chrono")]
+            Type::DateTime => {
+                builder.with_datetime_field(name, move |row: &R| row.get_datetime(name))
+            }
+            field_type => {
+                return Err(RowError::UnsupportedTypeError(
+                    String::from(name),
+                    field_type.variant_name(),
+                ));
+            }
+        };
+    }
+
+    Ok(builder.build())
+}
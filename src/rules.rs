@@ -0,0 +1,447 @@
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use thiserror::Error;
+
+use crate::{
+    engine::{self, CachedFields, Engine, EngineFields, ExecutionError, ValidationError},
+    expression::{And, Computed, Expression, Literal, MAX_DEPTH, Not, Operation, Or},
+};
+
+#[derive(Error, Debug)]
+pub enum RuleError {
+    #[error("A rule with the name '{0}' does not exist")]
+    InvalidRuleError(String),
+    #[error("Rules reference each other in a cycle: {}", .0.join(" -> "))]
+    ReferenceCycleError(Vec<String>),
+    #[error("Expressions can be nested at most {0} levels deep")]
+    TooDeepError(usize),
+    #[error("Resolved rules can have at most {0} nodes")]
+    TooManyNodesError(usize),
+    #[error("Rule '{0}' is invalid: {1}")]
+    InvalidExpressionError(String, ValidationError),
+    #[error("Rule '{0}' failed: {1}")]
+    FailedExpressionError(String, ExecutionError),
+}
+
+// How many groups, negations and operations a resolved rule can have. Rules
+// used more than once are copied at every use, so shared rules referencing
+// each other grow exponentially with the nesting.
+pub const MAX_RESOLVED_NODES: usize = 100_000;
+
+// Named expressions that can use each other through `@name` references.
+// References are kept as written, `resolve` inlines them. Rules can carry an
+// action with a priority, making the set a policy: the actions of the rules
+// matching a target are evaluated from the highest priority down, see
+// `evaluate_first_match`. Rules without an action are only used through
+// references.
+#[derive(Clone, Debug)]
+pub struct RuleSet<A = ()> {
+    rules: BTreeMap<String, Rule<A>>,
+    // The rules with actions resolved for evaluation, in the order of
+    // `by_priority`, None where resolving fails. Filled by the first
+    // evaluation and cleared by any change.
+    resolved: Cache<Vec<(String, Option<Expression>)>>,
+}
+
+// Shared rule sets are evaluated from several threads.
+#[cfg(feature = "std")]
+type Cache<T> = std::sync::OnceLock<T>;
+#[cfg(not(feature = "std"))]
+type Cache<T> = core::cell::OnceCell<T>;
+
+#[derive(Clone, Debug)]
+struct Rule<A> {
+    expression: Expression,
+    action: Option<(i32, A)>,
+}
+
+impl<A> Default for RuleSet<A> {
+    fn default() -> Self {
+        RuleSet {
+            rules: BTreeMap::new(),
+            resolved: Cache::new(),
+        }
+    }
+}
+
+impl RuleSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<A> RuleSet<A> {
+    pub fn with_rule(mut self, rule_name: &str, expression: Expression) -> Self {
+        self.insert(rule_name, expression);
+
+        self
+    }
+
+    pub fn with_action(
+        mut self,
+        rule_name: &str,
+        expression: Expression,
+        priority: i32,
+        action: A,
+    ) -> Self {
+        self.insert_action(rule_name, expression, priority, action);
+
+        self
+    }
+
+    // Returns the rule previously stored under the name.
+    pub fn insert(&mut self, rule_name: &str, expression: Expression) -> Option<Expression> {
+        self.insert_rule(rule_name, expression, None)
+    }
+
+    // Like `insert`, for a rule with an action.
+    pub fn insert_action(
+        &mut self,
+        rule_name: &str,
+        expression: Expression,
+        priority: i32,
+        action: A,
+    ) -> Option<Expression> {
+        self.insert_rule(rule_name, expression, Some((priority, action)))
+    }
+
+    pub fn remove(&mut self, rule_name: &str) -> Option<Expression> {
+        self.resolved.take();
+
+        self.rules.remove(rule_name).map(|rule| rule.expression)
+    }
+
+    pub fn get_rule(&self, rule_name: &str) -> Option<&Expression> {
+        self.rules.get(rule_name).map(|rule| &rule.expression)
+    }
+
+    // The priority and action of the rule, None if it has no action.
+    pub fn get_action(&self, rule_name: &str) -> Option<(i32, &A)> {
+        self.rules
+            .get(rule_name)
+            .and_then(|rule| rule.action.as_ref())
+            .map(|(priority, action)| (*priority, action))
+    }
+
+    pub fn rule_names(&self) -> impl Iterator<Item = &str> {
+        self.rules.keys().map(String::as_str)
+    }
+
+    // The rule with every reference replaced by the referenced rule.
+    pub fn resolve(&self, rule_name: &str) -> Result<Expression, RuleError> {
+        self.resolve_rule(rule_name, &mut Resolution::default(), 0)
+    }
+
+    // Resolves the references of an expression that isn't part of the set.
+    pub fn resolve_expression(&self, expression: &Expression) -> Result<Expression, RuleError> {
+        self.resolve_at(expression, &mut Resolution::default(), 0)
+    }
+
+    // Resolves and validates every rule, reporting the first failure in name
+    // order.
+    pub fn validate<T>(&self, engine: &Engine<T>) -> Result<(), RuleError> {
+        for rule_name in self.rules.keys() {
+            let expression = self.resolve(rule_name)?;
+            engine
+                .validate(&expression)
+                .map_err(|e| RuleError::InvalidExpressionError(rule_name.clone(), e))?;
+        }
+
+        Ok(())
+    }
+
+    // The name and action of the rule with the highest priority that matches
+    // the target. Rules of the same priority are tried in name order. Rules
+    // are resolved once per set, not per target.
+    pub fn evaluate_first_match<T>(
+        &self,
+        engine: &Engine<T>,
+        target: &T,
+    ) -> Result<Option<(&str, &A)>, RuleError> {
+        let engine_fields = EngineFields(engine);
+        let fields = CachedFields::new(&engine_fields);
+        for (rule_name, expression) in self.resolved() {
+            if self.execute(rule_name, expression.as_ref(), target, &fields)? {
+                return Ok(Some((rule_name, self.action(rule_name))));
+            }
+        }
+
+        Ok(None)
+    }
+
+    // The names and actions of every rule matching the target, in the order
+    // of `evaluate_first_match`. Fields are extracted from the target once.
+    pub fn evaluate_all_matches<T>(
+        &self,
+        engine: &Engine<T>,
+        target: &T,
+    ) -> Result<Vec<(&str, &A)>, RuleError> {
+        let engine_fields = EngineFields(engine);
+        let fields = CachedFields::new(&engine_fields);
+        let mut matches = Vec::new();
+        for (rule_name, expression) in self.resolved() {
+            if self.execute(rule_name, expression.as_ref(), target, &fields)? {
+                matches.push((rule_name.as_str(), self.action(rule_name)));
+            }
+        }
+
+        Ok(matches)
+    }
+
+    fn insert_rule(
+        &mut self,
+        rule_name: &str,
+        expression: Expression,
+        action: Option<(i32, A)>,
+    ) -> Option<Expression> {
+        self.resolved.take();
+
+        self.rules
+            .insert(String::from(rule_name), Rule { expression, action })
+            .map(|rule| rule.expression)
+    }
+
+    // The rules with actions, highest priority first. The sort is stable, so
+    // ties stay in name order.
+    fn by_priority(&self) -> Vec<(&str, &A)> {
+        let mut rules = self
+            .rules
+            .iter()
+            .filter_map(|(rule_name, rule)| {
+                rule.action
+                    .as_ref()
+                    .map(|(priority, action)| (*priority, rule_name.as_str(), action))
+            })
+            .collect::<Vec<_>>();
+        rules.sort_by_key(|(priority, _, _)| core::cmp::Reverse(*priority));
+
+        rules
+            .into_iter()
+            .map(|(_, rule_name, action)| (rule_name, action))
+            .collect()
+    }
+
+    // The rules with actions resolved, computed once for all targets.
+    fn resolved(&self) -> &[(String, Option<Expression>)] {
+        self.resolved.get_or_init(|| {
+            self.by_priority()
+                .into_iter()
+                .map(|(rule_name, _)| (String::from(rule_name), self.resolve(rule_name).ok()))
+                .collect()
+        })
+    }
+
+    // Only called for rules with an action.
+    fn action(&self, rule_name: &str) -> &A {
+        self.get_action(rule_name)
+            .map(|(_, action)| action)
+            .expect("resolved rules have actions")
+    }
+
+    fn execute<T>(
+        &self,
+        rule_name: &str,
+        expression: Option<&Expression>,
+        target: &T,
+        fields: &CachedFields<T>,
+    ) -> Result<bool, RuleError> {
+        // Errors aren't cached, resolving again reports them.
+        let Some(expression) = expression else {
+            return self.resolve(rule_name).and(Ok(false));
+        };
+
+        engine::execute(expression, target, fields)
+            .map_err(|e| RuleError::FailedExpressionError(rule_name.to_string(), e))
+    }
+
+    fn resolve_rule(
+        &self,
+        rule_name: &str,
+        resolution: &mut Resolution,
+        depth: usize,
+    ) -> Result<Expression, RuleError> {
+        let stack = &mut resolution.stack;
+        if let Some(start) = stack.iter().position(|name| name == rule_name) {
+            let mut cycle = stack[start..].to_vec();
+            cycle.push(rule_name.to_string());
+
+            return Err(RuleError::ReferenceCycleError(cycle));
+        }
+
+        let expression = self
+            .get_rule(rule_name)
+            .ok_or_else(|| RuleError::InvalidRuleError(rule_name.to_string()))?;
+
+        stack.push(rule_name.to_string());
+        let resolved = self.resolve_at(expression, resolution, depth)?;
+        resolution.stack.pop();
+
+        Ok(resolved)
+    }
+
+    // Inlining nests rules inside each other, so the depth is counted across
+    // rules to keep the recursion bounded. Nodes are counted as they are
+    // produced, so runaway copies stop at the limit instead of completing.
+    fn resolve_at(
+        &self,
+        expression: &Expression,
+        resolution: &mut Resolution,
+        depth: usize,
+    ) -> Result<Expression, RuleError> {
+        if depth > MAX_DEPTH {
+            return Err(RuleError::TooDeepError(MAX_DEPTH));
+        }
+        if !matches!(expression, Expression::Reference(_)) {
+            resolution.nodes += 1;
+            if resolution.nodes > MAX_RESOLVED_NODES {
+                return Err(RuleError::TooManyNodesError(MAX_RESOLVED_NODES));
+            }
+        }
+
+        Ok(match expression {
+            Expression::And(and) => Expression::And(And::new(self.resolve_all(
+                and.get_subexpressions(),
+                resolution,
+                depth + 1,
+            )?)),
+            Expression::Or(or) => Expression::Or(Or::new(self.resolve_all(
+                or.get_subexpressions(),
+                resolution,
+                depth + 1,
+            )?)),
+            Expression::Not(not) => Expression::Not(Not::new(self.resolve_at(
+                not.get_subexpression(),
+                resolution,
+                depth + 1,
+            )?)),
+            Expression::Operation(operation) => Expression::Operation(Operation::new(
+                self.resolve_literal(&operation.lhs, resolution, depth)?,
+                operation.op.clone(),
+                self.resolve_literal(&operation.rhs, resolution, depth)?,
+            )),
+            Expression::Reference(reference) => {
+                self.resolve_rule(reference.get_rule_name(), resolution, depth + 1)?
+            }
+        })
+    }
+
+    // Conditions of `if()` operands can reference rules too, they are nested
+    // one level below the operation like in the engine.
+    fn resolve_literal(
+        &self,
+        literal: &Literal,
+        resolution: &mut Resolution,
+        depth: usize,
+    ) -> Result<Literal, RuleError> {
+        let Literal::LiteralComputed(computed) = literal else {
+            return Ok(literal.clone());
+        };
+
+        let computed = match &**computed {
+            Computed::If(condition, then, otherwise) => Computed::If(
+                self.resolve_at(condition, resolution, depth + 1)?,
+                then.clone(),
+                otherwise.clone(),
+            ),
+            computed => computed.clone(),
+        };
+        let mut error = None;
+        let computed = computed.map_operands(|operand| {
+            if error.is_some() {
+                return operand;
+            }
+            self.resolve_literal(&operand, resolution, depth)
+                .unwrap_or_else(|e| {
+                    error = Some(e);
+                    operand
+                })
+        });
+
+        match error {
+            Some(e) => Err(e),
+            None => Ok(Literal::LiteralComputed(Box::new(computed))),
+        }
+    }
+
+    fn resolve_all(
+        &self,
+        subexpressions: &[Expression],
+        resolution: &mut Resolution,
+        depth: usize,
+    ) -> Result<Vec<Expression>, RuleError> {
+        subexpressions
+            .iter()
+            .map(|subexpression| self.resolve_at(subexpression, resolution, depth))
+            .collect()
+    }
+}
+
+// The rules being resolved, to detect cycles, and the nodes resolved so far.
+#[derive(Default)]
+struct Resolution {
+    stack: Vec<String>,
+    nodes: usize,
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::{Parser, SchemaBuilder};
+
+    use super::*;
+
+    fn rule_set() -> RuleSet<&'static str> {
+        RuleSet::default()
+            .with_rule("a", Parser::parse("x > 1").unwrap())
+            .with_action("b", Parser::parse("if(@a, 100, 10) > x").unwrap(), 0, "b")
+    }
+
+    #[test]
+    fn resolves_references_in_conditions() {
+        let set = rule_set();
+        assert_eq!(
+            set.resolve("b").unwrap(),
+            Parser::parse("if(x > 1, 100, 10) > x").unwrap()
+        );
+
+        let engine = Engine::new(
+            SchemaBuilder::<f64>::new()
+                .with_number_field("x", |x| Some(*x))
+                .build(),
+        );
+        set.validate(&engine).unwrap();
+        assert_eq!(
+            set.evaluate_first_match(&engine, &50.0).unwrap(),
+            Some(("b", &"b"))
+        );
+        assert_eq!(set.evaluate_first_match(&engine, &200.0).unwrap(), None);
+        assert_eq!(
+            set.evaluate_first_match(&engine, &0.0).unwrap(),
+            Some(("b", &"b"))
+        );
+    }
+
+    #[test]
+    fn counts_conditions_against_the_limits() {
+        let set = rule_set().with_rule("c", Parser::parse("if(@c, 1, 2) > x").unwrap());
+        assert!(matches!(
+            set.resolve("c"),
+            Err(RuleError::ReferenceCycleError(_))
+        ));
+
+        // Every level doubles the resolved conditions.
+        let mut set = RuleSet::new().with_rule("r0", Parser::parse("x > 1").unwrap());
+        for i in 1..40 {
+            let expression = format!("if((@r{0} and @r{0}), 1, 2) > x", i - 1);
+            set.insert(&format!("r{}", i), Parser::parse(&expression).unwrap());
+        }
+        assert!(matches!(
+            set.resolve("r39"),
+            Err(RuleError::TooManyNodesError(MAX_RESOLVED_NODES))
+        ));
+    }
+}
@@ -1,19 +1,47 @@
-use std::{collections::HashMap, rc::Rc};
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+};
 
+#[cfg(feature = "chrono")]
 use chrono::{DateTime, Utc};
+use hashbrown::HashMap;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "json")]
+use serde_json::Value as Json;
+#[cfg(feature = "json")]
+use thiserror::Error;
 
-#[derive(Clone, Copy, Debug)]
+use crate::{
+    descriptor::{FieldDescriptor, SchemaDescriptor},
+    engine::{ExecutionError, execute_operation},
+    expression::{Literal, Operation, Operator},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Type {
     String,
+    #[cfg(feature = "regex")]
     Regex,
     Number,
     Boolean,
+    #[cfg(feature = "raw")]
     Raw,
+    #[cfg(feature = "chrono")]
     DateTime,
     StringList,
     NumberList,
     BooleanList,
+    #[cfg(feature = "raw")]
     RawList,
+    #[cfg(feature = "chrono")]
     DateTimeList,
     Null,
 }
@@ -26,15 +54,20 @@ impl Type {
     pub fn variant_name(&self) -> &'static str {
         match self {
             Type::String => "String",
+            #[cfg(feature = "regex")]
             Type::Regex => "Regex",
             Type::Number => "Number",
             Type::Boolean => "Boolean",
+            #[cfg(feature = "raw")]
             Type::Raw => "Raw",
+            #[cfg(feature = "chrono")]
             Type::DateTime => "DateTime",
             Type::StringList => "StringList",
             Type::NumberList => "NumberList",
             Type::BooleanList => "BooleanList",
+            #[cfg(feature = "raw")]
             Type::RawList => "RawList",
+            #[cfg(feature = "chrono")]
             Type::DateTimeList => "DateTimeList",
             Type::Null => "Null",
         }
@@ -42,17 +75,23 @@ impl Type {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Value {
     String(String),
+    #[cfg(feature = "regex")]
     Regex(String),
     Number(f64),
     Boolean(bool),
+    #[cfg(feature = "raw")]
     Raw(Vec<u8>),
+    #[cfg(feature = "chrono")]
     DateTime(DateTime<Utc>),
     StringList(Vec<String>),
     NumberList(Vec<f64>),
     BooleanList(Vec<bool>),
+    #[cfg(feature = "raw")]
     RawList(Vec<Vec<u8>>),
+    #[cfg(feature = "chrono")]
     DateTimeList(Vec<DateTime<Utc>>),
     Null,
 }
@@ -65,15 +104,20 @@ impl Value {
     pub fn get_type(&self) -> Type {
         match self {
             Value::String(_) => Type::String,
+            #[cfg(feature = "regex")]
             Value::Regex(_) => Type::Regex,
             Value::Number(_) => Type::Number,
             Value::Boolean(_) => Type::Boolean,
+            #[cfg(feature = "raw")]
             Value::Raw(_) => Type::Raw,
+            #[cfg(feature = "chrono")]
             Value::DateTime(_) => Type::DateTime,
             Value::StringList(_) => Type::StringList,
             Value::NumberList(_) => Type::NumberList,
             Value::BooleanList(_) => Type::BooleanList,
+            #[cfg(feature = "raw")]
             Value::RawList(_) => Type::RawList,
+            #[cfg(feature = "chrono")]
             Value::DateTimeList(_) => Type::DateTimeList,
             Value::Null => Type::Null,
         }
@@ -82,6 +126,251 @@ impl Value {
     pub fn get_type_name(&self) -> &'static str {
         self.get_type().variant_name()
     }
+
+    // Whether `self op other` holds, with the semantics of the engine, e.g.
+    // `Value::from(3).compare(Operator::In, &Value::from(vec![1.0, 3.0]))`.
+    pub fn compare(&self, op: Operator, other: &Value) -> Result<bool, ExecutionError> {
+        let operation = Operation::new(
+            Literal::LiteralValue(self.clone()),
+            op,
+            Literal::LiteralValue(other.clone()),
+        );
+
+        execute_operation(&operation, &(), &SchemaBuilder::<()>::new().build())
+    }
+}
+
+impl From<&str> for Value {
+    fn from(val: &str) -> Self {
+        Value::String(String::from(val))
+    }
+}
+
+impl From<String> for Value {
+    fn from(val: String) -> Self {
+        Value::String(val)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(val: f64) -> Self {
+        Value::Number(val)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(val: i32) -> Self {
+        Value::Number(f64::from(val))
+    }
+}
+
+// Beyond 2^53 integers lose precision, like in the parser.
+impl From<i64> for Value {
+    fn from(val: i64) -> Self {
+        Value::Number(val as f64)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(val: bool) -> Self {
+        Value::Boolean(val)
+    }
+}
+
+#[cfg(feature = "raw")]
+impl From<Vec<u8>> for Value {
+    fn from(val: Vec<u8>) -> Self {
+        Value::Raw(val)
+    }
+}
+
+impl From<Vec<String>> for Value {
+    fn from(val: Vec<String>) -> Self {
+        Value::StringList(val)
+    }
+}
+
+impl From<Vec<&str>> for Value {
+    fn from(val: Vec<&str>) -> Self {
+        Value::StringList(val.into_iter().map(String::from).collect())
+    }
+}
+
+impl From<Vec<f64>> for Value {
+    fn from(val: Vec<f64>) -> Self {
+        Value::NumberList(val)
+    }
+}
+
+impl From<Vec<bool>> for Value {
+    fn from(val: Vec<bool>) -> Self {
+        Value::BooleanList(val)
+    }
+}
+
+#[cfg(feature = "raw")]
+impl From<Vec<Vec<u8>>> for Value {
+    fn from(val: Vec<Vec<u8>>) -> Self {
+        Value::RawList(val)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<Vec<DateTime<Utc>>> for Value {
+    fn from(val: Vec<DateTime<Utc>>) -> Self {
+        Value::DateTimeList(val)
+    }
+}
+
+// None is null, like a field without a value.
+impl<V: Into<Value>> From<Option<V>> for Value {
+    fn from(val: Option<V>) -> Self {
+        val.map_or(Value::Null, Into::into)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<DateTime<Utc>> for Value {
+    fn from(val: DateTime<Utc>) -> Self {
+        Value::DateTime(val)
+    }
+}
+
+#[cfg(feature = "json")]
+#[derive(Error, Debug)]
+pub enum JsonValueError {
+    #[error("JSON objects can't be values")]
+    ObjectError,
+    #[error("Empty lists have no type")]
+    EmptyListError,
+    #[error("Lists must be of only strings, numbers or booleans")]
+    MixedListError,
+}
+
+// Strings stay strings, also when they look like datetimes. Lists take the
+// type of their items.
+#[cfg(feature = "json")]
+impl TryFrom<Json> for Value {
+    type Error = JsonValueError;
+
+    fn try_from(json: Json) -> Result<Self, Self::Error> {
+        Ok(match json {
+            Json::Null => Value::Null,
+            Json::Bool(val) => Value::Boolean(val),
+            Json::Number(val) => Value::Number(val.as_f64().unwrap_or(f64::NAN)),
+            Json::String(val) => Value::String(val),
+            Json::Array(items) => match items.first() {
+                None => return Err(JsonValueError::EmptyListError),
+                Some(Json::String(_)) => Value::StringList(
+                    items
+                        .into_iter()
+                        .map(|item| match item {
+                            Json::String(val) => Ok(val),
+                            _ => Err(JsonValueError::MixedListError),
+                        })
+                        .collect::<Result<_, _>>()?,
+                ),
+                Some(Json::Number(_)) => Value::NumberList(
+                    items
+                        .iter()
+                        .map(|item| item.as_f64().ok_or(JsonValueError::MixedListError))
+                        .collect::<Result<_, _>>()?,
+                ),
+                Some(Json::Bool(_)) => Value::BooleanList(
+                    items
+                        .iter()
+                        .map(|item| item.as_bool().ok_or(JsonValueError::MixedListError))
+                        .collect::<Result<_, _>>()?,
+                ),
+                Some(_) => return Err(JsonValueError::MixedListError),
+            },
+            Json::Object(_) => return Err(JsonValueError::ObjectError),
+        })
+    }
+}
+
+// Numbers are compared and hashed by their bit pattern, so equality is
+// structural: `NaN` equals itself and `-0.0` differs from `0.0`.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::String(lhv), Value::String(rhv)) => lhv == rhv,
+            #[cfg(feature = "regex")]
+            (Value::Regex(lhv), Value::Regex(rhv)) => lhv == rhv,
+            (Value::Number(lhv), Value::Number(rhv)) => lhv.to_bits() == rhv.to_bits(),
+            (Value::Boolean(lhv), Value::Boolean(rhv)) => lhv == rhv,
+            #[cfg(feature = "raw")]
+            (Value::Raw(lhv), Value::Raw(rhv)) => lhv == rhv,
+            #[cfg(feature = "chrono")]
+            (Value::DateTime(lhv), Value::DateTime(rhv)) => lhv == rhv,
+            (Value::StringList(lhv), Value::StringList(rhv)) => lhv == rhv,
+            (Value::NumberList(lhv), Value::NumberList(rhv)) => {
+                lhv.len() == rhv.len()
+                    && lhv
+                        .iter()
+                        .zip(rhv)
+                        .all(|(lhv, rhv)| lhv.to_bits() == rhv.to_bits())
+            }
+            (Value::BooleanList(lhv), Value::BooleanList(rhv)) => lhv == rhv,
+            #[cfg(feature = "raw")]
+            (Value::RawList(lhv), Value::RawList(rhv)) => lhv == rhv,
+            #[cfg(feature = "chrono")]
+            (Value::DateTimeList(lhv), Value::DateTimeList(rhv)) => lhv == rhv,
+            (Value::Null, Value::Null) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+// Values of the same scalar type are ordered, numbers by `f64::total_cmp` to
+// agree with equality. Other values are only ordered when equal.
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Value::String(lhv), Value::String(rhv)) => Some(lhv.cmp(rhv)),
+            (Value::Number(lhv), Value::Number(rhv)) => Some(lhv.total_cmp(rhv)),
+            (Value::Boolean(lhv), Value::Boolean(rhv)) => Some(lhv.cmp(rhv)),
+            #[cfg(feature = "raw")]
+            (Value::Raw(lhv), Value::Raw(rhv)) => Some(lhv.cmp(rhv)),
+            #[cfg(feature = "chrono")]
+            (Value::DateTime(lhv), Value::DateTime(rhv)) => Some(lhv.cmp(rhv)),
+            (lhv, rhv) if lhv == rhv => Some(Ordering::Equal),
+            _ => None,
+        }
+    }
+}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        core::mem::discriminant(self).hash(state);
+
+        match self {
+            Value::String(val) => val.hash(state),
+            #[cfg(feature = "regex")]
+            Value::Regex(val) => val.hash(state),
+            Value::Number(val) => val.to_bits().hash(state),
+            Value::Boolean(val) => val.hash(state),
+            #[cfg(feature = "raw")]
+            Value::Raw(val) => val.hash(state),
+            #[cfg(feature = "chrono")]
+            Value::DateTime(val) => val.hash(state),
+            Value::StringList(items) => items.hash(state),
+            Value::NumberList(items) => {
+                items.len().hash(state);
+                for val in items {
+                    val.to_bits().hash(state);
+                }
+            }
+            Value::BooleanList(items) => items.hash(state),
+            #[cfg(feature = "raw")]
+            Value::RawList(items) => items.hash(state),
+            #[cfg(feature = "chrono")]
+            Value::DateTimeList(items) => items.hash(state),
+            Value::Null => {}
+        }
+    }
 }
 
 pub struct Field<T> {
@@ -99,7 +388,8 @@ impl<T> Field<T> {
 }
 
 pub struct SchemaBuilder<T> {
-    fields: HashMap<&'static str, Rc<Field<T>>>,
+    fields: HashMap<&'static str, Field<T>>,
+    descriptions: HashMap<&'static str, String>,
 }
 
 macro_rules! field_extractor_builder {
@@ -113,47 +403,88 @@ macro_rules! field_extractor_builder {
                 extractor(target).map_or_else(|| Value::Null, |val| Value::$enum_name(val))
             });
 
-            self.fields.insert(
-                field_name,
-                Rc::new(Field::new(Type::$enum_name, wrapped_extractor)),
-            );
+            self.fields
+                .insert(field_name, Field::new(Type::$enum_name, wrapped_extractor));
 
             self
         }
     };
 }
 
+impl<T> Default for SchemaBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> SchemaBuilder<T> {
     pub fn new() -> Self {
         Self {
             fields: HashMap::new(),
+            descriptions: HashMap::new(),
         }
     }
 
     field_extractor_builder!(with_string_field, String, String);
     field_extractor_builder!(with_number_field, f64, Number);
     field_extractor_builder!(with_boolean_field, bool, Boolean);
+    #[cfg(feature = "raw")]
     field_extractor_builder!(with_raw_field, Vec<u8>, Raw);
+    #[cfg(feature = "chrono")]
     field_extractor_builder!(with_datetime_field, DateTime<Utc>, DateTime);
     field_extractor_builder!(with_string_list_field, Vec<String>, StringList);
     field_extractor_builder!(with_number_list_field, Vec<f64>, NumberList);
     field_extractor_builder!(with_boolean_list_field, Vec<bool>, BooleanList);
+    #[cfg(feature = "raw")]
     field_extractor_builder!(with_raw_list_field, Vec<Vec<u8>>, RawList);
+    #[cfg(feature = "chrono")]
     field_extractor_builder!(with_datetime_list_field, Vec<DateTime<Utc>>, DateTimeList);
 
+    pub fn with_description(mut self, field_name: &'static str, description: &str) -> Self {
+        self.descriptions
+            .insert(field_name, String::from(description));
+
+        self
+    }
+
     pub fn build(self) -> Schema<T> {
         Schema {
             fields: self.fields,
+            descriptions: self.descriptions,
         }
     }
 }
 
 pub struct Schema<T> {
-    fields: HashMap<&'static str, Rc<Field<T>>>,
+    fields: HashMap<&'static str, Field<T>>,
+    descriptions: HashMap<&'static str, String>,
 }
 
 impl<T> Schema<T> {
-    pub fn get_field(&self, field_name: &str) -> Option<Rc<Field<T>>> {
-        self.fields.get(field_name).cloned()
+    pub fn get_field(&self, field_name: &str) -> Option<&Field<T>> {
+        self.fields.get(field_name)
+    }
+
+    pub fn get_field_names(&self) -> impl Iterator<Item = &str> {
+        self.fields.keys().copied()
+    }
+
+    pub fn get_description(&self, field_name: &str) -> Option<&str> {
+        self.descriptions.get(field_name).map(String::as_str)
+    }
+
+    pub fn descriptor(&self) -> SchemaDescriptor {
+        let mut fields = self
+            .fields
+            .iter()
+            .map(|(name, field)| FieldDescriptor {
+                name: name.to_string(),
+                field_type: field.field_type,
+                description: self.get_description(name).map(String::from),
+            })
+            .collect::<Vec<FieldDescriptor>>();
+        fields.sort_by(|a, b| a.name.cmp(&b.name));
+
+        SchemaDescriptor { fields }
     }
 }
@@ -1,5 +1,22 @@
+//! Textual serialization of expressions.
+//!
+//! Serialization is the inverse of [`Parser::parse`](crate::Parser::parse): for every
+//! `Expression` produced by the parser, parsing the output of `serialize()` yields the
+//! same expression again. Hand-built expressions round-trip as long as they stay within
+//! what the grammar can express (e.g. And/Or with at least two children, non-empty lists
+//! and raw values, finite numbers, field names matching the field syntax).
+//!
+//! [`minify`] produces the same guarantee with all optional whitespace removed and
+//! numbers written in their shortest form, for embedding in URLs and headers.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
 use crate::{
-    expression::{And, Expression, Literal, Not, Operation, Operator, Or},
+    expression::{And, Computed, Expression, Literal, Not, Operation, Operator, Or, Reference},
     schema::Value,
 };
 
@@ -14,6 +31,7 @@ impl Serialize for Expression {
             Expression::Or(or) => Serialize::fmt(or),
             Expression::Not(not) => Serialize::fmt(not),
             Expression::Operation(operation) => Serialize::fmt(operation),
+            Expression::Reference(reference) => Serialize::fmt(reference),
         }
     }
 }
@@ -24,7 +42,7 @@ impl Serialize for And {
             "({})",
             self.get_subexpressions()
                 .iter()
-                .map(|e| Serialize::fmt(e))
+                .map(Serialize::fmt)
                 .collect::<Vec<String>>()
                 .join(" AND ")
         )
@@ -37,7 +55,7 @@ impl Serialize for Or {
             "({})",
             self.get_subexpressions()
                 .iter()
-                .map(|e| Serialize::fmt(e))
+                .map(Serialize::fmt)
                 .collect::<Vec<String>>()
                 .join(" OR ")
         )
@@ -66,19 +84,171 @@ impl Serialize for Literal {
         match self {
             Literal::LiteralValue(value) => Serialize::fmt(value),
             Literal::LiteralField(field_name) => field_name.to_string(),
+            #[cfg(feature = "chrono")]
+            Literal::LiteralAge(field_name) => format!("age_of({})", field_name),
+            Literal::LiteralComputed(computed) => {
+                fmt_computed(computed, Serialize::fmt, Serialize::fmt, " ")
+            }
         }
     }
 }
 
-fn format_regex(val: &String) -> String {
+// Operands are parenthesized where the parser would otherwise group them
+// differently, given how tightly their operators bind.
+fn fmt_computed(
+    computed: &Computed,
+    fmt_literal: fn(&Literal) -> String,
+    fmt_expression: fn(&Expression) -> String,
+    space: &str,
+) -> String {
+    let (first, rest): (_, Vec<(&str, u8, &Literal)>) = match computed {
+        Computed::Bitwise(first, rest) => (
+            Some(first),
+            rest.iter()
+                .map(|(op, operand)| (op.fmt_static(), op.precedence(), operand))
+                .collect(),
+        ),
+        Computed::Concat(operands) => (
+            operands.first(),
+            operands
+                .iter()
+                .skip(1)
+                .map(|operand| ("+", computed.precedence(), operand))
+                .collect(),
+        ),
+        Computed::Coalesce(operands) => (
+            operands.first(),
+            operands
+                .iter()
+                .skip(1)
+                .map(|operand| ("??", computed.precedence(), operand))
+                .collect(),
+        ),
+        Computed::If(condition, then, otherwise) => {
+            return format!(
+                "if({},{}{},{}{})",
+                fmt_expression(condition),
+                space,
+                fmt_literal(then),
+                space,
+                fmt_literal(otherwise)
+            );
+        }
+        Computed::First(list) => return format!("first({})", fmt_literal(list)),
+        Computed::Last(list) => return format!("last({})", fmt_literal(list)),
+        // Only one slice parses per operand, so slices of slices need
+        // parentheses too.
+        Computed::Slice(list, start, end) => {
+            let list = match list {
+                Literal::LiteralComputed(computed)
+                    if computed.precedence() < u8::MAX
+                        || matches!(**computed, Computed::Slice(_, _, _)) =>
+                {
+                    format!("({})", fmt_literal(list))
+                }
+                _ => fmt_literal(list),
+            };
+
+            return format!("{}[{}..{}]", list, start, end);
+        }
+    };
+    // Nothing to join is the empty string, and nothing to coalesce null.
+    let Some(first) = first else {
+        return match computed {
+            Computed::Coalesce(_) => String::from("null"),
+            _ => format_string(""),
+        };
+    };
+
+    let mut out = fmt_literal(first);
+    let mut binding = precedence(first);
+    if let Some((_, first_precedence, _)) = rest.first()
+        && binding <= *first_precedence
+    {
+        out = format!("({})", out);
+        binding = u8::MAX;
+    }
+    for (op, op_precedence, operand) in rest {
+        if binding < op_precedence {
+            out = format!("({})", out);
+        }
+        let operand = match precedence(operand) <= op_precedence {
+            true => format!("({})", fmt_literal(operand)),
+            false => fmt_literal(operand),
+        };
+        out = format!("{}{}{}{}{}", out, space, op, space, operand);
+        binding = op_precedence;
+    }
+
+    out
+}
+
+fn precedence(literal: &Literal) -> u8 {
+    match literal {
+        Literal::LiteralComputed(computed) => computed.precedence(),
+        _ => u8::MAX,
+    }
+}
+
+// Whether the literal is written ending in a field name, which would run into
+// a following keyword without a space.
+fn ends_with_field(literal: &Literal) -> bool {
+    match literal {
+        Literal::LiteralField(_) => true,
+        Literal::LiteralComputed(computed) => match &**computed {
+            Computed::Bitwise(_, _) | Computed::Concat(_) | Computed::Coalesce(_) => computed
+                .get_operands()
+                .last()
+                .is_some_and(|operand| ends_with_field(operand)),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn format_string(val: &str) -> String {
+    let mut out = String::with_capacity(val.len() + 2);
+    out.push('"');
+    for c in val.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\x08' => out.push_str("\\b"),
+            '\x0C' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+
+    out
+}
+
+#[cfg(feature = "regex")]
+fn format_regex(val: &str) -> String {
     format!("/{}/", val.replace("/", "\\/"))
 }
 
-fn format_raw(val: &Vec<u8>) -> String {
+// Both `{}` and `{:e}` print the shortest digits that parse back to the same
+// f64, the same on every platform. Like JavaScript, very large and very small
+// magnitudes use an exponent instead of spelling out the zeros.
+fn format_number(val: f64) -> String {
+    let magnitude = val.abs();
+    if magnitude >= 1e21 || (magnitude != 0.0 && magnitude < 1e-6) {
+        format!("{:e}", val)
+    } else {
+        format!("{}", val)
+    }
+}
+
+#[cfg(feature = "raw")]
+fn format_raw(val: &[u8]) -> String {
     format!(
         "|{}|",
         val.iter()
-            .map(|byte| format!("{:02x?}", byte))
+            .map(|byte| format!("{:02x}", byte))
             .collect::<Vec<String>>()
             .join(" ")
     )
@@ -87,17 +257,20 @@ fn format_raw(val: &Vec<u8>) -> String {
 impl Serialize for Value {
     fn fmt(&self) -> String {
         match self {
-            Value::String(val) => format!("{:?}", val),
+            Value::String(val) => format_string(val),
+            #[cfg(feature = "regex")]
             Value::Regex(val) => format_regex(val),
-            Value::Number(val) => format!("{}", val),
+            Value::Number(val) => format_number(*val),
             Value::Boolean(val) => format!("{}", val),
+            #[cfg(feature = "raw")]
             Value::Raw(val) => format_raw(val),
+            #[cfg(feature = "chrono")]
             Value::DateTime(val) => val.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true),
             Value::StringList(items) => format!(
                 "[{}]",
                 items
                     .iter()
-                    .map(|val| format!("{:?}", val))
+                    .map(|val| format_string(val))
                     .collect::<Vec<String>>()
                     .join(", ")
             ),
@@ -105,7 +278,7 @@ impl Serialize for Value {
                 "[{}]",
                 items
                     .iter()
-                    .map(|val| format!("{}", val))
+                    .map(|val| format_number(*val))
                     .collect::<Vec<String>>()
                     .join(", ")
             ),
@@ -117,14 +290,16 @@ impl Serialize for Value {
                     .collect::<Vec<String>>()
                     .join(", ")
             ),
+            #[cfg(feature = "raw")]
             Value::RawList(items) => format!(
                 "[{}]",
                 items
                     .iter()
-                    .map(format_raw)
+                    .map(|val| format_raw(val))
                     .collect::<Vec<String>>()
                     .join(", ")
             ),
+            #[cfg(feature = "chrono")]
             Value::DateTimeList(items) => format!(
                 "[{}]",
                 items
@@ -138,8 +313,107 @@ impl Serialize for Value {
     }
 }
 
+impl Serialize for Reference {
+    fn fmt(&self) -> String {
+        format!("@{}", self.get_rule_name())
+    }
+}
+
 impl Serialize for Operator {
     fn fmt(&self) -> String {
         self.fmt_static().to_string()
     }
 }
+
+pub fn minify(expression: &Expression) -> String {
+    match expression {
+        Expression::And(and) => minify_group(and.get_subexpressions(), "AND"),
+        Expression::Or(or) => minify_group(or.get_subexpressions(), "OR"),
+        Expression::Not(not) => format!("!({})", minify(not.get_subexpression())),
+        Expression::Reference(reference) => Serialize::fmt(reference),
+        Expression::Operation(operation) => {
+            // A field running into `IN` or `AND`/`OR` would be read as part of its name.
+            let separator = match (ends_with_field(&operation.lhs), &operation.op) {
+                (true, Operator::In) => " ",
+                _ => "",
+            };
+
+            format!(
+                "{}{}{}{}",
+                minify_literal(&operation.lhs),
+                separator,
+                operation.op.fmt_static(),
+                minify_literal(&operation.rhs)
+            )
+        }
+    }
+}
+
+fn minify_group(subexpressions: &[Expression], keyword: &str) -> String {
+    let mut out = String::from("(");
+    for (i, subexpression) in subexpressions.iter().enumerate() {
+        if i > 0 {
+            let ends_with_field = match &subexpressions[i - 1] {
+                Expression::Operation(operation) => ends_with_field(&operation.rhs),
+                Expression::Reference(_) => true,
+                _ => false,
+            };
+            if ends_with_field {
+                out.push(' ');
+            }
+            out.push_str(keyword);
+        }
+        out.push_str(&minify(subexpression));
+    }
+    out.push(')');
+
+    out
+}
+
+fn minify_literal(literal: &Literal) -> String {
+    let value = match literal {
+        Literal::LiteralValue(value) => value,
+        Literal::LiteralField(field_name) => return field_name.to_string(),
+        #[cfg(feature = "chrono")]
+        Literal::LiteralAge(_) => return Serialize::fmt(literal),
+        Literal::LiteralComputed(computed) => {
+            return fmt_computed(computed, minify_literal, minify, "");
+        }
+    };
+
+    match value {
+        Value::Number(val) => format_number_short(*val),
+        #[cfg(feature = "raw")]
+        Value::Raw(val) => format_raw(val).replace(' ', ""),
+        Value::StringList(items) => minify_list(items.iter().map(|val| format_string(val))),
+        Value::NumberList(items) => minify_list(items.iter().map(|val| format_number_short(*val))),
+        Value::BooleanList(items) => minify_list(items.iter().map(|val| format!("{}", val))),
+        #[cfg(feature = "raw")]
+        Value::RawList(items) => {
+            minify_list(items.iter().map(|val| format_raw(val).replace(' ', "")))
+        }
+        #[cfg(feature = "chrono")]
+        Value::DateTimeList(items) => minify_list(
+            items
+                .iter()
+                .map(|val| val.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)),
+        ),
+        value => Serialize::fmt(value),
+    }
+}
+
+fn minify_list(items: impl Iterator<Item = String>) -> String {
+    format!("[{}]", items.collect::<Vec<String>>().join(","))
+}
+
+// `1e21` instead of `1000000000000000000000`; both forms are exact.
+fn format_number_short(val: f64) -> String {
+    let plain = format!("{}", val);
+    let exponent = format!("{:e}", val);
+
+    if exponent.len() < plain.len() {
+        exponent
+    } else {
+        plain
+    }
+}
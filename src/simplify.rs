@@ -0,0 +1,50 @@
+use alloc::vec::Vec;
+
+use hashbrown::HashSet;
+
+use crate::expression::{And, Expression, Not, Or};
+
+// Removes double negation, flattens nested groups of the same kind, drops
+// duplicate clauses and pushes negation over And/Or (De Morgan), so negations
+// only remain directly around operations and references. Operators are never
+// inverted, as e.g. `!(a > 1)` and `a <= 1` differ when `a` is null.
+pub fn simplify(expression: &Expression) -> Expression {
+    match expression {
+        Expression::And(and) => group(and.get_subexpressions(), true),
+        Expression::Or(or) => group(or.get_subexpressions(), false),
+        Expression::Not(not) => match not.get_subexpression() {
+            Expression::Not(inner) => simplify(inner.get_subexpression()),
+            Expression::And(and) => group(&negate_all(and.get_subexpressions()), false),
+            Expression::Or(or) => group(&negate_all(or.get_subexpressions()), true),
+            operation => Expression::Not(Not::new(operation.clone())),
+        },
+        Expression::Operation(_) | Expression::Reference(_) => expression.clone(),
+    }
+}
+
+fn negate_all(subexpressions: &[Expression]) -> Vec<Expression> {
+    subexpressions
+        .iter()
+        .map(|subexpression| Expression::Not(Not::new(subexpression.clone())))
+        .collect()
+}
+
+fn group(subexpressions: &[Expression], is_and: bool) -> Expression {
+    let mut clauses = Vec::with_capacity(subexpressions.len());
+    for subexpression in subexpressions.iter().map(simplify) {
+        match (subexpression, is_and) {
+            (Expression::And(and), true) => clauses.extend(and.into_subexpressions()),
+            (Expression::Or(or), false) => clauses.extend(or.into_subexpressions()),
+            (subexpression, _) => clauses.push(subexpression),
+        }
+    }
+
+    let mut seen = HashSet::new();
+    clauses.retain(|clause| seen.insert(clause.clone()));
+
+    match (clauses.len(), is_and) {
+        (1, _) => clauses.remove(0),
+        (_, true) => Expression::And(And::new(clauses)),
+        (_, false) => Expression::Or(Or::new(clauses)),
+    }
+}
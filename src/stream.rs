@@ -0,0 +1,115 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    engine::{Engine, EngineFields, ExecutionError, execute_at},
+    expression::Expression,
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClauseStats {
+    // Items the clause was executed on. Clauses after a deciding one are
+    // skipped, like the engine does.
+    pub evaluated: u64,
+    pub hits: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StreamStats {
+    pub scanned: u64,
+    pub matched: u64,
+    pub errors: u64,
+    // One entry per top-level clause: the subexpressions of a top-level And or
+    // Or, otherwise the whole expression.
+    pub clauses: Vec<ClauseStats>,
+}
+
+// The items matching an expression, read from an iterator of items. Execution
+// errors are yielded in place of the item and don't end the stream.
+pub struct EvaluateStream<'a, T, I> {
+    engine: &'a Engine<T>,
+    expression: &'a Expression,
+    items: I,
+    stats: StreamStats,
+}
+
+impl<'a, T, I: Iterator<Item = T>> EvaluateStream<'a, T, I> {
+    pub(crate) fn new(engine: &'a Engine<T>, expression: &'a Expression, items: I) -> Self {
+        let clauses = match expression {
+            Expression::And(and) => and.get_subexpressions().len(),
+            Expression::Or(or) => or.get_subexpressions().len(),
+            _ => 1,
+        };
+
+        Self {
+            engine,
+            expression,
+            items,
+            stats: StreamStats {
+                clauses: vec![ClauseStats::default(); clauses],
+                ..StreamStats::default()
+            },
+        }
+    }
+
+    pub fn get_stats(&self) -> &StreamStats {
+        &self.stats
+    }
+
+    pub fn into_stats(self) -> StreamStats {
+        self.stats
+    }
+
+    fn evaluate(&mut self, item: &T) -> Result<bool, ExecutionError> {
+        let (clauses, is_and) = match self.expression {
+            Expression::And(and) => (&and.get_subexpressions()[..], true),
+            Expression::Or(or) => (&or.get_subexpressions()[..], false),
+            expression => (core::slice::from_ref(expression), true),
+        };
+
+        // Clauses of a group are one level down.
+        let depth = match self.expression {
+            Expression::And(_) | Expression::Or(_) => 1,
+            _ => 0,
+        };
+
+        let fields = EngineFields(self.engine);
+        for (clause, stats) in clauses.iter().zip(&mut self.stats.clauses) {
+            let result = execute_at(clause, item, &fields, depth)?;
+            stats.evaluated += 1;
+            if result {
+                stats.hits += 1;
+            }
+
+            if result != is_and {
+                return Ok(result);
+            }
+        }
+
+        Ok(is_and)
+    }
+}
+
+impl<T, I: Iterator<Item = T>> Iterator for EvaluateStream<'_, T, I> {
+    type Item = Result<T, ExecutionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let item = self.items.next()?;
+            self.stats.scanned += 1;
+
+            match self.evaluate(&item) {
+                Ok(true) => {
+                    self.stats.matched += 1;
+
+                    return Some(Ok(item));
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    self.stats.errors += 1;
+
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,243 @@
+#[cfg(feature = "regex")]
+use alloc::format;
+#[cfg(feature = "chrono")]
+use alloc::vec;
+use alloc::{string::String, vec::Vec};
+
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+
+use crate::{
+    descriptor::{FieldDescriptor, SchemaDescriptor},
+    expression::{And, Expression, Literal, Not, Operation, Operator, Or},
+    schema::{Type, Value},
+};
+
+// Generates random expressions that validate against a schema descriptor. The
+// output only depends on the descriptor, the seed and the size settings, so a
+// failing case can be reproduced from its seed.
+pub struct ExprGenerator {
+    fields: Vec<FieldDescriptor>,
+    state: u64,
+    max_depth: usize,
+    max_width: usize,
+}
+
+impl ExprGenerator {
+    pub fn new(descriptor: SchemaDescriptor, seed: u64) -> Self {
+        let mut fields = descriptor
+            .fields
+            .into_iter()
+            .filter(|field| !field.field_type.is_null())
+            .collect::<Vec<FieldDescriptor>>();
+        #[cfg(feature = "regex")]
+        fields.retain(|field| field.field_type != Type::Regex);
+        assert!(!fields.is_empty(), "the descriptor has no usable fields");
+        // Descriptor order must not affect the output.
+        fields.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Self {
+            fields,
+            state: seed,
+            max_depth: 3,
+            max_width: 4,
+        }
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+
+        self
+    }
+
+    // The maximum number of children of an And/Or node, at least 2.
+    pub fn with_max_width(mut self, max_width: usize) -> Self {
+        self.max_width = max_width.max(2);
+
+        self
+    }
+
+    pub fn generate(&mut self) -> Expression {
+        self.expression(self.max_depth)
+    }
+
+    // splitmix64
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    fn pick<T: Clone>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len())].clone()
+    }
+
+    fn expression(&mut self, depth: usize) -> Expression {
+        if depth == 0 {
+            return Expression::Operation(self.operation());
+        }
+
+        match self.below(6) {
+            0 => Expression::And(And::new(self.subexpressions(depth - 1))),
+            1 => Expression::Or(Or::new(self.subexpressions(depth - 1))),
+            2 => Expression::Not(Not::new(self.expression(depth - 1))),
+            _ => Expression::Operation(self.operation()),
+        }
+    }
+
+    fn subexpressions(&mut self, depth: usize) -> Vec<Expression> {
+        let count = 2 + self.below(self.max_width - 1);
+
+        (0..count).map(|_| self.expression(depth)).collect()
+    }
+
+    fn operation(&mut self) -> Operation {
+        let index = self.below(self.fields.len());
+        let field = self.fields[index].clone();
+        let name = Literal::LiteralField(field.name.into());
+        let field_type = field.field_type;
+
+        if self.below(10) == 0 {
+            let op = self.pick(&[Operator::Eq, Operator::Ne]);
+            return Operation::new(name, op, Literal::LiteralValue(Value::Null));
+        }
+
+        let ordered = match field_type {
+            Type::Number => true,
+            #[cfg(feature = "chrono")]
+            Type::DateTime => true,
+            _ => false,
+        };
+        let comparisons = match ordered {
+            true => &[
+                Operator::Eq,
+                Operator::Ne,
+                Operator::Gt,
+                Operator::Gte,
+                Operator::Lt,
+                Operator::Lte,
+            ][..],
+            false => &[Operator::Eq, Operator::Ne][..],
+        };
+
+        // Half of the operations compare with a value of the field's own type,
+        // the others use one of the `IN` forms the type supports.
+        if self.below(2) == 0 {
+            let op = self.pick(comparisons);
+            let value = Literal::LiteralValue(self.value(field_type));
+
+            return match self.below(2) {
+                0 => Operation::new(name, op, value),
+                _ => Operation::new(value, op, name),
+            };
+        }
+
+        let value =
+            |generator: &mut Self, value_type| Literal::LiteralValue(generator.value(value_type));
+
+        match field_type {
+            Type::String => match self.below(2 + cfg!(feature = "regex") as usize) {
+                0 => Operation::new(name, Operator::In, value(self, Type::StringList)),
+                #[cfg(feature = "regex")]
+                2 => Operation::new(value(self, Type::Regex), Operator::In, name),
+                _ => Operation::new(value(self, Type::String), Operator::In, name),
+            },
+            Type::Number => Operation::new(name, Operator::In, value(self, Type::NumberList)),
+            Type::Boolean => Operation::new(name, Operator::In, value(self, Type::BooleanList)),
+            #[cfg(feature = "raw")]
+            Type::Raw => match self.below(2) {
+                0 => Operation::new(name, Operator::In, value(self, Type::RawList)),
+                _ => Operation::new(value(self, Type::Raw), Operator::In, name),
+            },
+            #[cfg(feature = "chrono")]
+            Type::DateTime => {
+                let (a, b) = (self.datetime(), self.datetime());
+                let range = Value::DateTimeList(vec![a.min(b), a.max(b)]);
+
+                Operation::new(name, Operator::In, Literal::LiteralValue(range))
+            }
+            Type::StringList => match self.below(1 + cfg!(feature = "regex") as usize) {
+                #[cfg(feature = "regex")]
+                1 => Operation::new(value(self, Type::Regex), Operator::In, name),
+                _ => Operation::new(value(self, Type::String), Operator::In, name),
+            },
+            Type::NumberList => Operation::new(value(self, Type::Number), Operator::In, name),
+            Type::BooleanList => Operation::new(value(self, Type::Boolean), Operator::In, name),
+            #[cfg(feature = "raw")]
+            Type::RawList => Operation::new(value(self, Type::Raw), Operator::In, name),
+            // DateTimeList, Regex and Null
+            _ => {
+                let op = self.pick(comparisons);
+
+                Operation::new(name, op, value(self, field_type))
+            }
+        }
+    }
+
+    fn value(&mut self, value_type: Type) -> Value {
+        let count = 1 + self.below(3);
+
+        match value_type {
+            Type::String => Value::String(self.string()),
+            // Patterns are plain words, so they always compile.
+            #[cfg(feature = "regex")]
+            Type::Regex => Value::Regex(format!("^{}", self.string())),
+            Type::Number => Value::Number(self.number()),
+            Type::Boolean => Value::Boolean(self.below(2) == 0),
+            #[cfg(feature = "raw")]
+            Type::Raw => Value::Raw(self.raw()),
+            #[cfg(feature = "chrono")]
+            Type::DateTime => Value::DateTime(self.datetime()),
+            Type::StringList => Value::StringList((0..count).map(|_| self.string()).collect()),
+            Type::NumberList => Value::NumberList((0..count).map(|_| self.number()).collect()),
+            Type::BooleanList => {
+                Value::BooleanList((0..count).map(|_| self.below(2) == 0).collect())
+            }
+            #[cfg(feature = "raw")]
+            Type::RawList => Value::RawList((0..count).map(|_| self.raw()).collect()),
+            #[cfg(feature = "chrono")]
+            Type::DateTimeList => {
+                Value::DateTimeList((0..count).map(|_| self.datetime()).collect())
+            }
+            Type::Null => Value::Null,
+        }
+    }
+
+    fn string(&mut self) -> String {
+        const CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+        let len = self.below(8);
+
+        (0..len).map(|_| self.pick(CHARS) as char).collect()
+    }
+
+    fn number(&mut self) -> f64 {
+        let num = self.below(2001) as f64 - 1000.0;
+
+        match self.below(4) {
+            0 => num / 4.0,
+            _ => num,
+        }
+    }
+
+    #[cfg(feature = "raw")]
+    fn raw(&mut self) -> Vec<u8> {
+        let len = 1 + self.below(4);
+
+        (0..len).map(|_| self.next_u64() as u8).collect()
+    }
+
+    #[cfg(feature = "chrono")]
+    // Between 2000-01-01 and 2030-01-01.
+    fn datetime(&mut self) -> DateTime<Utc> {
+        let secs = 946_684_800 + self.below(946_771_200) as i64;
+
+        DateTime::from_timestamp(secs, 0).unwrap()
+    }
+}
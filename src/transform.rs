@@ -0,0 +1,150 @@
+use alloc::{boxed::Box, vec::Vec};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use crate::{
+    expression::{And, Computed, Expression, FieldName, Literal, Not, Operation, Or, Reference},
+    schema::Value,
+};
+
+// Rebuilds an expression tree by value. Each `transform_*` method defaults to
+// transforming its children through the matching `fold_*` function; overrides
+// may return a different node kind, e.g. replacing an operation with an And.
+pub trait ExpressionTransformer {
+    fn transform_expression(&mut self, expression: Expression) -> Expression {
+        fold_expression(self, expression)
+    }
+
+    fn transform_and(&mut self, and: And) -> Expression {
+        fold_and(self, and)
+    }
+
+    fn transform_or(&mut self, or: Or) -> Expression {
+        fold_or(self, or)
+    }
+
+    fn transform_not(&mut self, not: Not) -> Expression {
+        fold_not(self, not)
+    }
+
+    fn transform_operation(&mut self, operation: Operation) -> Expression {
+        fold_operation(self, operation)
+    }
+
+    fn transform_reference(&mut self, reference: Reference) -> Expression {
+        Expression::Reference(reference)
+    }
+
+    fn transform_literal(&mut self, literal: Literal) -> Literal {
+        fold_literal(self, literal)
+    }
+
+    fn transform_field(&mut self, field_name: FieldName) -> Literal {
+        Literal::LiteralField(field_name)
+    }
+
+    fn transform_value(&mut self, value: Value) -> Literal {
+        Literal::LiteralValue(value)
+    }
+}
+
+pub fn fold_expression<T: ExpressionTransformer + ?Sized>(
+    transformer: &mut T,
+    expression: Expression,
+) -> Expression {
+    match expression {
+        Expression::And(and) => transformer.transform_and(and),
+        Expression::Or(or) => transformer.transform_or(or),
+        Expression::Not(not) => transformer.transform_not(not),
+        Expression::Operation(operation) => transformer.transform_operation(operation),
+        Expression::Reference(reference) => transformer.transform_reference(reference),
+    }
+}
+
+pub fn fold_and<T: ExpressionTransformer + ?Sized>(transformer: &mut T, and: And) -> Expression {
+    Expression::And(And::new(fold_all(transformer, and.into_subexpressions())))
+}
+
+pub fn fold_or<T: ExpressionTransformer + ?Sized>(transformer: &mut T, or: Or) -> Expression {
+    Expression::Or(Or::new(fold_all(transformer, or.into_subexpressions())))
+}
+
+fn fold_all<T: ExpressionTransformer + ?Sized>(
+    transformer: &mut T,
+    subexpressions: Vec<Expression>,
+) -> Vec<Expression> {
+    subexpressions
+        .into_iter()
+        .map(|subexpression| transformer.transform_expression(subexpression))
+        .collect()
+}
+
+pub fn fold_not<T: ExpressionTransformer + ?Sized>(transformer: &mut T, not: Not) -> Expression {
+    Expression::Not(Not::new(
+        transformer.transform_expression(not.into_subexpression()),
+    ))
+}
+
+pub fn fold_operation<T: ExpressionTransformer + ?Sized>(
+    transformer: &mut T,
+    operation: Operation,
+) -> Expression {
+    let lhs = transformer.transform_literal(operation.lhs);
+    let rhs = transformer.transform_literal(operation.rhs);
+
+    Expression::Operation(Operation::new(lhs, operation.op, rhs))
+}
+
+pub fn fold_literal<T: ExpressionTransformer + ?Sized>(
+    transformer: &mut T,
+    literal: Literal,
+) -> Literal {
+    match literal {
+        Literal::LiteralValue(value) => transformer.transform_value(value),
+        Literal::LiteralField(field_name) => transformer.transform_field(field_name),
+        // Renames carry over to the age. A field replaced by a value keeps its
+        // age, the age of a value depends on the time of execution.
+        #[cfg(feature = "chrono")]
+        Literal::LiteralAge(field_name) => match transformer.transform_field(field_name.clone()) {
+            Literal::LiteralField(field_name) => Literal::LiteralAge(field_name),
+            _ => Literal::LiteralAge(field_name),
+        },
+        Literal::LiteralComputed(computed) => {
+            let computed = match *computed {
+                Computed::If(condition, then, otherwise) => {
+                    Computed::If(transformer.transform_expression(condition), then, otherwise)
+                }
+                computed => computed,
+            };
+
+            Literal::LiteralComputed(Box::new(
+                computed.map_operands(|operand| transformer.transform_literal(operand)),
+            ))
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+pub(crate) struct RenameFields<'a>(pub &'a HashMap<String, String>);
+
+#[cfg(feature = "std")]
+impl ExpressionTransformer for RenameFields<'_> {
+    fn transform_field(&mut self, field_name: FieldName) -> Literal {
+        match self.0.get(field_name.as_str()) {
+            Some(renamed) => Literal::LiteralField(renamed.into()),
+            None => Literal::LiteralField(field_name),
+        }
+    }
+}
+
+pub(crate) struct BindField<'a>(pub &'a str, pub &'a Value);
+
+impl ExpressionTransformer for BindField<'_> {
+    fn transform_field(&mut self, field_name: FieldName) -> Literal {
+        if field_name == self.0 {
+            Literal::LiteralValue(self.1.clone())
+        } else {
+            Literal::LiteralField(field_name)
+        }
+    }
+}
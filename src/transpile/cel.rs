@@ -0,0 +1,430 @@
+use core::str;
+
+use pom::parser::*;
+use thiserror::Error;
+
+#[cfg(feature = "chrono")]
+use crate::parser::datetime;
+use crate::{
+    expression::{And, Expression, Literal, Not, Operation, Operator, Or},
+    parser::{ExpressionParser, identifier, keyword, number, space},
+    schema::{Type, Value},
+};
+
+use super::{FieldMapping, TranspileError};
+
+pub struct CelTranspiler {
+    mapping: FieldMapping,
+}
+
+impl CelTranspiler {
+    pub fn new(mapping: FieldMapping) -> Self {
+        Self { mapping }
+    }
+
+    pub fn transpile(&self, expression: &Expression) -> Result<String, TranspileError> {
+        Ok(match expression {
+            Expression::And(and) => format!(
+                "({})",
+                self.transpile_all(and.get_subexpressions())?.join(" && ")
+            ),
+            Expression::Or(or) => format!(
+                "({})",
+                self.transpile_all(or.get_subexpressions())?.join(" || ")
+            ),
+            Expression::Not(not) => format!("!({})", self.transpile(not.get_subexpression())?),
+            Expression::Operation(operation) => self.transpile_operation(operation)?,
+            Expression::Reference(reference) => {
+                return Err(TranspileError::UnresolvedReferenceError(
+                    reference.get_rule_name().to_string(),
+                ));
+            }
+        })
+    }
+
+    fn transpile_all(&self, subexpressions: &[Expression]) -> Result<Vec<String>, TranspileError> {
+        subexpressions.iter().map(|e| self.transpile(e)).collect()
+    }
+
+    fn operand(&self, literal: &Literal) -> Result<String, TranspileError> {
+        match literal {
+            Literal::LiteralValue(value) => Ok(format_value(value)),
+            Literal::LiteralField(field_name) => self
+                .mapping
+                .get_field(field_name)
+                .map(|field| field.name.clone())
+                .ok_or_else(|| TranspileError::UnmappedFieldError(field_name.to_string())),
+            #[cfg(feature = "chrono")]
+            Literal::LiteralAge(_) => Err(TranspileError::unsupported_literal(literal)),
+            Literal::LiteralComputed(_) => Err(TranspileError::unsupported_literal(literal)),
+        }
+    }
+
+    fn transpile_operation(&self, operation: &Operation) -> Result<String, TranspileError> {
+        let lhs_type = self.mapping.literal_type(&operation.lhs)?;
+        let rhs_type = self.mapping.literal_type(&operation.rhs)?;
+        let lhs = self.operand(&operation.lhs)?;
+        let rhs = self.operand(&operation.rhs)?;
+
+        let op = match operation.op {
+            Operator::Eq => "==",
+            Operator::Ne => "!=",
+            Operator::Gt => ">",
+            Operator::Gte => ">=",
+            Operator::Lt => "<",
+            Operator::Lte => "<=",
+            Operator::In => {
+                return match (lhs_type, rhs_type) {
+                    (Type::String, Type::String) => Ok(format!("{}.contains({})", rhs, lhs)),
+                    #[cfg(feature = "regex")]
+                    (Type::Regex, Type::String) => Ok(format!("{}.matches({})", rhs, lhs)),
+                    #[cfg(feature = "regex")]
+                    (Type::Regex, Type::StringList) => {
+                        Ok(format!("{}.exists(s, s.matches({}))", rhs, lhs))
+                    }
+                    #[cfg(feature = "chrono")]
+                    (Type::DateTime, Type::DateTimeList) => match &operation.rhs {
+                        Literal::LiteralValue(Value::DateTimeList(range)) if range.len() == 2 => {
+                            Ok(format!(
+                                "({} >= {} && {} < {})",
+                                lhs,
+                                format_value(&Value::DateTime(range[0])),
+                                lhs,
+                                format_value(&Value::DateTime(range[1]))
+                            ))
+                        }
+                        _ => Err(TranspileError::unsupported(operation)),
+                    },
+                    (Type::String, Type::StringList)
+                    | (Type::Number, Type::NumberList)
+                    | (Type::Boolean, Type::BooleanList)
+                    | (Type::Null, _) => Ok(format!("{} in {}", lhs, rhs)),
+                    #[cfg(feature = "raw")]
+                    (Type::Raw, Type::RawList) => Ok(format!("{} in {}", lhs, rhs)),
+                    _ => Err(TranspileError::unsupported(operation)),
+                };
+            }
+        };
+
+        Ok(format!("{} {} {}", lhs, op, rhs))
+    }
+}
+
+fn format_string(val: &str) -> String {
+    let mut out = String::with_capacity(val.len() + 2);
+    out.push('"');
+    for c in val.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() && (c as u32) < 0x100 => {
+                out.push_str(&format!("\\x{:02x}", c as u32))
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+
+    out
+}
+
+#[cfg(feature = "raw")]
+fn format_bytes(val: &[u8]) -> String {
+    format!(
+        "b\"{}\"",
+        val.iter()
+            .map(|byte| format!("\\x{:02x}", byte))
+            .collect::<String>()
+    )
+}
+
+fn format_number(val: f64) -> String {
+    // CEL distinguishes int and double literals; integral values that fit are
+    // written as ints, everything else in a form that always parses as a double.
+    if val.fract() == 0.0 && val.abs() < 9_007_199_254_740_992.0 {
+        format!("{}", val)
+    } else {
+        format!("{:?}", val)
+    }
+}
+
+fn format_list<T>(items: &[T], format_item: impl Fn(&T) -> String) -> String {
+    format!(
+        "[{}]",
+        items
+            .iter()
+            .map(format_item)
+            .collect::<Vec<String>>()
+            .join(", ")
+    )
+}
+
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::String(val) => format_string(val),
+        #[cfg(feature = "regex")]
+        Value::Regex(val) => format_string(val),
+        Value::Number(val) => format_number(*val),
+        Value::Boolean(val) => format!("{}", val),
+        #[cfg(feature = "raw")]
+        Value::Raw(val) => format_bytes(val),
+        #[cfg(feature = "chrono")]
+        Value::DateTime(val) => format!(
+            "timestamp({})",
+            format_string(&val.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true))
+        ),
+        Value::StringList(items) => format_list(items, |val| format_string(val)),
+        Value::NumberList(items) => format_list(items, |val| format_number(*val)),
+        Value::BooleanList(items) => format_list(items, |val| format!("{}", val)),
+        #[cfg(feature = "raw")]
+        Value::RawList(items) => format_list(items, |val| format_bytes(val)),
+        #[cfg(feature = "chrono")]
+        Value::DateTimeList(items) => {
+            format_list(items, |val| format_value(&Value::DateTime(*val)))
+        }
+        Value::Null => String::from("null"),
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum CelError {
+    #[error("{0}")]
+    ParsingError(#[from] pom::Error),
+    #[error("Unsupported CEL construct: {0}")]
+    UnsupportedError(String),
+}
+
+impl ExpressionParser {
+    pub fn from_cel(input: &str) -> Result<Expression, CelError> {
+        let operand = cel_parser().parse(input.as_bytes())?;
+
+        into_expression(operand)
+    }
+}
+
+// Intermediate parse tree; method calls and bare boolean fields only become
+// operations once it is known they are used as conditions.
+enum Node {
+    And(Vec<Node>),
+    Or(Vec<Node>),
+    Not(Box<Node>),
+    Compare(Literal, Operator, Literal),
+    Call(Literal, String, Literal),
+    Operand(Literal),
+}
+
+fn into_expression(node: Node) -> Result<Expression, CelError> {
+    Ok(match node {
+        Node::And(nodes) => Expression::And(And::new(into_expressions(nodes)?)),
+        Node::Or(nodes) => Expression::Or(Or::new(into_expressions(nodes)?)),
+        Node::Not(node) => Expression::Not(Not::new(into_expression(*node)?)),
+        Node::Compare(lhs, op, rhs) => Expression::Operation(Operation::new(lhs, op, rhs)),
+        Node::Call(receiver, method, arg) => {
+            let lhs = match (method.as_str(), arg) {
+                ("contains", arg) => arg,
+                #[cfg(feature = "regex")]
+                ("matches", Literal::LiteralValue(Value::String(pattern))) => {
+                    Literal::LiteralValue(Value::Regex(pattern))
+                }
+                #[cfg(feature = "regex")]
+                ("startsWith", Literal::LiteralValue(Value::String(prefix))) => {
+                    Literal::LiteralValue(Value::Regex(format!("^{}", regex::escape(&prefix))))
+                }
+                #[cfg(feature = "regex")]
+                ("endsWith", Literal::LiteralValue(Value::String(suffix))) => {
+                    Literal::LiteralValue(Value::Regex(format!("{}$", regex::escape(&suffix))))
+                }
+                (method, _) => return Err(CelError::UnsupportedError(format!("{}()", method))),
+            };
+
+            Expression::Operation(Operation::new(lhs, Operator::In, receiver))
+        }
+        Node::Operand(field @ Literal::LiteralField(_)) => Expression::Operation(Operation::new(
+            field,
+            Operator::Eq,
+            Literal::LiteralValue(Value::Boolean(true)),
+        )),
+        Node::Operand(Literal::LiteralValue(value)) => {
+            return Err(CelError::UnsupportedError(format!(
+                "{} used as a condition",
+                value.get_type_name()
+            )));
+        }
+        #[cfg(feature = "chrono")]
+        Node::Operand(Literal::LiteralAge(_)) => {
+            return Err(CelError::UnsupportedError(String::from(
+                "age_of() used as a condition",
+            )));
+        }
+        Node::Operand(Literal::LiteralComputed(_)) => {
+            return Err(CelError::UnsupportedError(String::from(
+                "computed value used as a condition",
+            )));
+        }
+    })
+}
+
+fn into_expressions(nodes: Vec<Node>) -> Result<Vec<Expression>, CelError> {
+    nodes.into_iter().map(into_expression).collect()
+}
+
+fn hex_byte<'a>() -> Parser<'a, u8, u8> {
+    (one_of(b"0123456789abcdefABCDEF").repeat(2))
+        .convert(|digits| u8::from_str_radix(str::from_utf8(&digits).unwrap(), 16))
+}
+
+fn quoted<'a>(quote: u8, special: &'static [u8]) -> Parser<'a, u8, Vec<u8>> {
+    let escape = sym(b'\\')
+        * (sym(b'\\')
+            | sym(b'"')
+            | sym(b'\'')
+            | sym(b'n').map(|_| b'\n')
+            | sym(b'r').map(|_| b'\r')
+            | sym(b't').map(|_| b'\t')
+            | (sym(b'x') * hex_byte()));
+
+    sym(quote) * (none_of(special) | escape).repeat(0..) - sym(quote)
+}
+
+fn string<'a>() -> Parser<'a, u8, String> {
+    (quoted(b'"', b"\\\"") | quoted(b'\'', b"\\'")).convert(String::from_utf8)
+}
+
+#[cfg(feature = "raw")]
+fn bytes<'a>() -> Parser<'a, u8, Vec<u8>> {
+    one_of(b"bB") * (quoted(b'"', b"\\\"") | quoted(b'\'', b"\\'"))
+}
+
+fn list<'a, T: 'a>(item: fn() -> Parser<'a, u8, T>) -> Parser<'a, u8, Vec<T>> {
+    (sym(b'[') + space()) * list_items(item) - (space() + sym(b']'))
+}
+
+fn list_items<'a, T: 'a>(item: fn() -> Parser<'a, u8, T>) -> Parser<'a, u8, Vec<T>> {
+    (item() + ((space() + sym(b',') + space()) * item()).repeat(0..)).map(|(first, mut rest)| {
+        rest.insert(0, first);
+
+        rest
+    })
+}
+
+#[cfg(feature = "chrono")]
+fn timestamp<'a>() -> Parser<'a, u8, chrono::DateTime<chrono::Utc>> {
+    (seq(b"timestamp") + space() + sym(b'(') + space() + sym(b'"')) * datetime()
+        - (sym(b'"') + space() + sym(b')'))
+}
+
+fn value<'a>() -> Parser<'a, u8, Value> {
+    let mut parser = keyword(b"null").map(|_| Value::Null)
+        | keyword(b"true").map(|_| Value::Boolean(true))
+        | keyword(b"false").map(|_| Value::Boolean(false));
+    #[cfg(feature = "chrono")]
+    {
+        parser = parser | timestamp().map(Value::DateTime);
+    }
+    #[cfg(feature = "raw")]
+    {
+        parser = parser | bytes().map(Value::Raw);
+    }
+    parser = parser
+        | string().map(Value::String)
+        | number().map(Value::Number)
+        | list(string).map(Value::StringList)
+        | list(number).map(Value::NumberList);
+    #[cfg(feature = "raw")]
+    {
+        parser = parser | list(bytes).map(Value::RawList);
+    }
+    #[cfg(feature = "chrono")]
+    {
+        parser = parser | list(timestamp).map(Value::DateTimeList);
+    }
+
+    parser
+}
+
+fn path<'a>() -> Parser<'a, u8, Vec<String>> {
+    (identifier() + (sym(b'.') * identifier()).repeat(0..)).map(|(first, mut rest)| {
+        rest.insert(0, first);
+
+        rest
+    })
+}
+
+fn operand<'a>() -> Parser<'a, u8, Node> {
+    let call = (sym(b'(') + space()) * value() - (space() + sym(b')'));
+
+    value().map(|value| Node::Operand(Literal::LiteralValue(value)))
+        | (path() + call.opt()).convert(|(mut path, arg)| match arg {
+            // `a.b.contains("x")`: the last path segment is the method name.
+            Some(arg) if path.len() > 1 => {
+                let method = path.pop().unwrap();
+
+                Ok(Node::Call(
+                    Literal::LiteralField(path.join(":").into()),
+                    method,
+                    Literal::LiteralValue(arg),
+                ))
+            }
+            Some(_) => Err("unsupported function call"),
+            None => Ok(Node::Operand(Literal::LiteralField(path.join(":").into()))),
+        })
+}
+
+fn operator<'a>() -> Parser<'a, u8, Operator> {
+    seq(b"==").map(|_| Operator::Eq)
+        | seq(b"!=").map(|_| Operator::Ne)
+        | seq(b">=").map(|_| Operator::Gte)
+        | seq(b"<=").map(|_| Operator::Lte)
+        | seq(b">").map(|_| Operator::Gt)
+        | seq(b"<").map(|_| Operator::Lt)
+        | keyword(b"in").map(|_| Operator::In)
+}
+
+fn comparison<'a>() -> Parser<'a, u8, Node> {
+    (operand() + (space() * operator() + space() * operand()).opt()).convert(|(lhs, rest)| {
+        match (lhs, rest) {
+            (lhs, None) => Ok(lhs),
+            (Node::Operand(lhs), Some((op, Node::Operand(rhs)))) => Ok(Node::Compare(lhs, op, rhs)),
+            _ => Err("method calls cannot be compared"),
+        }
+    })
+}
+
+fn unary<'a>() -> Parser<'a, u8, Node> {
+    (sym(b'!') * space() * call(unary)).map(|node| Node::Not(Box::new(node)))
+        | (sym(b'(') * space() * call(or) - space() - sym(b')'))
+        | comparison()
+}
+
+fn and<'a>() -> Parser<'a, u8, Node> {
+    (unary() + (space() * seq(b"&&") * space() * unary()).repeat(0..)).map(|(first, mut rest)| {
+        match rest.len() {
+            0 => first,
+            _ => {
+                rest.insert(0, first);
+
+                Node::And(rest)
+            }
+        }
+    })
+}
+
+fn or<'a>() -> Parser<'a, u8, Node> {
+    (and() + (space() * seq(b"||") * space() * and()).repeat(0..)).map(|(first, mut rest)| {
+        match rest.len() {
+            0 => first,
+            _ => {
+                rest.insert(0, first);
+
+                Node::Or(rest)
+            }
+        }
+    })
+}
+
+fn cel_parser<'a>() -> Parser<'a, u8, Node> {
+    space() * or() - space() - end()
+}
@@ -0,0 +1,299 @@
+use serde_json::{Value as Json, json};
+
+use crate::{
+    expression::{Expression, Literal, Operation, Operator},
+    schema::{Type, Value},
+};
+
+use super::FieldMapping;
+
+#[derive(Debug)]
+pub struct ElasticsearchQuery {
+    pub query: Json,
+    // Top-level conjuncts that could not be expressed in the query DSL. The query
+    // matches a superset of the expression, so hits must still be filtered
+    // through the engine against these.
+    pub unsupported: Vec<Expression>,
+}
+
+impl ElasticsearchQuery {
+    pub fn is_exact(&self) -> bool {
+        self.unsupported.is_empty()
+    }
+}
+
+pub struct ElasticsearchTranspiler {
+    mapping: FieldMapping,
+}
+
+impl ElasticsearchTranspiler {
+    pub fn new(mapping: FieldMapping) -> Self {
+        Self { mapping }
+    }
+
+    pub fn transpile(&self, expression: &Expression) -> ElasticsearchQuery {
+        let conjuncts = match expression {
+            Expression::And(and) => and.get_subexpressions().iter().collect(),
+            expression => vec![expression],
+        };
+
+        let mut filters = Vec::new();
+        let mut unsupported = Vec::new();
+        for conjunct in conjuncts {
+            match self.translate(conjunct) {
+                Some(filter) => filters.push(filter),
+                None => unsupported.push(conjunct.clone()),
+            }
+        }
+
+        let query = match filters.len() {
+            0 => json!({ "match_all": {} }),
+            _ => json!({ "bool": { "filter": filters } }),
+        };
+
+        ElasticsearchQuery { query, unsupported }
+    }
+
+    fn translate(&self, expression: &Expression) -> Option<Json> {
+        Some(match expression {
+            Expression::And(and) => {
+                json!({ "bool": { "filter": self.translate_all(and.get_subexpressions())? } })
+            }
+            Expression::Or(or) => json!({
+                "bool": {
+                    "should": self.translate_all(or.get_subexpressions())?,
+                    "minimum_should_match": 1,
+                }
+            }),
+            Expression::Not(not) => {
+                json!({ "bool": { "must_not": self.translate(not.get_subexpression())? } })
+            }
+            Expression::Operation(operation) => self.translate_operation(operation)?,
+            Expression::Reference(_) => return None,
+        })
+    }
+
+    fn translate_all(&self, subexpressions: &[Expression]) -> Option<Vec<Json>> {
+        subexpressions.iter().map(|e| self.translate(e)).collect()
+    }
+
+    fn translate_operation(&self, operation: &Operation) -> Option<Json> {
+        // Only comparisons between a mapped field and a literal can be pushed down.
+        match (&operation.lhs, &operation.rhs) {
+            (Literal::LiteralField(field_name), Literal::LiteralValue(value)) => {
+                let field = self.mapping.get_field(field_name)?;
+
+                translate_comparison(&field.name, field.field_type, &operation.op, value)
+            }
+            (Literal::LiteralValue(value), Literal::LiteralField(field_name)) => {
+                let field = self.mapping.get_field(field_name)?;
+                let path = &field.name;
+
+                let mirrored = match operation.op {
+                    Operator::Eq => Operator::Eq,
+                    Operator::Ne => Operator::Ne,
+                    Operator::Gt => Operator::Lt,
+                    Operator::Gte => Operator::Lte,
+                    Operator::Lt => Operator::Gt,
+                    Operator::Lte => Operator::Gte,
+                    Operator::In => {
+                        return match (value, field.field_type) {
+                            (Value::String(needle), Type::String) => Some(json!({
+                                "wildcard": { path: { "value": format!("*{}*", escape_wildcard(needle)) } }
+                            })),
+                            (Value::Regex(pattern), Type::String | Type::StringList) => {
+                                Some(json!({
+                                    "regexp": { path: { "value": to_lucene_regex(pattern)? } }
+                                }))
+                            }
+                            // A term query on an array field matches if any element matches.
+                            (Value::String(_), Type::StringList)
+                            | (Value::Number(_), Type::NumberList)
+                            | (Value::Boolean(_), Type::BooleanList) => {
+                                Some(json!({ "term": { path: to_json(value)? } }))
+                            }
+                            _ => None,
+                        };
+                    }
+                };
+
+                translate_comparison(path, field.field_type, &mirrored, value)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn translate_comparison(
+    path: &str,
+    field_type: Type,
+    op: &Operator,
+    value: &Value,
+) -> Option<Json> {
+    if value.is_null() {
+        return Some(match op {
+            Operator::Eq => json!({ "bool": { "must_not": { "exists": { "field": path } } } }),
+            Operator::Ne => json!({ "exists": { "field": path } }),
+            _ => json!({ "match_none": {} }),
+        });
+    }
+
+    Some(match op {
+        Operator::Eq => json!({ "term": { path: to_json(value)? } }),
+        Operator::Ne => json!({ "bool": { "must_not": { "term": { path: to_json(value)? } } } }),
+        Operator::Gt => json!({ "range": { path: { "gt": to_json(value)? } } }),
+        Operator::Gte => json!({ "range": { path: { "gte": to_json(value)? } } }),
+        Operator::Lt => json!({ "range": { path: { "lt": to_json(value)? } } }),
+        Operator::Lte => json!({ "range": { path: { "lte": to_json(value)? } } }),
+        Operator::In => match (field_type, value) {
+            (Type::DateTime, Value::DateTimeList(range)) if range.len() == 2 => json!({
+                "range": { path: {
+                    "gte": to_json(&Value::DateTime(range[0]))?,
+                    "lt": to_json(&Value::DateTime(range[1]))?,
+                } }
+            }),
+            (Type::String, Value::StringList(_))
+            | (Type::Number, Value::NumberList(_))
+            | (Type::Boolean, Value::BooleanList(_)) => {
+                json!({ "terms": { path: to_json(value)? } })
+            }
+            _ => return None,
+        },
+    })
+}
+
+fn escape_wildcard(val: &str) -> String {
+    let mut out = String::with_capacity(val.len());
+    for c in val.chars() {
+        if matches!(c, '*' | '?' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+
+    out
+}
+
+// Lucene regular expressions are implicitly anchored and use a different
+// syntax for escapes and character classes, so only patterns built from the
+// shared subset are translated.
+fn to_lucene_regex(pattern: &str) -> Option<String> {
+    let (anchored_start, pattern) = match pattern.strip_prefix('^') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+    let (anchored_end, pattern) = match pattern.strip_suffix('$') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+
+    if pattern.chars().any(|c| {
+        matches!(
+            c,
+            '\\' | '^' | '$' | '{' | '}' | '#' | '@' | '&' | '<' | '>' | '~' | '"'
+        )
+    }) {
+        return None;
+    }
+
+    Some(format!(
+        "{}{}{}",
+        if anchored_start { "" } else { ".*" },
+        pattern,
+        if anchored_end { "" } else { ".*" }
+    ))
+}
+
+fn to_json(value: &Value) -> Option<Json> {
+    Some(match value {
+        Value::String(val) => json!(val),
+        Value::Number(val) => json!(val),
+        Value::Boolean(val) => json!(val),
+        Value::DateTime(val) => json!(val.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)),
+        Value::StringList(items) => json!(items),
+        Value::NumberList(items) => json!(items),
+        Value::BooleanList(items) => json!(items),
+        Value::DateTimeList(items) => Json::Array(
+            items
+                .iter()
+                .map(|val| to_json(&Value::DateTime(*val)))
+                .collect::<Option<Vec<Json>>>()?,
+        ),
+        Value::Regex(_) | Value::Raw(_) | Value::RawList(_) | Value::Null => return None,
+    })
+}
+
+// Missing and null fields never match a comparison in the engine, so these pin
+// down that the queries only select such documents where the engine would.
+#[cfg(test)]
+mod tests {
+    use crate::Parser;
+
+    use super::*;
+
+    // The single filter the expression translates to.
+    fn translate(input: &str) -> Json {
+        let mapping = FieldMapping::new()
+            .with_field("name", "name", Type::String)
+            .with_field("age", "profile.age", Type::Number);
+        let expression = Parser::parse(input).unwrap();
+        let query = ElasticsearchTranspiler::new(mapping).transpile(&expression);
+        assert!(query.is_exact());
+
+        query.query["bool"]["filter"][0].clone()
+    }
+
+    #[test]
+    fn negation_matches_null_fields() {
+        assert_eq!(
+            translate(r#"!(name == "x")"#),
+            json!({ "bool": { "must_not": { "term": { "name": "x" } } } })
+        );
+        assert_eq!(
+            translate("!(age > 3)"),
+            json!({ "bool": { "must_not": { "range": { "profile.age": { "gt": 3.0 } } } } })
+        );
+    }
+
+    #[test]
+    fn inequality_matches_null_fields() {
+        // must_not also matches documents where the field is null or missing.
+        assert_eq!(
+            translate(r#"name != "x""#),
+            json!({ "bool": { "must_not": { "term": { "name": "x" } } } })
+        );
+        assert_eq!(
+            translate(r#""x" != name"#),
+            json!({ "bool": { "must_not": { "term": { "name": "x" } } } })
+        );
+    }
+
+    #[test]
+    fn ordering_skips_null_fields() {
+        assert_eq!(
+            translate("age > 3"),
+            json!({ "range": { "profile.age": { "gt": 3.0 } } })
+        );
+        assert_eq!(
+            translate("3 >= age"),
+            json!({ "range": { "profile.age": { "lte": 3.0 } } })
+        );
+    }
+
+    #[test]
+    fn null_literals_are_rewritten() {
+        assert_eq!(
+            translate("name == null"),
+            json!({ "bool": { "must_not": { "exists": { "field": "name" } } } })
+        );
+        assert_eq!(
+            translate("null != name"),
+            json!({ "exists": { "field": "name" } })
+        );
+        assert_eq!(translate("age > null"), json!({ "match_none": {} }));
+        assert_eq!(
+            translate("!(age <= null)"),
+            json!({ "bool": { "must_not": { "match_none": {} } } })
+        );
+    }
+}
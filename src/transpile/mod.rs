@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::{
+    expression::{Literal, Operation},
+    schema::Type,
+    serialize::Serialize,
+};
+
+pub mod cel;
+#[cfg(feature = "elasticsearch")]
+pub mod elasticsearch;
+#[cfg(feature = "mongo")]
+pub mod mongo;
+#[cfg(feature = "polars")]
+pub mod polars;
+pub mod sql;
+
+#[derive(Error, Debug)]
+pub enum TranspileError {
+    #[error("The field '{0}' has no mapping")]
+    UnmappedFieldError(String),
+    #[error("Cannot translate '{0}'")]
+    UnsupportedOperationError(String),
+    #[error("The reference to rule '{0}' must be resolved before translating")]
+    UnresolvedReferenceError(String),
+}
+
+impl TranspileError {
+    pub(crate) fn unsupported(operation: &Operation) -> Self {
+        TranspileError::UnsupportedOperationError(Serialize::fmt(operation))
+    }
+
+    // `age_of()` depends on the time the engine executes at, which the
+    // targets have no equivalent of. Computed operands aren't translated
+    // either.
+    pub(crate) fn unsupported_literal(literal: &Literal) -> Self {
+        TranspileError::UnsupportedOperationError(Serialize::fmt(literal))
+    }
+}
+
+pub struct MappedField {
+    pub name: String,
+    pub field_type: Type,
+}
+
+pub struct FieldMapping {
+    fields: HashMap<String, MappedField>,
+}
+
+impl Default for FieldMapping {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FieldMapping {
+    pub fn new() -> Self {
+        Self {
+            fields: HashMap::new(),
+        }
+    }
+
+    pub fn with_field(mut self, field_name: &str, target_name: &str, field_type: Type) -> Self {
+        self.fields.insert(
+            field_name.to_string(),
+            MappedField {
+                name: target_name.to_string(),
+                field_type,
+            },
+        );
+
+        self
+    }
+
+    pub fn get_field(&self, field_name: &str) -> Option<&MappedField> {
+        self.fields.get(field_name)
+    }
+
+    pub(crate) fn literal_type(&self, literal: &Literal) -> Result<Type, TranspileError> {
+        match literal {
+            Literal::LiteralValue(value) => Ok(value.get_type()),
+            Literal::LiteralField(field_name) => self
+                .get_field(field_name)
+                .map(|field| field.field_type)
+                .ok_or_else(|| TranspileError::UnmappedFieldError(field_name.to_string())),
+            #[cfg(feature = "chrono")]
+            Literal::LiteralAge(_) => Err(TranspileError::unsupported_literal(literal)),
+            Literal::LiteralComputed(_) => Err(TranspileError::unsupported_literal(literal)),
+        }
+    }
+}
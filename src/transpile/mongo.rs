@@ -0,0 +1,251 @@
+use bson::{Bson, Document, doc, spec::BinarySubtype};
+
+use crate::{
+    expression::{Expression, Literal, Operation, Operator},
+    schema::{Type, Value},
+};
+
+use super::{FieldMapping, TranspileError};
+
+pub struct MongoTranspiler {
+    mapping: FieldMapping,
+}
+
+impl MongoTranspiler {
+    pub fn new(mapping: FieldMapping) -> Self {
+        Self { mapping }
+    }
+
+    pub fn transpile(&self, expression: &Expression) -> Result<Document, TranspileError> {
+        Ok(match expression {
+            Expression::And(and) => doc! { "$and": self.transpile_all(and.get_subexpressions())? },
+            Expression::Or(or) => doc! { "$or": self.transpile_all(or.get_subexpressions())? },
+            // $nor matches documents where the inner filter does not, including
+            // documents where the field is missing, like the engine's null handling.
+            Expression::Not(not) => doc! { "$nor": [self.transpile(not.get_subexpression())?] },
+            Expression::Operation(operation) => self.transpile_operation(operation)?,
+            Expression::Reference(reference) => {
+                return Err(TranspileError::UnresolvedReferenceError(
+                    reference.get_rule_name().to_string(),
+                ));
+            }
+        })
+    }
+
+    fn transpile_all(
+        &self,
+        subexpressions: &[Expression],
+    ) -> Result<Vec<Document>, TranspileError> {
+        subexpressions.iter().map(|e| self.transpile(e)).collect()
+    }
+
+    fn path(&self, field_name: &str) -> Result<String, TranspileError> {
+        self.mapping
+            .get_field(field_name)
+            .map(|field| field.name.clone())
+            .ok_or_else(|| TranspileError::UnmappedFieldError(field_name.to_string()))
+    }
+
+    fn transpile_operation(&self, operation: &Operation) -> Result<Document, TranspileError> {
+        let lhs_type = self.mapping.literal_type(&operation.lhs)?;
+        let rhs_type = self.mapping.literal_type(&operation.rhs)?;
+
+        if lhs_type.is_null() || rhs_type.is_null() {
+            let other = if lhs_type.is_null() {
+                &operation.rhs
+            } else {
+                &operation.lhs
+            };
+
+            return Ok(match (other, &operation.op) {
+                (Literal::LiteralField(field_name), Operator::Eq) => {
+                    doc! { self.path(field_name)?: Bson::Null }
+                }
+                (Literal::LiteralField(field_name), Operator::Ne) => {
+                    doc! { self.path(field_name)?: { "$ne": Bson::Null } }
+                }
+                (Literal::LiteralValue(value), Operator::Eq) => doc! { "$expr": value.is_null() },
+                (Literal::LiteralValue(value), Operator::Ne) => doc! { "$expr": !value.is_null() },
+                _ => doc! { "$expr": false },
+            });
+        }
+
+        match (&operation.lhs, &operation.rhs) {
+            (Literal::LiteralField(field_name), Literal::LiteralValue(value)) => {
+                let path = self.path(field_name)?;
+                let value = to_bson(value);
+
+                Ok(match operation.op {
+                    Operator::Eq => doc! { path: value },
+                    Operator::Ne => doc! { path: { "$ne": value } },
+                    Operator::Gt => doc! { path: { "$gt": value } },
+                    Operator::Gte => doc! { path: { "$gte": value } },
+                    Operator::Lt => doc! { path: { "$lt": value } },
+                    Operator::Lte => doc! { path: { "$lte": value } },
+                    Operator::In => match (lhs_type, &operation.rhs) {
+                        (Type::String, Literal::LiteralValue(Value::String(haystack))) => {
+                            // A field contained in a literal string has no query operator.
+                            doc! { "$expr": { "$gte": [{ "$indexOfCP": [haystack, format!("${}", path)] }, 0] } }
+                        }
+                        (Type::DateTime, Literal::LiteralValue(Value::DateTimeList(range)))
+                            if range.len() == 2 =>
+                        {
+                            doc! { path: { "$gte": to_bson(&Value::DateTime(range[0])), "$lt": to_bson(&Value::DateTime(range[1])) } }
+                        }
+                        (
+                            Type::String | Type::Number | Type::Boolean | Type::Raw,
+                            Literal::LiteralValue(
+                                Value::StringList(_)
+                                | Value::NumberList(_)
+                                | Value::BooleanList(_)
+                                | Value::RawList(_),
+                            ),
+                        ) => doc! { path: { "$in": value } },
+                        _ => return Err(TranspileError::unsupported(operation)),
+                    },
+                })
+            }
+            (Literal::LiteralValue(value), Literal::LiteralField(field_name)) => {
+                let path = self.path(field_name)?;
+
+                Ok(match operation.op {
+                    Operator::Eq => doc! { path: to_bson(value) },
+                    Operator::Ne => doc! { path: { "$ne": to_bson(value) } },
+                    Operator::Gt => doc! { path: { "$lt": to_bson(value) } },
+                    Operator::Gte => doc! { path: { "$lte": to_bson(value) } },
+                    Operator::Lt => doc! { path: { "$gt": to_bson(value) } },
+                    Operator::Lte => doc! { path: { "$gte": to_bson(value) } },
+                    Operator::In => match (value, rhs_type) {
+                        (Value::Regex(pattern), Type::String | Type::StringList) => {
+                            doc! { path: { "$regex": pattern } }
+                        }
+                        (Value::String(needle), Type::String) => {
+                            doc! { path: { "$regex": regex::escape(needle) } }
+                        }
+                        // Matching a scalar against an array field tests membership.
+                        (Value::String(_), Type::StringList)
+                        | (Value::Number(_), Type::NumberList)
+                        | (Value::Boolean(_), Type::BooleanList)
+                        | (Value::Raw(_), Type::RawList) => doc! { path: to_bson(value) },
+                        _ => return Err(TranspileError::unsupported(operation)),
+                    },
+                })
+            }
+            _ => {
+                let op = match operation.op {
+                    Operator::Eq => "$eq",
+                    Operator::Ne => "$ne",
+                    Operator::Gt => "$gt",
+                    Operator::Gte => "$gte",
+                    Operator::Lt => "$lt",
+                    Operator::Lte => "$lte",
+                    Operator::In => return Err(TranspileError::unsupported(operation)),
+                };
+
+                Ok(
+                    doc! { "$expr": { op: [self.operand(&operation.lhs)?, self.operand(&operation.rhs)?] } },
+                )
+            }
+        }
+    }
+
+    fn operand(&self, literal: &Literal) -> Result<Bson, TranspileError> {
+        Ok(match literal {
+            Literal::LiteralValue(value) => doc! { "$literal": to_bson(value) }.into(),
+            Literal::LiteralField(field_name) => {
+                Bson::String(format!("${}", self.path(field_name)?))
+            }
+            Literal::LiteralAge(_) | Literal::LiteralComputed(_) => {
+                return Err(TranspileError::unsupported_literal(literal));
+            }
+        })
+    }
+}
+
+fn to_bson(value: &Value) -> Bson {
+    match value {
+        Value::String(val) => Bson::String(val.clone()),
+        Value::Regex(val) => Bson::String(val.clone()),
+        Value::Number(val) => Bson::Double(*val),
+        Value::Boolean(val) => Bson::Boolean(*val),
+        Value::Raw(val) => Bson::Binary(bson::Binary {
+            subtype: BinarySubtype::Generic,
+            bytes: val.clone(),
+        }),
+        Value::DateTime(val) => Bson::DateTime(bson::DateTime::from_millis(val.timestamp_millis())),
+        Value::StringList(items) => items.iter().cloned().map(Bson::String).collect(),
+        Value::NumberList(items) => items.iter().copied().map(Bson::Double).collect(),
+        Value::BooleanList(items) => items.iter().copied().map(Bson::Boolean).collect(),
+        Value::RawList(items) => items
+            .iter()
+            .map(|val| to_bson(&Value::Raw(val.clone())))
+            .collect(),
+        Value::DateTimeList(items) => items
+            .iter()
+            .map(|val| to_bson(&Value::DateTime(*val)))
+            .collect(),
+        Value::Null => Bson::Null,
+    }
+}
+
+// Missing and null fields never match a comparison in the engine, so these pin
+// down that the filters only select such documents where the engine would.
+#[cfg(test)]
+mod tests {
+    use crate::Parser;
+
+    use super::*;
+
+    fn transpile(input: &str) -> Document {
+        let mapping = FieldMapping::new()
+            .with_field("name", "name", Type::String)
+            .with_field("age", "profile.age", Type::Number);
+        let expression = Parser::parse(input).unwrap();
+
+        MongoTranspiler::new(mapping)
+            .transpile(&expression)
+            .unwrap()
+    }
+
+    #[test]
+    fn negation_matches_null_fields() {
+        assert_eq!(
+            transpile(r#"!(name == "x")"#),
+            doc! { "$nor": [{ "name": "x" }] }
+        );
+        assert_eq!(
+            transpile("!(age > 3)"),
+            doc! { "$nor": [{ "profile.age": { "$gt": 3.0 } }] }
+        );
+    }
+
+    #[test]
+    fn inequality_matches_null_fields() {
+        // $ne also matches documents where the field is null or missing.
+        assert_eq!(transpile(r#"name != "x""#), doc! { "name": { "$ne": "x" } });
+        assert_eq!(transpile(r#""x" != name"#), doc! { "name": { "$ne": "x" } });
+    }
+
+    #[test]
+    fn ordering_skips_null_fields() {
+        assert_eq!(transpile("age > 3"), doc! { "profile.age": { "$gt": 3.0 } });
+        assert_eq!(
+            transpile("3 >= age"),
+            doc! { "profile.age": { "$lte": 3.0 } }
+        );
+    }
+
+    #[test]
+    fn null_literals_are_rewritten() {
+        assert_eq!(transpile("name == null"), doc! { "name": Bson::Null });
+        assert_eq!(
+            transpile("null != name"),
+            doc! { "name": { "$ne": Bson::Null } }
+        );
+        assert_eq!(transpile("age > null"), doc! { "$expr": false });
+        assert_eq!(
+            transpile("!(age <= null)"),
+            doc! { "$nor": [{ "$expr": false }] }
+        );
+    }
+}
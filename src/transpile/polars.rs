@@ -0,0 +1,145 @@
+use polars::prelude::{Expr, IntoSeries, NamedFrom, Null, Series, col, lit};
+
+use crate::{
+    expression::{Expression, Literal, Operation, Operator},
+    schema::{Type, Value},
+};
+
+use super::{FieldMapping, TranspileError};
+
+pub struct PolarsTranspiler {
+    mapping: FieldMapping,
+}
+
+impl PolarsTranspiler {
+    pub fn new(mapping: FieldMapping) -> Self {
+        Self { mapping }
+    }
+
+    pub fn transpile(&self, expression: &Expression) -> Result<Expr, TranspileError> {
+        Ok(match expression {
+            Expression::And(and) => self
+                .transpile_all(and.get_subexpressions())?
+                .reduce(Expr::and)
+                .unwrap_or(lit(true)),
+            Expression::Or(or) => self
+                .transpile_all(or.get_subexpressions())?
+                .reduce(Expr::or)
+                .unwrap_or(lit(false)),
+            Expression::Not(not) => self.transpile(not.get_subexpression())?.not(),
+            Expression::Operation(operation) => self.transpile_operation(operation)?,
+            Expression::Reference(reference) => {
+                return Err(TranspileError::UnresolvedReferenceError(
+                    reference.get_rule_name().to_string(),
+                ));
+            }
+        })
+    }
+
+    fn transpile_all(
+        &self,
+        subexpressions: &[Expression],
+    ) -> Result<impl Iterator<Item = Expr>, TranspileError> {
+        Ok(subexpressions
+            .iter()
+            .map(|e| self.transpile(e))
+            .collect::<Result<Vec<Expr>, TranspileError>>()?
+            .into_iter())
+    }
+
+    fn operand(&self, literal: &Literal) -> Result<Expr, TranspileError> {
+        match literal {
+            Literal::LiteralValue(value) => to_lit(value),
+            Literal::LiteralField(field_name) => self
+                .mapping
+                .get_field(field_name)
+                .map(|field| col(field.name.as_str()))
+                .ok_or_else(|| TranspileError::UnmappedFieldError(field_name.to_string())),
+            Literal::LiteralAge(_) | Literal::LiteralComputed(_) => {
+                Err(TranspileError::unsupported_literal(literal))
+            }
+        }
+    }
+
+    fn transpile_operation(&self, operation: &Operation) -> Result<Expr, TranspileError> {
+        let lhs_type = self.mapping.literal_type(&operation.lhs)?;
+        let rhs_type = self.mapping.literal_type(&operation.rhs)?;
+
+        if lhs_type.is_null() || rhs_type.is_null() {
+            let other = if lhs_type.is_null() {
+                &operation.rhs
+            } else {
+                &operation.lhs
+            };
+            let other = self.operand(other)?;
+
+            return Ok(match operation.op {
+                Operator::Eq => other.is_null(),
+                Operator::Ne => other.is_not_null(),
+                _ => lit(false),
+            });
+        }
+
+        let lhs = self.operand(&operation.lhs)?;
+        let rhs = self.operand(&operation.rhs)?;
+
+        let expr = match operation.op {
+            // The `_missing` variants treat null like the engine does: `null == null`
+            // holds and `null != "x"` is true rather than null.
+            Operator::Eq => return Ok(lhs.eq_missing(rhs)),
+            Operator::Ne => return Ok(lhs.neq_missing(rhs)),
+            Operator::Gt => lhs.gt(rhs),
+            Operator::Gte => lhs.gt_eq(rhs),
+            Operator::Lt => lhs.lt(rhs),
+            Operator::Lte => lhs.lt_eq(rhs),
+            Operator::In => match (lhs_type, rhs_type) {
+                (Type::String, Type::String) => rhs.str().contains_literal(lhs),
+                (Type::Regex, Type::String) => rhs.str().contains(lhs, true),
+                (Type::DateTime, Type::DateTimeList) => match &operation.rhs {
+                    Literal::LiteralValue(Value::DateTimeList(range)) if range.len() == 2 => {
+                        let from = to_lit(&Value::DateTime(range[0]))?;
+                        let until = to_lit(&Value::DateTime(range[1]))?;
+
+                        lhs.clone().gt_eq(from).and(lhs.lt(until))
+                    }
+                    _ => return Err(TranspileError::unsupported(operation)),
+                },
+                (Type::String, Type::StringList)
+                | (Type::Number, Type::NumberList)
+                | (Type::Boolean, Type::BooleanList) => match &operation.rhs {
+                    Literal::LiteralValue(_) => lhs.is_in(rhs.implode(false), false),
+                    Literal::LiteralField(_)
+                    | Literal::LiteralAge(_)
+                    | Literal::LiteralComputed(_) => lhs.is_in(rhs, false),
+                },
+                _ => return Err(TranspileError::unsupported(operation)),
+            },
+        };
+
+        // Comparisons involving a null are false in the engine rather than
+        // unknown, which matters once they are negated.
+        Ok(expr.fill_null(lit(false)))
+    }
+}
+
+// Datetimes are converted to naive UTC literals, so datetime columns are
+// expected to hold naive UTC timestamps.
+fn to_lit(value: &Value) -> Result<Expr, TranspileError> {
+    Ok(match value {
+        Value::String(val) => lit(val.clone()),
+        Value::Regex(val) => lit(val.clone()),
+        Value::Number(val) => lit(*val),
+        Value::Boolean(val) => lit(*val),
+        Value::Raw(val) => lit(val.clone()),
+        Value::DateTime(val) => lit(val.naive_utc()),
+        Value::StringList(items) => lit(Series::new("".into(), items)),
+        Value::NumberList(items) => lit(Series::new("".into(), items)),
+        Value::BooleanList(items) => lit(Series::new("".into(), items)),
+        Value::RawList(items) => lit(Series::new("".into(), items).into_series()),
+        Value::DateTimeList(items) => lit(Series::new(
+            "".into(),
+            items.iter().map(|val| val.naive_utc()).collect::<Vec<_>>(),
+        )),
+        Value::Null => lit(Null {}),
+    })
+}
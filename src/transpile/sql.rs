@@ -0,0 +1,360 @@
+use crate::{
+    expression::{Expression, Literal, Operation, Operator},
+    schema::{Type, Value},
+};
+
+use super::{FieldMapping, TranspileError};
+
+#[derive(Clone, Copy, Debug)]
+pub enum Dialect {
+    Postgres,
+    MySql,
+    Sqlite,
+}
+
+impl Dialect {
+    fn placeholder(&self, index: usize) -> String {
+        match self {
+            Dialect::Postgres => format!("${}", index),
+            Dialect::MySql | Dialect::Sqlite => String::from("?"),
+        }
+    }
+
+    fn quote_identifier(&self, name: &str) -> String {
+        name.split('.')
+            .map(|part| match self {
+                Dialect::Postgres | Dialect::Sqlite => format!("\"{}\"", part.replace('"', "\"\"")),
+                Dialect::MySql => format!("`{}`", part.replace('`', "``")),
+            })
+            .collect::<Vec<String>>()
+            .join(".")
+    }
+
+    // Null-safe inequality: the engine treats `null != "x"` as true, while a
+    // plain `<>` would yield NULL and filter the row out.
+    fn distinct(&self, lhs: &str, rhs: &str) -> String {
+        match self {
+            Dialect::Postgres => format!("{} IS DISTINCT FROM {}", lhs, rhs),
+            Dialect::MySql => format!("NOT ({} <=> {})", lhs, rhs),
+            Dialect::Sqlite => format!("{} IS NOT {}", lhs, rhs),
+        }
+    }
+
+    fn contains(&self, haystack: &str, needle: &str) -> String {
+        match self {
+            Dialect::Postgres => format!("strpos({}, {}) > 0", haystack, needle),
+            Dialect::MySql | Dialect::Sqlite => format!("INSTR({}, {}) > 0", haystack, needle),
+        }
+    }
+
+    #[cfg(feature = "regex")]
+    fn regex_match(&self, subject: &str, pattern: &str) -> String {
+        match self {
+            Dialect::Postgres => format!("{} ~ {}", subject, pattern),
+            Dialect::MySql | Dialect::Sqlite => format!("{} REGEXP {}", subject, pattern),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SqlQuery {
+    pub clause: String,
+    pub params: Vec<Value>,
+}
+
+pub struct SqlTranspiler {
+    dialect: Dialect,
+    mapping: FieldMapping,
+}
+
+struct Context<'a> {
+    transpiler: &'a SqlTranspiler,
+    params: Vec<Value>,
+}
+
+impl SqlTranspiler {
+    pub fn new(dialect: Dialect, mapping: FieldMapping) -> Self {
+        Self { dialect, mapping }
+    }
+
+    pub fn transpile(&self, expression: &Expression) -> Result<SqlQuery, TranspileError> {
+        let mut context = Context {
+            transpiler: self,
+            params: Vec::new(),
+        };
+        let clause = context.expression(expression)?;
+
+        Ok(SqlQuery {
+            clause,
+            params: context.params,
+        })
+    }
+}
+
+impl Context<'_> {
+    fn expression(&mut self, expression: &Expression) -> Result<String, TranspileError> {
+        Ok(match expression {
+            Expression::And(and) => format!(
+                "({})",
+                and.get_subexpressions()
+                    .iter()
+                    .map(|e| self.expression(e))
+                    .collect::<Result<Vec<String>, TranspileError>>()?
+                    .join(" AND ")
+            ),
+            Expression::Or(or) => format!(
+                "({})",
+                or.get_subexpressions()
+                    .iter()
+                    .map(|e| self.expression(e))
+                    .collect::<Result<Vec<String>, TranspileError>>()?
+                    .join(" OR ")
+            ),
+            // Comparisons against NULL columns are unknown in SQL but false in
+            // the engine, so they must not flip to true when negated.
+            Expression::Not(not) => format!(
+                "NOT COALESCE({}, FALSE)",
+                self.expression(not.get_subexpression())?
+            ),
+            Expression::Operation(operation) => self.operation(operation)?,
+            Expression::Reference(reference) => {
+                return Err(TranspileError::UnresolvedReferenceError(
+                    reference.get_rule_name().to_string(),
+                ));
+            }
+        })
+    }
+
+    fn bind(&mut self, value: Value) -> String {
+        self.params.push(value);
+
+        self.transpiler.dialect.placeholder(self.params.len())
+    }
+
+    fn operand(&mut self, literal: &Literal) -> Result<String, TranspileError> {
+        match literal {
+            Literal::LiteralValue(Value::Null) => Ok(String::from("NULL")),
+            Literal::LiteralValue(value) => Ok(self.bind(value.clone())),
+            Literal::LiteralField(field_name) => self
+                .transpiler
+                .mapping
+                .get_field(field_name)
+                .map(|field| self.transpiler.dialect.quote_identifier(&field.name))
+                .ok_or_else(|| TranspileError::UnmappedFieldError(field_name.to_string())),
+            #[cfg(feature = "chrono")]
+            Literal::LiteralAge(_) => Err(TranspileError::unsupported_literal(literal)),
+            Literal::LiteralComputed(_) => Err(TranspileError::unsupported_literal(literal)),
+        }
+    }
+
+    fn operation(&mut self, operation: &Operation) -> Result<String, TranspileError> {
+        let dialect = self.transpiler.dialect;
+        let lhs_type = self.transpiler.mapping.literal_type(&operation.lhs)?;
+        let rhs_type = self.transpiler.mapping.literal_type(&operation.rhs)?;
+
+        if lhs_type.is_null() || rhs_type.is_null() {
+            let (lhs, rhs) = (self.operand(&operation.lhs)?, self.operand(&operation.rhs)?);
+            let other = if lhs_type.is_null() { rhs } else { lhs };
+
+            return Ok(match operation.op {
+                Operator::Eq => format!("{} IS NULL", other),
+                Operator::Ne => format!("{} IS NOT NULL", other),
+                _ => String::from("FALSE"),
+            });
+        }
+
+        if let Operator::In = operation.op {
+            return self.in_operation(operation, lhs_type, rhs_type);
+        }
+
+        let lhs = self.operand(&operation.lhs)?;
+        let rhs = self.operand(&operation.rhs)?;
+
+        Ok(match operation.op {
+            Operator::Eq => format!("{} = {}", lhs, rhs),
+            Operator::Ne => dialect.distinct(&lhs, &rhs),
+            Operator::Gt => format!("{} > {}", lhs, rhs),
+            Operator::Gte => format!("{} >= {}", lhs, rhs),
+            Operator::Lt => format!("{} < {}", lhs, rhs),
+            Operator::Lte => format!("{} <= {}", lhs, rhs),
+            Operator::In => unreachable!(),
+        })
+    }
+
+    fn in_operation(
+        &mut self,
+        operation: &Operation,
+        lhs_type: Type,
+        rhs_type: Type,
+    ) -> Result<String, TranspileError> {
+        match (lhs_type, rhs_type) {
+            (Type::String, Type::String) => self.contains(operation),
+            #[cfg(feature = "raw")]
+            (Type::Raw, Type::Raw) => self.contains(operation),
+            #[cfg(feature = "regex")]
+            (Type::Regex, Type::String) => {
+                let rhs = self.operand(&operation.rhs)?;
+                let lhs = self.operand(&operation.lhs)?;
+
+                Ok(self.transpiler.dialect.regex_match(&rhs, &lhs))
+            }
+            #[cfg(feature = "chrono")]
+            (Type::DateTime, Type::DateTimeList) => match &operation.rhs {
+                Literal::LiteralValue(Value::DateTimeList(range)) if range.len() == 2 => {
+                    let lower = self.operand(&operation.lhs)?;
+                    let from = self.bind(Value::DateTime(range[0]));
+                    let upper = self.operand(&operation.lhs)?;
+                    let until = self.bind(Value::DateTime(range[1]));
+
+                    Ok(format!("({} >= {} AND {} < {})", lower, from, upper, until))
+                }
+                _ => Err(TranspileError::unsupported(operation)),
+            },
+            (Type::String, Type::StringList)
+            | (Type::Number, Type::NumberList)
+            | (Type::Boolean, Type::BooleanList) => self.in_list(operation),
+            #[cfg(feature = "raw")]
+            (Type::Raw, Type::RawList) => self.in_list(operation),
+            _ => Err(TranspileError::unsupported(operation)),
+        }
+    }
+
+    // Operands are bound in the order they appear in the clause, as MySQL and
+    // SQLite placeholders are positional.
+    fn contains(&mut self, operation: &Operation) -> Result<String, TranspileError> {
+        let rhs = self.operand(&operation.rhs)?;
+        let lhs = self.operand(&operation.lhs)?;
+
+        Ok(self.transpiler.dialect.contains(&rhs, &lhs))
+    }
+
+    fn in_list(&mut self, operation: &Operation) -> Result<String, TranspileError> {
+        let lhs = self.operand(&operation.lhs)?;
+
+        match &operation.rhs {
+            Literal::LiteralValue(list) => {
+                let placeholders = split_list(list)
+                    .into_iter()
+                    .map(|item| self.bind(item))
+                    .collect::<Vec<String>>();
+
+                Ok(format!("{} IN ({})", lhs, placeholders.join(", ")))
+            }
+            Literal::LiteralField(_) => match self.transpiler.dialect {
+                Dialect::Postgres => {
+                    let rhs = self.operand(&operation.rhs)?;
+
+                    Ok(format!("{} = ANY({})", lhs, rhs))
+                }
+                _ => Err(TranspileError::unsupported(operation)),
+            },
+            #[cfg(feature = "chrono")]
+            Literal::LiteralAge(_) => Err(TranspileError::unsupported(operation)),
+            Literal::LiteralComputed(_) => Err(TranspileError::unsupported(operation)),
+        }
+    }
+}
+
+fn split_list(list: &Value) -> Vec<Value> {
+    match list {
+        Value::StringList(items) => items.iter().cloned().map(Value::String).collect(),
+        Value::NumberList(items) => items.iter().copied().map(Value::Number).collect(),
+        Value::BooleanList(items) => items.iter().copied().map(Value::Boolean).collect(),
+        #[cfg(feature = "raw")]
+        Value::RawList(items) => items.iter().cloned().map(Value::Raw).collect(),
+        #[cfg(feature = "chrono")]
+        Value::DateTimeList(items) => items.iter().copied().map(Value::DateTime).collect(),
+        value => vec![value.clone()],
+    }
+}
+
+// The engine treats comparisons with a null field as false, unlike SQL where
+// they are unknown, so these pin down how each dialect keeps null columns from
+// matching or flipping to true.
+#[cfg(test)]
+mod tests {
+    use crate::Parser;
+
+    use super::*;
+
+    fn transpile(dialect: Dialect, input: &str) -> String {
+        let mapping = FieldMapping::new()
+            .with_field("name", "name", Type::String)
+            .with_field("age", "age", Type::Number);
+        let expression = Parser::parse(input).unwrap();
+
+        SqlTranspiler::new(dialect, mapping)
+            .transpile(&expression)
+            .unwrap()
+            .clause
+    }
+
+    #[test]
+    fn negation_is_true_for_null_columns() {
+        assert_eq!(
+            transpile(Dialect::Postgres, r#"!(name == "x")"#),
+            r#"NOT COALESCE("name" = $1, FALSE)"#
+        );
+        assert_eq!(
+            transpile(Dialect::MySql, r#"!(name == "x")"#),
+            "NOT COALESCE(`name` = ?, FALSE)"
+        );
+        assert_eq!(
+            transpile(Dialect::Sqlite, r#"!(name == "x")"#),
+            r#"NOT COALESCE("name" = ?, FALSE)"#
+        );
+        assert_eq!(
+            transpile(Dialect::Postgres, "!((age > 3 or age < 1))"),
+            r#"NOT COALESCE(("age" > $1 OR "age" < $2), FALSE)"#
+        );
+    }
+
+    #[test]
+    fn inequality_is_true_for_null_columns() {
+        assert_eq!(
+            transpile(Dialect::Postgres, r#"name != "x""#),
+            r#""name" IS DISTINCT FROM $1"#
+        );
+        assert_eq!(
+            transpile(Dialect::MySql, r#"name != "x""#),
+            "NOT (`name` <=> ?)"
+        );
+        assert_eq!(
+            transpile(Dialect::Sqlite, r#"name != "x""#),
+            r#""name" IS NOT ?"#
+        );
+    }
+
+    #[test]
+    fn ordering_is_false_for_null_columns() {
+        // A NULL column makes the comparison unknown, which filters the row out
+        // like the engine's false, and the negation above turns it into true.
+        assert_eq!(transpile(Dialect::Postgres, "age > 3"), r#""age" > $1"#);
+        assert_eq!(transpile(Dialect::MySql, "age >= 3"), "`age` >= ?");
+        assert_eq!(transpile(Dialect::Sqlite, "age < 3"), r#""age" < ?"#);
+        assert_eq!(
+            transpile(Dialect::Sqlite, "!(age <= 3)"),
+            r#"NOT COALESCE("age" <= ?, FALSE)"#
+        );
+    }
+
+    #[test]
+    fn null_literals_are_rewritten() {
+        for dialect in [Dialect::Postgres, Dialect::MySql, Dialect::Sqlite] {
+            let name = dialect.quote_identifier("name");
+            assert_eq!(
+                transpile(dialect, "name == null"),
+                format!("{} IS NULL", name)
+            );
+            assert_eq!(
+                transpile(dialect, "null != name"),
+                format!("{} IS NOT NULL", name)
+            );
+            assert_eq!(transpile(dialect, "age > null"), "FALSE");
+            assert_eq!(
+                transpile(dialect, "!(age <= null)"),
+                "NOT COALESCE(FALSE, FALSE)"
+            );
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{
+    complete::{OPERATORS, TYPES, is_valid},
+    descriptor::SchemaDescriptor,
+    schema::Type,
+};
+
+// Generates a TypeScript module for clients building expressions against the
+// schema: a `Fields` interface with the type of each field's values, and an
+// `operators` table with the right-hand side types each operator accepts after
+// a field of each type. Fields are null when the target has no value for them.
+// Datetimes are RFC 3339 strings and raw values arrays of bytes.
+pub fn to_typescript(descriptor: &SchemaDescriptor) -> String {
+    let mut lines = vec![
+        String::from("// Generated from a schema descriptor, do not edit."),
+        String::new(),
+        format!(
+            "export type ValueType = {};",
+            TYPES
+                .iter()
+                .map(|value_type| quote(value_type.variant_name()))
+                .collect::<Vec<String>>()
+                .join(" | ")
+        ),
+        format!(
+            "export type Operator = {};",
+            OPERATORS
+                .iter()
+                .map(|op| quote(op.fmt_static()))
+                .collect::<Vec<String>>()
+                .join(" | ")
+        ),
+        String::new(),
+        String::from("export interface Fields {"),
+    ];
+    for field in &descriptor.fields {
+        if let Some(description) = &field.description {
+            lines.push(format!("  /** {} */", description.replace("*/", "*\\/")));
+        }
+        lines.push(format!(
+            "  {}: {} | null;",
+            quote(&field.name),
+            value_type(field.field_type)
+        ));
+    }
+    lines.push(String::from("}"));
+    lines.push(String::new());
+    lines.push(String::from("export type FieldName = keyof Fields;"));
+    lines.push(String::new());
+
+    lines.push(String::from(
+        "export const fieldTypes: Record<FieldName, ValueType> = {",
+    ));
+    for field in &descriptor.fields {
+        lines.push(format!(
+            "  {}: {},",
+            quote(&field.name),
+            quote(field.field_type.variant_name())
+        ));
+    }
+    lines.push(String::from("};"));
+    lines.push(String::new());
+
+    lines.push(String::from(
+        "export const operators: Record<ValueType, Partial<Record<Operator, ValueType[]>>> = {",
+    ));
+    for lhs in TYPES {
+        let entries = OPERATORS
+            .iter()
+            .filter_map(|op| {
+                let rhs_types = TYPES
+                    .iter()
+                    .filter(|rhs| is_valid(*lhs, op, **rhs))
+                    .map(|rhs| quote(rhs.variant_name()))
+                    .collect::<Vec<String>>();
+
+                (!rhs_types.is_empty())
+                    .then(|| format!("{}: [{}]", quote(op.fmt_static()), rhs_types.join(", ")))
+            })
+            .collect::<Vec<String>>();
+
+        lines.push(format!(
+            "  {}: {{ {} }},",
+            quote(lhs.variant_name()),
+            entries.join(", ")
+        ));
+    }
+    lines.push(String::from("};"));
+
+    lines.join("\n") + "\n"
+}
+
+fn value_type(field_type: Type) -> &'static str {
+    match field_type {
+        Type::String => "string",
+        #[cfg(feature = "regex")]
+        Type::Regex => "string",
+        Type::Number => "number",
+        Type::Boolean => "boolean",
+        #[cfg(feature = "raw")]
+        Type::Raw => "number[]",
+        #[cfg(feature = "chrono")]
+        Type::DateTime => "string",
+        Type::StringList => "string[]",
+        Type::NumberList => "number[]",
+        Type::BooleanList => "boolean[]",
+        #[cfg(feature = "raw")]
+        Type::RawList => "number[][]",
+        #[cfg(feature = "chrono")]
+        Type::DateTimeList => "string[]",
+        Type::Null => "null",
+    }
+}
+
+fn quote(val: &str) -> String {
+    format!("\"{}\"", val.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,118 @@
+use alloc::vec::Vec;
+
+use crate::{
+    engine::execute_operation,
+    expression::{Expression, Literal, Operation, Operator, Or},
+    schema::{SchemaBuilder, Value},
+    simplify::simplify,
+};
+
+// ORs the expressions together, leaving out clauses that add nothing: those
+// equal to another clause after canonicalization, and those implied by another
+// clause, e.g. `age > 20` next to `age > 10`. Implication is only recognized
+// between comparisons of the same operand with values, and through the
+// clauses of Ands, so some redundant clauses can remain. Returns None when
+// there are no expressions.
+pub fn union(expressions: impl IntoIterator<Item = Expression>) -> Option<Expression> {
+    let mut clauses = Vec::new();
+    for expression in expressions {
+        match simplify(&expression).canonicalize() {
+            Expression::Or(or) => clauses.extend(or.into_subexpressions()),
+            expression => clauses.push(expression),
+        }
+    }
+
+    // Of clauses implying each other, the first is kept.
+    let mut kept = Vec::<Expression>::new();
+    for clause in clauses {
+        if kept.iter().any(|other| implies(&clause, other)) {
+            continue;
+        }
+
+        kept.retain(|other| !implies(other, &clause));
+        kept.push(clause);
+    }
+
+    match kept.len() {
+        0 => None,
+        1 => kept.pop(),
+        _ => Some(Expression::Or(Or::new(kept))),
+    }
+}
+
+// Whether `expression` being true means `other` is too. False when unsure.
+fn implies(expression: &Expression, other: &Expression) -> bool {
+    if expression == other {
+        return true;
+    }
+
+    match (expression, other) {
+        (expression, Expression::And(and)) => and
+            .get_subexpressions()
+            .iter()
+            .all(|subexpression| implies(expression, subexpression)),
+        (Expression::And(and), other) => and
+            .get_subexpressions()
+            .iter()
+            .any(|subexpression| implies(subexpression, other)),
+        (expression, Expression::Or(or)) => or
+            .get_subexpressions()
+            .iter()
+            .any(|subexpression| implies(expression, subexpression)),
+        (Expression::Operation(operation), Expression::Operation(other)) => {
+            implies_operation(operation, other)
+        }
+        _ => false,
+    }
+}
+
+fn implies_operation(operation: &Operation, other: &Operation) -> bool {
+    if matches!(operation.lhs, Literal::LiteralValue(_)) || operation.lhs != other.lhs {
+        return false;
+    }
+    let (Literal::LiteralValue(value), Literal::LiteralValue(_)) = (&operation.rhs, &other.rhs)
+    else {
+        return false;
+    };
+
+    match (&operation.op, &other.op) {
+        // The operand is the value, so the other holds if it holds for the value.
+        (Operator::Eq, op) => holds(value, op.clone(), &other.rhs),
+        // A bound within the other's bound, e.g. `> 20` within `> 10` or
+        // `>= 10`. A strict bound is also within the same strict bound.
+        (Operator::Gt, Operator::Gt) => holds(value, Operator::Gte, &other.rhs),
+        (Operator::Lt, Operator::Lt) => holds(value, Operator::Lte, &other.rhs),
+        (Operator::Gt | Operator::Gte, op @ (Operator::Gt | Operator::Gte))
+        | (Operator::Lt | Operator::Lte, op @ (Operator::Lt | Operator::Lte)) => {
+            holds(value, op.clone(), &other.rhs)
+        }
+        // Every item of the list is in the other.
+        (Operator::In, Operator::In) => match items(value) {
+            Some(items) => items
+                .into_iter()
+                .all(|item| holds(&item, Operator::In, &other.rhs)),
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+// Whether `value op rhs` is true, by executing it.
+fn holds(value: &Value, op: Operator, rhs: &Literal) -> bool {
+    let operation = Operation::new(Literal::LiteralValue(value.clone()), op, rhs.clone());
+
+    execute_operation(&operation, &(), &SchemaBuilder::<()>::new().build()).unwrap_or(false)
+}
+
+// The items of lists whose `IN` means being one of the items. Datetime lists
+// are ranges.
+fn items(value: &Value) -> Option<Vec<Value>> {
+    Some(match value {
+        Value::StringList(items) => items.iter().cloned().map(Value::String).collect(),
+        Value::NumberList(items) => items.iter().copied().map(Value::Number).collect(),
+        Value::BooleanList(items) => items.iter().copied().map(Value::Boolean).collect(),
+        #[cfg(feature = "raw")]
+        Value::RawList(items) => items.iter().cloned().map(Value::Raw).collect(),
+        _ => return None,
+    })
+}
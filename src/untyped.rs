@@ -0,0 +1,101 @@
+use alloc::{boxed::Box, collections::BTreeMap, string::String, vec::Vec};
+
+use hashbrown::HashMap;
+
+use crate::{
+    engine::{self, ExecutionError, FieldList, ValidationError},
+    expression::{Expression, FieldName},
+    iter::Operations,
+    schema::{Field, Type, Value},
+};
+
+// Maps of field names to values that expressions can be executed against
+// without a schema.
+pub trait ValueMap {
+    fn get_value(&self, field_name: &str) -> Option<&Value>;
+}
+
+impl ValueMap for HashMap<String, Value> {
+    fn get_value(&self, field_name: &str) -> Option<&Value> {
+        self.get(field_name)
+    }
+}
+
+#[cfg(feature = "std")]
+impl ValueMap for std::collections::HashMap<String, Value> {
+    fn get_value(&self, field_name: &str) -> Option<&Value> {
+        self.get(field_name)
+    }
+}
+
+impl ValueMap for BTreeMap<String, Value> {
+    fn get_value(&self, field_name: &str) -> Option<&Value> {
+        self.get(field_name)
+    }
+}
+
+// Validates and executes expressions against value maps, for prototyping and
+// dynamic data where defining a `Schema` up front isn't worth it. The type of
+// a field is the type of its value in the map, so a field that's null in one
+// map only supports `==`, `!=` and `IN` when validating against that map.
+// Fields missing from the map are invalid.
+#[derive(Default)]
+pub struct UntypedEngine;
+
+impl UntypedEngine {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn validate<M: ValueMap>(
+        &self,
+        expression: &Expression,
+        values: &M,
+    ) -> Result<(), ValidationError> {
+        engine::validate(expression, &|field_name| {
+            values.get_value(field_name).map(Value::get_type)
+        })
+    }
+
+    pub fn execute<M: ValueMap>(
+        &self,
+        expression: &Expression,
+        values: &M,
+    ) -> Result<bool, ExecutionError> {
+        // Extractors for the fields of the expression that are in the map.
+        let mut fields: Vec<(FieldName, Field<M>)> = Vec::new();
+        for operation in Operations::new(expression) {
+            let literals = [&operation.lhs, &operation.rhs];
+            for (field_name, _) in literals
+                .iter()
+                .flat_map(|literal| literal.get_field_operands())
+            {
+                let Some(value) = values.get_value(field_name) else {
+                    continue;
+                };
+                if fields.iter().any(|(name, _)| name == field_name) {
+                    continue;
+                }
+
+                fields.push((
+                    field_name.clone(),
+                    field(field_name.clone(), value.get_type()),
+                ));
+            }
+        }
+
+        engine::execute(expression, values, &FieldList(fields))
+    }
+}
+
+fn field<M: ValueMap>(field_name: FieldName, field_type: Type) -> Field<M> {
+    Field::new(
+        field_type,
+        Box::new(move |values: &M| {
+            values
+                .get_value(&field_name)
+                .cloned()
+                .unwrap_or(Value::Null)
+        }),
+    )
+}
@@ -0,0 +1,90 @@
+use crate::{
+    expression::{And, Computed, Expression, Literal, Not, Operation, Operator, Or, Reference},
+    schema::Value,
+};
+
+// Read-only traversal of an expression tree. Each `visit_*` method defaults to
+// walking into its children, so implementations override only the nodes they
+// care about and call the matching `walk_*` function to keep descending.
+pub trait ExpressionVisitor {
+    fn visit_expression(&mut self, expression: &Expression) {
+        walk_expression(self, expression);
+    }
+
+    fn visit_and(&mut self, and: &And) {
+        walk_and(self, and);
+    }
+
+    fn visit_or(&mut self, or: &Or) {
+        walk_or(self, or);
+    }
+
+    fn visit_not(&mut self, not: &Not) {
+        walk_not(self, not);
+    }
+
+    fn visit_operation(&mut self, operation: &Operation) {
+        walk_operation(self, operation);
+    }
+
+    fn visit_reference(&mut self, _reference: &Reference) {}
+
+    fn visit_literal(&mut self, literal: &Literal) {
+        walk_literal(self, literal);
+    }
+
+    fn visit_operator(&mut self, _operator: &Operator) {}
+
+    fn visit_field(&mut self, _field_name: &str) {}
+
+    fn visit_value(&mut self, _value: &Value) {}
+}
+
+pub fn walk_expression<V: ExpressionVisitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    match expression {
+        Expression::And(and) => visitor.visit_and(and),
+        Expression::Or(or) => visitor.visit_or(or),
+        Expression::Not(not) => visitor.visit_not(not),
+        Expression::Operation(operation) => visitor.visit_operation(operation),
+        Expression::Reference(reference) => visitor.visit_reference(reference),
+    }
+}
+
+pub fn walk_and<V: ExpressionVisitor + ?Sized>(visitor: &mut V, and: &And) {
+    for subexpression in and.get_subexpressions() {
+        visitor.visit_expression(subexpression);
+    }
+}
+
+pub fn walk_or<V: ExpressionVisitor + ?Sized>(visitor: &mut V, or: &Or) {
+    for subexpression in or.get_subexpressions() {
+        visitor.visit_expression(subexpression);
+    }
+}
+
+pub fn walk_not<V: ExpressionVisitor + ?Sized>(visitor: &mut V, not: &Not) {
+    visitor.visit_expression(not.get_subexpression());
+}
+
+pub fn walk_operation<V: ExpressionVisitor + ?Sized>(visitor: &mut V, operation: &Operation) {
+    visitor.visit_literal(&operation.lhs);
+    visitor.visit_operator(&operation.op);
+    visitor.visit_literal(&operation.rhs);
+}
+
+pub fn walk_literal<V: ExpressionVisitor + ?Sized>(visitor: &mut V, literal: &Literal) {
+    match literal {
+        Literal::LiteralValue(value) => visitor.visit_value(value),
+        Literal::LiteralField(field_name) => visitor.visit_field(field_name),
+        #[cfg(feature = "chrono")]
+        Literal::LiteralAge(field_name) => visitor.visit_field(field_name),
+        Literal::LiteralComputed(computed) => {
+            if let Computed::If(condition, _, _) = &**computed {
+                visitor.visit_expression(condition);
+            }
+            for operand in computed.get_operands() {
+                visitor.visit_literal(operand);
+            }
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use serde_json::json;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    descriptor::{ClientValidator, SchemaDescriptor},
+    lsp::LanguageServer,
+    parser::ExpressionParser,
+};
+
+// Bindings for running the parser and validator in the browser. Descriptors
+// are passed as JSON, as produced by serializing a `SchemaDescriptor`, and
+// errors are thrown as JS `Error`s carrying the message. The crate isn't a
+// cdylib by default, so build with `cargo rustc --lib --crate-type cdylib
+// --target wasm32-unknown-unknown --features wasm` before running wasm-bindgen.
+
+fn descriptor(descriptor_json: &str) -> Result<SchemaDescriptor, JsError> {
+    serde_json::from_str(descriptor_json)
+        .map_err(|e| JsError::new(&format!("Invalid schema descriptor: {}", e)))
+}
+
+// Returns the expression in its serialized form.
+#[wasm_bindgen]
+pub fn parse(input: &str) -> Result<String, JsError> {
+    Ok(ExpressionParser::parse(input)?.serialize())
+}
+
+#[wasm_bindgen]
+pub fn validate(input: &str, descriptor_json: &str) -> Result<(), JsError> {
+    let expression = ExpressionParser::parse(input)?;
+
+    Ok(ClientValidator::new(descriptor(descriptor_json)?).validate(&expression)?)
+}
+
+#[wasm_bindgen]
+pub fn canonicalize(input: &str) -> Result<String, JsError> {
+    Ok(ExpressionParser::parse(input)?.canonicalize().serialize())
+}
+
+// All parse and validation errors as a JSON array of
+// `{ "start": .., "end": .., "message": .. }`, with UTF-16 offsets as used by
+// JS strings.
+#[wasm_bindgen]
+pub fn diagnostics(input: &str, descriptor_json: &str) -> Result<String, JsError> {
+    let utf16_offset = |offset: usize| input[..offset].encode_utf16().count();
+    let diagnostics = LanguageServer::new(descriptor(descriptor_json)?)
+        .diagnostics(input)
+        .into_iter()
+        .map(|diagnostic| {
+            json!({
+                "start": utf16_offset(diagnostic.span.start),
+                "end": utf16_offset(diagnostic.span.end),
+                "message": diagnostic.message,
+            })
+        })
+        .collect::<Vec<serde_json::Value>>();
+
+    Ok(serde_json::Value::Array(diagnostics).to_string())
+}
@@ -0,0 +1,252 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use thiserror::Error;
+
+use crate::{
+    Engine, Parser, RuleSet,
+    engine::{self, suggest_field},
+    parser::ParseError,
+    rules::RuleError,
+    schema::Type,
+};
+
+#[derive(Error, Debug)]
+pub enum WatchError {
+    #[error("Failed to read {0}: {1}")]
+    ReadError(PathBuf, io::Error),
+    #[error("{0}:{1}: expected `name = expression`")]
+    SyntaxError(PathBuf, usize),
+    #[error("{0}:{1}: '{2}' is not a valid rule name")]
+    InvalidNameError(PathBuf, usize, String),
+    #[error("Rule '{0}' is defined more than once")]
+    DuplicateRuleError(String),
+    #[error("Rule '{0}' doesn't parse: {1}")]
+    ParseError(String, ParseError),
+    #[error(transparent)]
+    RuleError(#[from] RuleError),
+}
+
+// Keeps a rule set loaded from a file, or from every `.rules` file of a
+// directory, in sync with the files. Each line of a file is a rule, written as
+// `name = expression`; empty lines and lines starting with `#` are skipped.
+// Rules are validated against the schema of the engine the watcher was created
+// with. A load replaces the rules only when every rule is valid, otherwise the
+// previous rules stay in place and the errors are reported.
+pub struct RuleWatcher {
+    path: PathBuf,
+    field_types: BTreeMap<String, Type>,
+    rules: Arc<RwLock<Arc<RuleSet>>>,
+    // The modification time and length of each file as of the last load.
+    loaded: Vec<(PathBuf, Option<Stamp>)>,
+}
+
+type Stamp = (SystemTime, u64);
+
+// The current rules of a watcher, shared with the thread reloading them. Rule
+// sets are swapped as a whole, so a set taken with `get` stays consistent.
+#[derive(Clone)]
+pub struct LiveRules(Arc<RwLock<Arc<RuleSet>>>);
+
+impl LiveRules {
+    pub fn get(&self) -> Arc<RuleSet> {
+        Arc::clone(&self.0.read().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+impl RuleWatcher {
+    // Loads the rules, failing with every error when any rule is invalid.
+    pub fn new<T>(path: impl Into<PathBuf>, engine: &Engine<T>) -> Result<Self, Vec<WatchError>> {
+        let schema = engine.get_schema();
+        let field_types = schema
+            .get_field_names()
+            .filter_map(|field_name| {
+                schema
+                    .get_field(field_name)
+                    .map(|field| (String::from(field_name), field.field_type))
+            })
+            .collect();
+
+        let mut watcher = RuleWatcher {
+            path: path.into(),
+            field_types,
+            rules: Arc::new(RwLock::new(Arc::new(RuleSet::new()))),
+            loaded: Vec::new(),
+        };
+        watcher.reload()?;
+
+        Ok(watcher)
+    }
+
+    pub fn get_rules(&self) -> Arc<RuleSet> {
+        self.live_rules().get()
+    }
+
+    pub fn live_rules(&self) -> LiveRules {
+        LiveRules(Arc::clone(&self.rules))
+    }
+
+    // Loads the rules again, keeping the current ones on errors.
+    pub fn reload(&mut self) -> Result<(), Vec<WatchError>> {
+        let files = self.files().map_err(|e| vec![e])?;
+        let rules = self.load(&files)?;
+
+        *self.rules.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(rules);
+        self.loaded = files;
+
+        Ok(())
+    }
+
+    // Reloads the rules when files were added, removed or modified since the
+    // last load. Returns whether the rules were replaced.
+    pub fn poll(&mut self) -> Result<bool, Vec<WatchError>> {
+        let files = self.files().map_err(|e| vec![e])?;
+        if files == self.loaded {
+            return Ok(false);
+        }
+
+        let result = self.load(&files);
+        // Files that failed to load are only reported again once they change.
+        self.loaded = files;
+        *self.rules.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(result?);
+
+        Ok(true)
+    }
+
+    // Polls every `interval` on a new thread, passing the errors of failed
+    // loads to `on_errors`. The thread stops once the returned rules and every
+    // clone of them are dropped.
+    pub fn spawn(
+        mut self,
+        interval: Duration,
+        on_errors: impl Fn(Vec<WatchError>) + Send + 'static,
+    ) -> LiveRules {
+        let live_rules = self.live_rules();
+        thread::spawn(move || {
+            while Arc::strong_count(&self.rules) > 1 {
+                thread::sleep(interval);
+                if let Err(errors) = self.poll() {
+                    on_errors(errors);
+                }
+            }
+        });
+
+        live_rules
+    }
+
+    fn files(&self) -> Result<Vec<(PathBuf, Option<Stamp>)>, WatchError> {
+        let read_error = |e| WatchError::ReadError(self.path.clone(), e);
+
+        let paths = if fs::metadata(&self.path).map_err(read_error)?.is_dir() {
+            let mut paths = fs::read_dir(&self.path)
+                .and_then(|entries| {
+                    entries
+                        .map(|entry| entry.map(|entry| entry.path()))
+                        .collect::<Result<Vec<PathBuf>, io::Error>>()
+                })
+                .map_err(read_error)?;
+            paths.retain(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "rules")
+            });
+            paths.sort();
+
+            paths
+        } else {
+            vec![self.path.clone()]
+        };
+
+        Ok(paths
+            .into_iter()
+            .map(|path| {
+                let stamp = fs::metadata(&path)
+                    .and_then(|metadata| Ok((metadata.modified()?, metadata.len())));
+                (path, stamp.ok())
+            })
+            .collect())
+    }
+
+    fn load(&self, files: &[(PathBuf, Option<Stamp>)]) -> Result<RuleSet, Vec<WatchError>> {
+        let mut errors = Vec::new();
+        let mut rules = RuleSet::new();
+        for (path, _) in files {
+            match fs::read_to_string(path) {
+                Ok(contents) => parse_rules(path, &contents, &mut rules, &mut errors),
+                Err(e) => errors.push(WatchError::ReadError(path.clone(), e)),
+            }
+        }
+
+        for rule_name in rules.rule_names() {
+            if let Err(e) = self.validate(&rules, rule_name) {
+                errors.push(e);
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(rules)
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn validate(&self, rules: &RuleSet, rule_name: &str) -> Result<(), WatchError> {
+        let expression = rules.resolve(rule_name)?;
+        engine::validate(&expression, &|field_name| {
+            self.field_types.get(field_name).copied()
+        })
+        .map_err(|e| suggest_field(e, self.field_types.keys().map(String::as_str)))
+        .map_err(|e| RuleError::InvalidExpressionError(String::from(rule_name), e))?;
+
+        Ok(())
+    }
+}
+
+fn parse_rules(path: &Path, contents: &str, rules: &mut RuleSet, errors: &mut Vec<WatchError>) {
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let Some((rule_name, expression)) = line.split_once('=') else {
+            errors.push(WatchError::SyntaxError(path.to_path_buf(), i + 1));
+            continue;
+        };
+        let rule_name = rule_name.trim();
+        if !is_rule_name(rule_name) {
+            errors.push(WatchError::InvalidNameError(
+                path.to_path_buf(),
+                i + 1,
+                String::from(rule_name),
+            ));
+            continue;
+        }
+        if rules.get_rule(rule_name).is_some() {
+            errors.push(WatchError::DuplicateRuleError(String::from(rule_name)));
+            continue;
+        }
+
+        match Parser::parse(expression.trim()) {
+            Ok(expression) => {
+                rules.insert(rule_name, expression);
+            }
+            Err(e) => errors.push(WatchError::ParseError(String::from(rule_name), e)),
+        }
+    }
+}
+
+// Names that can be referenced with `@name`.
+fn is_rule_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
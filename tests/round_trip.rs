@@ -0,0 +1,170 @@
+use chrono::{DateTime, Utc};
+use expression::{
+    Expression, Parser,
+    expression::{
+        And, BitwiseOperator, Computed, Literal, Not, Operation, Operator, Or, Reference,
+    },
+    schema::Value,
+};
+use proptest::prelude::*;
+
+fn field_name() -> impl Strategy<Value = String> {
+    "[a-zA-Z_][a-zA-Z_:0-9]{0,12}".prop_filter("keyword prefix", |name| {
+        let lower = name.to_ascii_lowercase();
+        !["null", "true", "false"]
+            .iter()
+            .any(|keyword| lower.starts_with(keyword))
+    })
+}
+
+fn rule_name() -> impl Strategy<Value = String> {
+    "[a-zA-Z_][a-zA-Z0-9_]{0,8}"
+}
+
+fn number() -> impl Strategy<Value = f64> {
+    prop_oneof![
+        any::<i32>().prop_map(f64::from),
+        any::<f64>().prop_filter("finite", |num| num.is_finite()),
+    ]
+}
+
+fn raw() -> impl Strategy<Value = Vec<u8>> {
+    prop::collection::vec(any::<u8>(), 1..8)
+}
+
+fn regex() -> impl Strategy<Value = String> {
+    any::<String>().prop_filter("trailing backslash", |pattern| !pattern.ends_with('\\'))
+}
+
+fn datetime() -> impl Strategy<Value = DateTime<Utc>> {
+    (0i64..4_102_444_800, 0u32..1_000_000_000)
+        .prop_map(|(secs, nanos)| DateTime::from_timestamp(secs, nanos).unwrap())
+}
+
+fn value() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        any::<String>().prop_map(Value::String),
+        regex().prop_map(Value::Regex),
+        number().prop_map(Value::Number),
+        any::<bool>().prop_map(Value::Boolean),
+        raw().prop_map(Value::Raw),
+        datetime().prop_map(Value::DateTime),
+        prop::collection::vec(any::<String>(), 1..4).prop_map(Value::StringList),
+        prop::collection::vec(number(), 1..4).prop_map(Value::NumberList),
+        prop::collection::vec(any::<bool>(), 1..4).prop_map(Value::BooleanList),
+        prop::collection::vec(raw(), 1..4).prop_map(Value::RawList),
+        prop::collection::vec(datetime(), 1..4).prop_map(Value::DateTimeList),
+        Just(Value::Null),
+    ]
+}
+
+fn bitwise_operator() -> impl Strategy<Value = BitwiseOperator> {
+    prop_oneof![
+        Just(BitwiseOperator::And),
+        Just(BitwiseOperator::Or),
+        Just(BitwiseOperator::Xor),
+        Just(BitwiseOperator::ShiftLeft),
+        Just(BitwiseOperator::ShiftRight),
+    ]
+}
+
+// Computed operands as the parser builds them, with chains of operators of
+// one precedence.
+fn literal() -> impl Strategy<Value = Literal> {
+    let leaf = prop_oneof![
+        value().prop_map(Literal::LiteralValue),
+        field_name().prop_map(|field_name| Literal::LiteralField(field_name.into())),
+        field_name().prop_map(|field_name| Literal::LiteralAge(field_name.into())),
+    ];
+
+    leaf.prop_recursive(2, 8, 3, |inner| {
+        let bitwise = (
+            inner.clone(),
+            prop::collection::vec((bitwise_operator(), inner.clone()), 1..3),
+        )
+            .prop_map(|(first, mut rest)| {
+                let first_op = rest[0].0;
+                for (op, _) in &mut rest {
+                    if op.precedence() != first_op.precedence() {
+                        *op = first_op;
+                    }
+                }
+
+                Computed::Bitwise(first, rest)
+            });
+        let concat = prop::collection::vec(inner.clone(), 2..4).prop_map(Computed::Concat);
+        let coalesce = prop::collection::vec(inner.clone(), 2..4).prop_map(Computed::Coalesce);
+        let conditional = (
+            inner.clone(),
+            operator(),
+            inner.clone(),
+            inner.clone(),
+            inner.clone(),
+        )
+            .prop_map(|(lhs, op, rhs, then, otherwise)| {
+                let condition = Expression::Operation(Operation::new(lhs, op, rhs));
+
+                Computed::If(condition, then, otherwise)
+            });
+        let first = inner.clone().prop_map(Computed::First);
+        let last = inner.clone().prop_map(Computed::Last);
+        let slice = (inner, any::<usize>(), any::<usize>())
+            .prop_map(|(list, start, end)| Computed::Slice(list, start, end));
+
+        prop_oneof![bitwise, concat, coalesce, conditional, first, last, slice]
+            .prop_map(|computed| Literal::LiteralComputed(Box::new(computed)))
+    })
+}
+
+fn operator() -> impl Strategy<Value = Operator> {
+    prop_oneof![
+        Just(Operator::Eq),
+        Just(Operator::Ne),
+        Just(Operator::Gt),
+        Just(Operator::Gte),
+        Just(Operator::Lt),
+        Just(Operator::Lte),
+        Just(Operator::In),
+    ]
+}
+
+fn expression() -> impl Strategy<Value = Expression> {
+    let operation = (literal(), operator(), literal())
+        .prop_map(|(lhs, op, rhs)| Expression::Operation(Operation::new(lhs, op, rhs)));
+    let reference = rule_name().prop_map(|name| Expression::Reference(Reference::new(&name)));
+
+    prop_oneof![4 => operation, 1 => reference].prop_recursive(4, 32, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 2..4).prop_map(|e| Expression::And(And::new(e))),
+            prop::collection::vec(inner.clone(), 2..4).prop_map(|e| Expression::Or(Or::new(e))),
+            inner.prop_map(|e| Expression::Not(Not::new(e))),
+        ]
+    })
+}
+
+proptest! {
+    #[test]
+    fn serialize_round_trips(expression in expression()) {
+        let serialized = expression.serialize();
+        let parsed = Parser::parse(&serialized)
+            .unwrap_or_else(|e| panic!("failed to reparse {serialized}: {e}"));
+
+        prop_assert_eq!(parsed.to_bytes(), expression.to_bytes(), "{}", serialized);
+    }
+
+    #[test]
+    fn minify_round_trips(expression in expression()) {
+        let minified = expression.minify();
+        let parsed = Parser::parse(&minified)
+            .unwrap_or_else(|e| panic!("failed to reparse {minified}: {e}"));
+
+        prop_assert_eq!(parsed.to_bytes(), expression.to_bytes(), "{}", minified);
+    }
+
+    #[test]
+    fn binary_round_trips(expression in expression()) {
+        let decoded = Expression::from_bytes(&expression.to_bytes()).unwrap();
+
+        prop_assert_eq!(decoded.to_bytes(), expression.to_bytes());
+    }
+}